@@ -0,0 +1,140 @@
+//! Abfrage des Audit-Logs (`log`): filtert die JSONL-Ereignisse nach Zeitraum, ID, Kamera und Entscheidung
+//! und gibt die passenden Zeilen unverändert aus
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use uuid::Uuid;
+
+/// Pseudonym einer ID in einem Audit-Log mit `--audit-salt-file`; dasselbe Salz ergibt denselben Hash,
+/// damit sich die Ereignisse einer Person weiterhin zuordnen lassen
+pub fn hashed_id(salt: &Uuid, id: &str) -> String {
+    Uuid::new_v5(salt, id.as_bytes()).to_string()
+}
+
+/// Liest einen Zeitpunkt, entweder als Datum (`2024-01-01`, Mitternacht Ortszeit) oder nach RFC 3339
+pub fn parse_time(value: &str) -> Result<DateTime<Local>, String> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Local));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("ungültiger Zeitpunkt {value}, erwartet z. B. 2024-01-01 oder 2024-01-01T08:00:00+01:00"))?;
+    Local
+        .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .ok_or_else(|| format!("{value} gibt es in der Ortszeit nicht"))
+}
+
+pub struct Filter {
+    pub since: Option<DateTime<Local>>,
+    pub until: Option<DateTime<Local>>,
+    /// Gesuchte IDs; bei gehashten Logs zusätzlich der Hash der ID
+    pub ids: Vec<String>,
+    pub camera: Option<String>,
+    pub denied_only: bool,
+}
+
+impl Filter {
+    pub fn matches(&self, event: &Value) -> bool {
+        let timestamp = event["timestamp"]
+            .as_str()
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok());
+        if self.since.is_some() || self.until.is_some() {
+            let Some(timestamp) = timestamp else {
+                return false;
+            };
+            if self.since.is_some_and(|since| timestamp < since) || self.until.is_some_and(|until| timestamp >= until) {
+                return false;
+            }
+        }
+        if !self.ids.is_empty() && !event["id"].as_str().is_some_and(|id| self.ids.iter().any(|wanted| wanted == id)) {
+            return false;
+        }
+        if self.camera.as_deref().is_some_and(|camera| event["camera"].as_str() != Some(camera)) {
+            return false;
+        }
+        // Nur getroffene Entscheidungen; zurückgestellte Gesichter und Leerlaufmeldungen zählen nicht
+        if self.denied_only && (event["allowed"].as_bool() != Some(false) || event["deferred"].as_bool() == Some(true)) {
+            return false;
+        }
+        true
+    }
+}
+
+/// Gibt alle passenden Ereignisse aus und liefert ihre Anzahl
+pub fn print_matching(path: &str, filter: &Filter) -> Result<usize, String> {
+    let file = File::open(path).map_err(|e| format!("{path} konnte nicht gelesen werden: {e}"))?;
+    let mut count = 0;
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| format!("{path} konnte nicht gelesen werden: {e}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<Value>(&line) {
+            Ok(event) if filter.matches(&event) => {
+                println!("{line}");
+                count += 1;
+            }
+            Ok(_) => {}
+            Err(e) => eprintln!("Warnung: Zeile {} in {path} ist ungültig: {e}", number + 1),
+        }
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn filter() -> Filter {
+        Filter {
+            since: None,
+            until: None,
+            ids: Vec::new(),
+            camera: None,
+            denied_only: false,
+        }
+    }
+
+    fn event(timestamp: &str, id: &str, camera: &str, allowed: bool) -> Value {
+        json!({ "timestamp": timestamp, "id": id, "camera": camera, "allowed": allowed })
+    }
+
+    #[test]
+    fn time_range_includes_since_and_excludes_until() {
+        let filter = Filter {
+            since: Some(parse_time("2024-05-01T08:00:00+00:00").unwrap()),
+            until: Some(parse_time("2024-05-01T09:00:00+00:00").unwrap()),
+            ..filter()
+        };
+        assert!(filter.matches(&event("2024-05-01T08:00:00+00:00", "a", "0", true)));
+        assert!(filter.matches(&event("2024-05-01T08:59:59+00:00", "a", "0", true)));
+        assert!(!filter.matches(&event("2024-05-01T09:00:00+00:00", "a", "0", true)));
+        assert!(!filter.matches(&event("2024-05-01T07:59:59+00:00", "a", "0", true)));
+        assert!(!filter.matches(&json!({ "id": "a" })), "ohne Zeitstempel passt kein Zeitraum");
+    }
+
+    #[test]
+    fn ids_camera_and_denied_only_narrow_the_events() {
+        let salt = Uuid::new_v4();
+        let filter = Filter {
+            ids: vec!["a".to_string(), hashed_id(&salt, "a")],
+            camera: Some("1".to_string()),
+            denied_only: true,
+            ..filter()
+        };
+        let timestamp = "2024-05-01T08:00:00+00:00";
+        assert!(filter.matches(&event(timestamp, "a", "1", false)));
+        assert!(filter.matches(&event(timestamp, &hashed_id(&salt, "a"), "1", false)), "gehashtes Log");
+        assert!(!filter.matches(&event(timestamp, &hashed_id(&Uuid::new_v4(), "a"), "1", false)));
+        assert!(!filter.matches(&event(timestamp, "b", "1", false)));
+        assert!(!filter.matches(&event(timestamp, "a", "0", false)));
+        assert!(!filter.matches(&event(timestamp, "a", "1", true)));
+        let mut deferred = event(timestamp, "a", "1", false);
+        deferred["deferred"] = json!(true);
+        assert!(!filter.matches(&deferred), "zurückgestellte Gesichter sind keine Abweisung");
+        assert!(self::filter().matches(&deferred), "ohne Bedingungen passt jedes Ereignis");
+    }
+}
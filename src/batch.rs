@@ -0,0 +1,152 @@
+//! Stapelverarbeitung mehrerer Videodateien nach einer Manifestdatei. Jede Datei läuft als eigener
+//! Prozess im Videomodus mit Ergebnisdatei; ein Fehler in einer Datei beendet nicht den ganzen Stapel.
+//! Am Ende werden die Ergebnisdateien zu einer Übersicht aller Treffer zusammengefasst.
+//!
+//! Manifest: `{"args": [...], "videos": [{"path": "clip.mp4", "results": "clip.jsonl", "args": [...]}]}`;
+//! `args` sind zusätzliche Optionen der Erkennung (gemeinsam bzw. je Datei), `results` ist optional.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+
+#[derive(Deserialize)]
+pub struct Manifest {
+    /// Optionen für alle Dateien
+    #[serde(default)]
+    pub args: Vec<String>,
+    pub videos: Vec<Video>,
+}
+
+#[derive(Deserialize)]
+pub struct Video {
+    pub path: String,
+    /// Ergebnisdatei; Standard ist `<path>.results.jsonl`
+    pub results: Option<String>,
+    #[serde(default)]
+    pub args: Vec<String>,
+}
+
+impl Video {
+    pub fn results_path(&self) -> String {
+        self.results.clone().unwrap_or_else(|| format!("{}.results.jsonl", self.path))
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &str) -> Result<Self, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{path} konnte nicht gelesen werden: {e}"))?;
+        serde_json::from_str(&content).map_err(|e| format!("{path} ist ungültig: {e}"))
+    }
+}
+
+/// Die für die Übersicht nötigen Felder einer Zeile der Ergebnisdatei
+#[derive(Deserialize)]
+struct ResultLine {
+    timestamp_ms: f64,
+    faces: Vec<ResultFace>,
+}
+
+#[derive(Deserialize)]
+struct ResultFace {
+    id: Option<String>,
+    allowed: bool,
+    #[serde(default)]
+    deferred: bool,
+}
+
+/// Auftreten einer ID in einer Datei, Zeiten in Millisekunden ab Videobeginn
+#[derive(Serialize, Clone)]
+pub struct Sighting {
+    pub count: u64,
+    pub first_ms: f64,
+    pub last_ms: f64,
+}
+
+#[derive(Serialize)]
+pub struct FileSummary {
+    pub path: String,
+    pub results: String,
+    /// Fehlerbeschreibung, wenn die Datei nicht vollständig verarbeitet wurde
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub frames: u64,
+    pub faces: u64,
+    pub allowed: u64,
+    pub denied: u64,
+    pub matches: BTreeMap<String, Sighting>,
+}
+
+impl FileSummary {
+    /// Fasst die Ergebnisdatei zusammen; auch nach einem Fehler, soweit sie geschrieben wurde
+    pub fn read(path: &str, results: &str, error: Option<String>) -> Self {
+        let mut summary = Self {
+            path: path.to_string(),
+            results: results.to_string(),
+            error,
+            frames: 0,
+            faces: 0,
+            allowed: 0,
+            denied: 0,
+            matches: BTreeMap::new(),
+        };
+        let Ok(file) = File::open(results) else {
+            return summary;
+        };
+        for line in BufReader::new(file).lines().map_while(Result::ok) {
+            let Ok(line) = serde_json::from_str::<ResultLine>(&line) else {
+                continue;
+            };
+            summary.frames += 1;
+            for face in line.faces.iter().filter(|face| !face.deferred) {
+                summary.faces += 1;
+                if face.allowed {
+                    summary.allowed += 1;
+                } else {
+                    summary.denied += 1;
+                }
+                if let Some(id) = &face.id {
+                    let sighting = summary.matches.entry(id.clone()).or_insert(Sighting {
+                        count: 0,
+                        first_ms: line.timestamp_ms,
+                        last_ms: line.timestamp_ms,
+                    });
+                    sighting.count += 1;
+                    sighting.first_ms = sighting.first_ms.min(line.timestamp_ms);
+                    sighting.last_ms = sighting.last_ms.max(line.timestamp_ms);
+                }
+            }
+        }
+        summary
+    }
+}
+
+/// Auftreten einer ID in einer der Dateien
+#[derive(Serialize)]
+pub struct FileSighting {
+    pub path: String,
+    #[serde(flatten)]
+    pub sighting: Sighting,
+}
+
+#[derive(Serialize)]
+pub struct BatchSummary {
+    pub files: Vec<FileSummary>,
+    /// Alle erkannten IDs über alle Dateien
+    pub matches: BTreeMap<String, Vec<FileSighting>>,
+}
+
+impl BatchSummary {
+    pub fn new(files: Vec<FileSummary>) -> Self {
+        let mut matches: BTreeMap<String, Vec<FileSighting>> = BTreeMap::new();
+        for file in &files {
+            for (id, sighting) in &file.matches {
+                matches.entry(id.clone()).or_default().push(FileSighting {
+                    path: file.path.clone(),
+                    sighting: sighting.clone(),
+                });
+            }
+        }
+        Self { files, matches }
+    }
+}
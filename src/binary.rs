@@ -0,0 +1,330 @@
+//! Binäres Datenbankformat (bincode): lädt große Galerien mit hochdimensionalen Embeddings deutlich
+//! schneller und ist kleiner als JSON. Gewählt wird es über die Dateiendung `.bin`.
+
+use crate::pca::Projection;
+use crate::{AccessLevel, Database, FaceEntry};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Kennung am Dateianfang, damit fremde oder beschädigte Dateien nicht als Datenbank gelesen werden
+const MAGIC: &[u8; 8] = b"FACERDB5";
+/// Frühere Fassungen werden weiterhin gelesen: ohne Ausschnitte je Aufnahme, zusätzlich ohne Projektion,
+/// ohne PIN-Hash bzw. auch ohne Modell im Kopf
+const MAGIC_V4: &[u8; 8] = b"FACERDB4";
+const MAGIC_V3: &[u8; 8] = b"FACERDB3";
+const MAGIC_V2: &[u8; 8] = b"FACERDB2";
+const MAGIC_V1: &[u8; 8] = b"FACERDB1";
+
+/// Wählt das Format anhand der Dateiendung
+pub fn is_binary(path: &str) -> bool {
+    Path::new(path).extension().is_some_and(|extension| extension == "bin")
+}
+
+// bincode unterstützt keine unbestimmten Formate; die Altformate, die `FaceEntry` beim Lesen von JSON
+// akzeptiert, gibt es hier nicht. Daher eigene Abbilder mit festem Aufbau.
+#[derive(Serialize)]
+struct EntryRef<'a> {
+    id: &'a str,
+    embeddings: &'a [Vec<f32>],
+    access: AccessLevel,
+    crop: &'a Option<String>,
+    crops: &'a [Option<String>],
+    needs_reenrollment: bool,
+    name: &'a Option<String>,
+    valid_until: &'a Option<DateTime<Local>>,
+    last_seen: &'a Option<DateTime<Local>>,
+    match_count: u64,
+    notes: &'a Option<String>,
+    pin_hash: &'a Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    id: String,
+    embeddings: Vec<Vec<f32>>,
+    access: AccessLevel,
+    crop: Option<String>,
+    crops: Vec<Option<String>>,
+    needs_reenrollment: bool,
+    name: Option<String>,
+    valid_until: Option<DateTime<Local>>,
+    last_seen: Option<DateTime<Local>>,
+    match_count: u64,
+    notes: Option<String>,
+    pin_hash: Option<String>,
+}
+
+/// Eintrag der Fassungen 3 und 4, vor den Ausschnitten je Aufnahme
+#[derive(Deserialize)]
+struct PinEntry {
+    id: String,
+    embeddings: Vec<Vec<f32>>,
+    access: AccessLevel,
+    crop: Option<String>,
+    needs_reenrollment: bool,
+    name: Option<String>,
+    valid_until: Option<DateTime<Local>>,
+    last_seen: Option<DateTime<Local>>,
+    match_count: u64,
+    notes: Option<String>,
+    pin_hash: Option<String>,
+}
+
+impl From<PinEntry> for Entry {
+    fn from(entry: PinEntry) -> Self {
+        Self {
+            id: entry.id,
+            embeddings: entry.embeddings,
+            access: entry.access,
+            crop: entry.crop,
+            crops: Vec::new(),
+            needs_reenrollment: entry.needs_reenrollment,
+            name: entry.name,
+            valid_until: entry.valid_until,
+            last_seen: entry.last_seen,
+            match_count: entry.match_count,
+            notes: entry.notes,
+            pin_hash: entry.pin_hash,
+        }
+    }
+}
+
+/// Eintrag der Fassungen 1 und 2, vor dem PIN-Hash
+#[derive(Deserialize)]
+struct LegacyEntry {
+    id: String,
+    embeddings: Vec<Vec<f32>>,
+    access: AccessLevel,
+    crop: Option<String>,
+    needs_reenrollment: bool,
+    name: Option<String>,
+    valid_until: Option<DateTime<Local>>,
+    last_seen: Option<DateTime<Local>>,
+    match_count: u64,
+    notes: Option<String>,
+}
+
+impl From<LegacyEntry> for Entry {
+    fn from(entry: LegacyEntry) -> Self {
+        Self {
+            id: entry.id,
+            embeddings: entry.embeddings,
+            access: entry.access,
+            crop: entry.crop,
+            crops: Vec::new(),
+            needs_reenrollment: entry.needs_reenrollment,
+            name: entry.name,
+            valid_until: entry.valid_until,
+            last_seen: entry.last_seen,
+            match_count: entry.match_count,
+            notes: entry.notes,
+            pin_hash: None,
+        }
+    }
+}
+
+/// Inhalt nach der Kennung: Dimension, Modell, Projektion und Einträge
+type Contents = (Option<usize>, Option<String>, Option<Projection>, Vec<Entry>);
+/// Inhalt der Fassung 4
+type PinContents = (Option<usize>, Option<String>, Option<Projection>, Vec<PinEntry>);
+/// Inhalt der Fassungen 2 und 3
+type LegacyContents<E> = (Option<usize>, Option<String>, Vec<E>);
+
+/// Kodiert die Einträge samt Dimension, Modell und Projektion im Kopf
+pub fn encode(faces: &[FaceEntry], model: Option<&str>, projection: Option<&Projection>) -> Vec<u8> {
+    let entries: Vec<EntryRef> = faces
+        .iter()
+        .map(|face| EntryRef {
+            id: &face.id,
+            embeddings: &face.embeddings,
+            access: face.access,
+            crop: &face.crop,
+            crops: &face.crops,
+            needs_reenrollment: face.needs_reenrollment,
+            name: &face.name,
+            valid_until: &face.valid_until,
+            last_seen: &face.last_seen,
+            match_count: face.match_count,
+            notes: &face.notes,
+            pin_hash: &face.pin_hash,
+        })
+        .collect();
+    let dimension = faces.first().and_then(FaceEntry::dimension);
+    let mut bytes = MAGIC.to_vec();
+    bincode::serde::encode_into_std_write((dimension, model, projection, entries), &mut bytes, bincode::config::standard())
+        .expect("Fehler beim Serialisieren");
+    bytes
+}
+
+pub fn decode(bytes: &[u8]) -> Result<Database, String> {
+    let config = bincode::config::standard();
+    let (dimension, model, projection, entries) = if let Some(payload) = bytes.strip_prefix(MAGIC) {
+        let (contents, _): (Contents, usize) =
+            bincode::serde::decode_from_slice(payload, config).map_err(|e| e.to_string())?;
+        contents
+    } else if let Some(payload) = bytes.strip_prefix(MAGIC_V4) {
+        let ((dimension, model, projection, entries), _): (PinContents, usize) =
+            bincode::serde::decode_from_slice(payload, config).map_err(|e| e.to_string())?;
+        (dimension, model, projection, entries.into_iter().map(Entry::from).collect())
+    } else if let Some(payload) = bytes.strip_prefix(MAGIC_V3) {
+        let ((dimension, model, entries), _): (LegacyContents<PinEntry>, usize) =
+            bincode::serde::decode_from_slice(payload, config).map_err(|e| e.to_string())?;
+        (dimension, model, None, entries.into_iter().map(Entry::from).collect())
+    } else if let Some(payload) = bytes.strip_prefix(MAGIC_V2) {
+        let ((dimension, model, entries), _): (LegacyContents<LegacyEntry>, usize) =
+            bincode::serde::decode_from_slice(payload, config).map_err(|e| e.to_string())?;
+        (dimension, model, None, entries.into_iter().map(Entry::from).collect())
+    } else if let Some(payload) = bytes.strip_prefix(MAGIC_V1) {
+        let ((dimension, entries), _): ((Option<usize>, Vec<LegacyEntry>), usize) =
+            bincode::serde::decode_from_slice(payload, config).map_err(|e| e.to_string())?;
+        (dimension, None, None, entries.into_iter().map(Entry::from).collect())
+    } else {
+        return Err("keine binäre Gesichtsdatenbank (Kennung fehlt)".to_string());
+    };
+    let faces = entries
+        .into_iter()
+        .map(|entry| FaceEntry {
+            id: entry.id,
+            embeddings: entry.embeddings,
+            access: entry.access,
+            crop: entry.crop,
+            crops: entry.crops,
+            needs_reenrollment: entry.needs_reenrollment,
+            name: entry.name,
+            valid_until: entry.valid_until,
+            last_seen: entry.last_seen,
+            match_count: entry.match_count,
+            notes: entry.notes,
+            pin_hash: entry.pin_hash,
+        })
+        .collect();
+    Ok(Database {
+        dimension,
+        model,
+        projection,
+        faces,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::face_entry;
+    use proptest::prelude::*;
+
+    /// Eintrag der Fassungen 3 und 4 zum Schreiben, ohne Ausschnitte je Aufnahme
+    #[derive(Serialize)]
+    struct PinEntryRef<'a> {
+        id: &'a str,
+        embeddings: &'a [Vec<f32>],
+        access: AccessLevel,
+        crop: &'a Option<String>,
+        needs_reenrollment: bool,
+        name: &'a Option<String>,
+        valid_until: &'a Option<DateTime<Local>>,
+        last_seen: &'a Option<DateTime<Local>>,
+        match_count: u64,
+        notes: &'a Option<String>,
+        pin_hash: &'a Option<String>,
+    }
+
+    /// Eintrag der Fassungen 1 und 2 zum Schreiben, ohne PIN-Hash
+    #[derive(Serialize)]
+    struct LegacyEntryRef<'a> {
+        id: &'a str,
+        embeddings: &'a [Vec<f32>],
+        access: AccessLevel,
+        crop: &'a Option<String>,
+        needs_reenrollment: bool,
+        name: &'a Option<String>,
+        valid_until: &'a Option<DateTime<Local>>,
+        last_seen: &'a Option<DateTime<Local>>,
+        match_count: u64,
+        notes: &'a Option<String>,
+    }
+
+    fn pin_entry_ref(face: &FaceEntry) -> PinEntryRef<'_> {
+        PinEntryRef {
+            id: &face.id,
+            embeddings: &face.embeddings,
+            access: face.access,
+            crop: &face.crop,
+            needs_reenrollment: face.needs_reenrollment,
+            name: &face.name,
+            valid_until: &face.valid_until,
+            last_seen: &face.last_seen,
+            match_count: face.match_count,
+            notes: &face.notes,
+            pin_hash: &face.pin_hash,
+        }
+    }
+
+    fn legacy_entry_ref(face: &FaceEntry) -> LegacyEntryRef<'_> {
+        LegacyEntryRef {
+            id: &face.id,
+            embeddings: &face.embeddings,
+            access: face.access,
+            crop: &face.crop,
+            needs_reenrollment: face.needs_reenrollment,
+            name: &face.name,
+            valid_until: &face.valid_until,
+            last_seen: &face.last_seen,
+            match_count: face.match_count,
+            notes: &face.notes,
+        }
+    }
+
+    fn with_magic(magic: &[u8; 8], contents: impl Serialize) -> Vec<u8> {
+        let mut bytes = magic.to_vec();
+        bincode::serde::encode_into_std_write(contents, &mut bytes, bincode::config::standard()).unwrap();
+        bytes
+    }
+
+    proptest! {
+        #[test]
+        fn encode_decode_round_trip(faces in prop::collection::vec(face_entry(), 0..5)) {
+            let projection = Projection { components: vec![vec![1.0, 0.0], vec![0.0, 1.0]] };
+            let database = decode(&encode(&faces, Some("modell"), Some(&projection))).unwrap();
+            prop_assert_eq!(database.dimension, faces.first().and_then(FaceEntry::dimension));
+            prop_assert_eq!(database.model.as_deref(), Some("modell"));
+            prop_assert_eq!(database.projection, Some(projection));
+            prop_assert_eq!(database.faces, faces);
+        }
+
+        #[test]
+        fn reads_earlier_versions(faces in prop::collection::vec(face_entry(), 1..4)) {
+            let dimension = faces[0].dimension();
+            let model = Some("modell".to_string());
+            let projection = Some(Projection { components: vec![vec![1.0]] });
+            let without_crops: Vec<FaceEntry> =
+                faces.iter().cloned().map(|face| FaceEntry { crops: Vec::new(), ..face }).collect();
+            let without_pin: Vec<FaceEntry> =
+                without_crops.iter().cloned().map(|face| FaceEntry { pin_hash: None, ..face }).collect();
+
+            let entries: Vec<PinEntryRef> = faces.iter().map(pin_entry_ref).collect();
+            let v4 = decode(&with_magic(MAGIC_V4, (dimension, &model, &projection, &entries))).unwrap();
+            prop_assert_eq!(&v4.projection, &projection);
+            prop_assert_eq!(&v4.faces, &without_crops);
+
+            let v3 = decode(&with_magic(MAGIC_V3, (dimension, &model, &entries))).unwrap();
+            prop_assert_eq!((v3.dimension, v3.model.as_ref(), v3.projection), (dimension, model.as_ref(), None));
+            prop_assert_eq!(&v3.faces, &without_crops);
+
+            let legacy: Vec<LegacyEntryRef> = faces.iter().map(legacy_entry_ref).collect();
+            let v2 = decode(&with_magic(MAGIC_V2, (dimension, &model, &legacy))).unwrap();
+            prop_assert_eq!(v2.model.as_ref(), model.as_ref());
+            prop_assert_eq!(&v2.faces, &without_pin);
+
+            let v1 = decode(&with_magic(MAGIC_V1, (dimension, &legacy))).unwrap();
+            prop_assert_eq!((v1.dimension, v1.model), (dimension, None));
+            prop_assert_eq!(&v1.faces, &without_pin);
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_files() {
+        assert!(decode(b"{\"faces\": []}").is_err());
+        assert!(decode(b"FACERDB5\xff\xff").is_err());
+    }
+}
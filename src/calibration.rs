@@ -0,0 +1,80 @@
+//! Bestimmung des Schwellwerts aus beschrifteten Bildpaaren
+
+use crate::rates::{f1, ratio};
+
+/// Kennzahlen eines Schwellwerts; als gleich gilt ein Paar mit Ähnlichkeit > `threshold`
+pub struct Calibration {
+    pub threshold: f32,
+    pub precision: f64,
+    pub recall: f64,
+    pub f1: f64,
+    /// Anteil verschiedener Personen, die fälschlich als gleich gelten
+    pub false_accept_rate: f64,
+    /// Anteil gleicher Personen, die fälschlich abgewiesen werden
+    pub false_reject_rate: f64,
+}
+
+impl Calibration {
+    fn evaluate(pairs: &[(f32, bool)], threshold: f32) -> Self {
+        let (mut tp, mut fp, mut fn_, mut tn) = (0usize, 0usize, 0usize, 0usize);
+        for &(similarity, same) in pairs {
+            match (similarity > threshold, same) {
+                (true, true) => tp += 1,
+                (true, false) => fp += 1,
+                (false, true) => fn_ += 1,
+                (false, false) => tn += 1,
+            }
+        }
+        let precision = ratio(tp, fp);
+        let recall = ratio(tp, fn_);
+        Self {
+            threshold,
+            precision,
+            recall,
+            f1: f1(precision, recall),
+            false_accept_rate: ratio(fp, tn),
+            false_reject_rate: ratio(fn_, tp),
+        }
+    }
+}
+
+/// Wählt den Schwellwert mit dem besten F1-Wert, oder mit `target_far` den niedrigsten Schwellwert, dessen
+/// Falschakzeptanzrate das Ziel einhält. Geprüft werden alle vorkommenden Ähnlichkeiten.
+/// `pairs` enthält je Paar die Ähnlichkeit und ob es dieselbe Person ist.
+pub fn calibrate(pairs: &[(f32, bool)], target_far: Option<f64>) -> Option<Calibration> {
+    let mut thresholds: Vec<f32> = pairs.iter().map(|&(similarity, _)| similarity).collect();
+    thresholds.sort_by(f32::total_cmp);
+    thresholds.dedup();
+    let candidates = thresholds.into_iter().map(|threshold| Calibration::evaluate(pairs, threshold));
+    match target_far {
+        Some(target) => candidates
+            .filter(|calibration| calibration.false_accept_rate <= target)
+            .min_by(|a, b| a.threshold.total_cmp(&b.threshold)),
+        None => candidates.max_by(|a, b| a.f1.total_cmp(&b.f1).then(b.threshold.total_cmp(&a.threshold))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRS: [(f32, bool); 6] = [(0.9, true), (0.8, true), (0.7, false), (0.6, true), (0.4, false), (0.2, false)];
+
+    #[test]
+    fn best_f1_threshold() {
+        let calibration = calibrate(&PAIRS, None).unwrap();
+        // Über 0.4: alle drei gleichen Paare, dazu ein verschiedenes als gleich
+        assert_eq!(calibration.threshold, 0.4);
+        assert_eq!((calibration.precision, calibration.recall), (0.75, 1.0));
+        assert_eq!(calibration.false_accept_rate, 1.0 / 3.0);
+        assert_eq!(calibration.false_reject_rate, 0.0);
+    }
+
+    #[test]
+    fn lowest_threshold_within_the_target_far() {
+        let calibration = calibrate(&PAIRS, Some(0.0)).unwrap();
+        assert_eq!(calibration.threshold, 0.7);
+        assert_eq!(calibration.recall, 2.0 / 3.0);
+        assert!(calibrate(&[], None).is_none());
+    }
+}
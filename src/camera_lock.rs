@@ -0,0 +1,60 @@
+//! Sperrdatei je Kameraindex, damit eine zweite Instanz nicht dieselbe Kamera öffnet. Kameras lassen sich
+//! meist nur exklusiv nutzen; ohne Sperre scheitert die zweite Instanz erst später mit unklaren Fehlern.
+
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::Write;
+
+/// Hält eine Dateisperre (`flock`) bis zum Ende des Laufs. Das Betriebssystem gibt sie auch frei, wenn die
+/// Instanz abstürzt; eine verwaiste Sperrdatei blockiert daher nie. Die Datei selbst bleibt liegen: Wer sie
+/// löscht, während eine andere Instanz sie schon geöffnet hat, hebt die Sperre für die nächste auf.
+pub struct CameraLock {
+    _file: File,
+}
+
+impl CameraLock {
+    /// Die PID in der Datei dient nur der Fehlermeldung
+    pub fn acquire(index: i32) -> Result<Self, String> {
+        let path = std::env::temp_dir().join(format!("facerec-kamera-{index}.lock"));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .map_err(|e| format!("Sperrdatei {} konnte nicht angelegt werden: {e}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let owner = std::fs::read_to_string(&path).ok().filter(|pid| !pid.trim().is_empty());
+                let owner = owner.map_or(String::new(), |pid| format!(" (PID {})", pid.trim()));
+                return Err(format!(
+                    "Kamera {index} wird bereits von facerec{owner} verwendet; Sperrdatei {}",
+                    path.display()
+                ));
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(format!("Sperrdatei {} konnte nicht gesperrt werden: {e}", path.display()));
+            }
+        }
+        file.set_len(0)
+            .and_then(|()| write!(file, "{}", std::process::id()))
+            .map_err(|e| format!("Sperrdatei {} nicht beschreibbar: {e}", path.display()))?;
+        Ok(Self { _file: file })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_held_lock_blocks_until_it_is_released() {
+        let index = -(std::process::id() as i32);
+        let lock = CameraLock::acquire(index).unwrap();
+        let error = CameraLock::acquire(index).err().unwrap();
+        assert!(error.contains(&format!("PID {}", std::process::id())), "{error}");
+        drop(lock);
+        assert!(CameraLock::acquire(index).is_ok(), "die Sperrdatei bleibt liegen, blockiert aber nicht");
+        std::fs::remove_file(std::env::temp_dir().join(format!("facerec-kamera-{index}.lock"))).unwrap();
+    }
+}
@@ -0,0 +1,81 @@
+//! Übergabe der Frames vom Aufnahme-Thread an die Verarbeitung
+
+use chrono::{DateTime, Local};
+use opencv::core::Mat;
+use std::sync::{Condvar, Mutex};
+use std::time::Instant;
+
+/// Ein aufgenommener Frame samt Aufnahmezeitpunkt
+pub struct Captured {
+    pub frame: Mat,
+    pub captured: Instant,
+    pub captured_at: DateTime<Local>,
+    /// Position in der Videodatei; None bei Live-Kameras
+    pub media_ms: Option<f64>,
+}
+
+/// Beschränkter Kanal mit Platz für genau einen Frame. Live-Quellen ersetzen einen noch nicht abgeholten
+/// Frame durch den neueren, sodass die Verarbeitung stets den frischesten erhält; Videodateien warten,
+/// bis der vorige Frame abgeholt wurde, damit keiner verloren geht.
+pub struct FrameSlot {
+    state: Mutex<SlotState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct SlotState {
+    frame: Option<Captured>,
+    closed: bool,
+}
+
+/// Ergebnis der Übergabe eines Frames
+pub enum Put {
+    Stored,
+    /// Ein nicht abgeholter älterer Frame wurde verworfen
+    ReplacedStale,
+    /// Die Gegenseite hat den Kanal geschlossen
+    Closed,
+}
+
+impl FrameSlot {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(SlotState::default()),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Legt einen Frame ab; mit `replace` wird ein wartender älterer Frame verworfen, sonst wird gewartet
+    pub fn put(&self, captured: Captured, replace: bool) -> Put {
+        let mut state = self.state.lock().unwrap();
+        if !replace {
+            state = self
+                .changed
+                .wait_while(state, |state| state.frame.is_some() && !state.closed)
+                .unwrap();
+        }
+        if state.closed {
+            return Put::Closed;
+        }
+        let stale = state.frame.replace(captured).is_some();
+        self.changed.notify_all();
+        if stale { Put::ReplacedStale } else { Put::Stored }
+    }
+
+    /// Wartet auf den nächsten Frame; None, sobald der Kanal geschlossen und leer ist
+    pub fn take(&self) -> Option<Captured> {
+        let mut state = self
+            .changed
+            .wait_while(self.state.lock().unwrap(), |state| state.frame.is_none() && !state.closed)
+            .unwrap();
+        let frame = state.frame.take();
+        self.changed.notify_all();
+        frame
+    }
+
+    /// Schließt den Kanal für beide Seiten
+    pub fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_all();
+    }
+}
@@ -0,0 +1,73 @@
+//! Gruppierung von Embeddings nach Ähnlichkeit, z. B. um unbekannte Personen vor der Erfassung zu sichten
+
+use crate::matching::cosine_similarity;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Agglomeratives Clustering mit mittlerer Verknüpfung: Es werden so lange die beiden Gruppen mit der
+/// höchsten mittleren Kosinus-Ähnlichkeit zusammengelegt, bis keine zwei Gruppen mehr über `threshold` liegen.
+/// Liefert die Indizes je Gruppe, größte Gruppe zuerst.
+pub fn agglomerative(embeddings: &[Vec<f32>], threshold: f32) -> Vec<Vec<usize>> {
+    agglomerative_by(embeddings.len(), |i, j| cosine_similarity(&embeddings[i], &embeddings[j]), threshold)
+}
+
+/// Wie `agglomerative`, mit beliebiger Ähnlichkeit zwischen den Elementen `0..n`.
+///
+/// Ein Mittelwert liegt nie über dem größten seiner Summanden, und beim Zusammenlegen mittelt sich die
+/// Verknüpfung zu einer dritten Gruppe aus den beiden bisherigen. Daher kommen nur Gruppen in Frage, die schon
+/// über `threshold` verknüpft sind; gemerkt wird nur deren Ähnlichkeitssumme, bei wenigen Duplikaten also
+/// kaum mehr als die Gruppen selbst statt einer vollen n×n-Matrix. Jedes Paar wird anfangs einmal verglichen.
+pub fn agglomerative_by(n: usize, pair_similarity: impl Fn(usize, usize) -> f32, threshold: f32) -> Vec<Vec<usize>> {
+    // Gruppen heißen nach ihrem ersten Element; `sums` führt die Summe aller Paarähnlichkeiten je Gruppenpaar
+    let mut members: Vec<Option<Vec<usize>>> = (0..n).map(|i| Some(vec![i])).collect();
+    let mut sums: BTreeMap<(usize, usize), f32> = BTreeMap::new();
+    for (i, j) in (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))) {
+        let value = pair_similarity(i, j);
+        if value > threshold {
+            sums.insert((i, j), value);
+        }
+    }
+    let size = |members: &[Option<Vec<usize>>], group: usize| members[group].as_ref().map_or(0, Vec::len);
+    let total = |left: &[usize], right: &[usize]| -> f32 {
+        left.iter().flat_map(|&i| right.iter().map(move |&j| (i, j))).map(|(i, j)| pair_similarity(i, j)).sum()
+    };
+
+    // Immer die beiden Gruppen mit der höchsten mittleren Ähnlichkeit, bei Gleichstand die mit den kleinsten Namen
+    while let Some((a, b)) = sums
+        .iter()
+        .map(|(&pair, &sum)| (pair, sum / (size(&members, pair.0) * size(&members, pair.1)) as f32))
+        .max_by(|(x, x_linkage), (y, y_linkage)| x_linkage.total_cmp(y_linkage).then(y.cmp(x)))
+        .map(|(pair, _)| pair)
+    {
+        sums.remove(&(a, b));
+        let neighbours: BTreeSet<usize> = sums
+            .keys()
+            .filter_map(|&(x, y)| match (x, y) {
+                (x, y) if x == a || x == b => Some(y),
+                (x, y) if y == a || y == b => Some(x),
+                _ => None,
+            })
+            .collect();
+        for c in neighbours {
+            let mut sum = 0.0;
+            for group in [a, b] {
+                // Nicht gemerkte Paare liegen unter der Schwelle, ihre Summe zählt aber zur neuen Verknüpfung
+                sum += sums.remove(&(group.min(c), group.max(c))).unwrap_or_else(|| {
+                    total(members[group].as_deref().unwrap_or_default(), members[c].as_deref().unwrap_or_default())
+                });
+            }
+            let linkage = sum / ((size(&members, a) + size(&members, b)) * size(&members, c)) as f32;
+            if linkage > threshold {
+                sums.insert((a.min(c), a.max(c)), sum);
+            }
+        }
+        let merged = members[b].take().unwrap_or_default();
+        members[a].get_or_insert_with(Vec::new).extend(merged);
+    }
+
+    let mut clusters: Vec<Vec<usize>> = members.into_iter().flatten().collect();
+    for cluster in clusters.iter_mut() {
+        cluster.sort_unstable();
+    }
+    clusters.sort_by(|a, b| b.len().cmp(&a.len()).then(a[0].cmp(&b[0])));
+    clusters
+}
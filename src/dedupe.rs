@@ -0,0 +1,135 @@
+//! Zusammenführen nahezu doppelter Einträge (`facerec dedupe`), wie sie durch Erfassungen derselben
+//! Person an mehreren Stationen oder wiederholte Neuerfassung entstehen
+
+use crate::{AccessLevel, FaceEntry, clustering};
+
+/// Gruppen von Einträgen, die dieselbe Person zeigen; nur Gruppen mit mehr als einem Eintrag.
+/// Zwei Einträge sind sich so ähnlich wie ihre ähnlichsten Aufnahmen.
+pub fn duplicates(faces: &[FaceEntry], threshold: f32) -> Vec<Vec<usize>> {
+    let pair_similarity = |i: usize, j: usize| {
+        faces[j]
+            .embeddings
+            .iter()
+            .map(|embedding| faces[i].similarity(embedding))
+            .max_by(f32::total_cmp)
+            .unwrap_or(f32::NEG_INFINITY)
+    };
+    clustering::agglomerative_by(faces.len(), pair_similarity, threshold)
+        .into_iter()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+/// Führt die Gruppe im ersten, also ältesten Eintrag zusammen: dessen ID und PIN bleiben, Aufnahmen werden
+/// vereint, Namen und Hinweise ohne Wiederholung verbunden und Wiedererkennungen addiert. Beim Zugang gilt
+/// das strengste Recht der Gruppe, damit eine Sperre nicht durch ein Duplikat aufgehoben wird.
+pub fn merge(group: Vec<FaceEntry>) -> FaceEntry {
+    let join = |values: Vec<&Option<String>>, separator: &str| {
+        let mut distinct: Vec<&str> = Vec::new();
+        for value in values.into_iter().flatten() {
+            if !distinct.contains(&value.as_str()) {
+                distinct.push(value);
+            }
+        }
+        (!distinct.is_empty()).then(|| distinct.join(separator))
+    };
+    let access = if group.iter().any(|face| face.access == AccessLevel::Denied) {
+        AccessLevel::Denied
+    } else if group.iter().any(|face| face.access == AccessLevel::Probation) {
+        AccessLevel::Probation
+    } else {
+        AccessLevel::Allowed
+    };
+    let name = join(group.iter().map(|face| &face.name).collect(), " / ");
+    let notes = join(group.iter().map(|face| &face.notes).collect(), "; ");
+    // Unbefristet, sobald einer der Einträge unbefristet ist
+    let valid_until = group
+        .iter()
+        .map(|face| face.valid_until)
+        .collect::<Option<Vec<_>>>()
+        .and_then(|until| until.into_iter().max());
+    let last_seen = group.iter().filter_map(|face| face.last_seen).max();
+    let match_count = group.iter().map(|face| face.match_count).sum();
+    let crop = group.iter().find_map(|face| face.crop.clone());
+    // Jede Aufnahme behält ihren Ausschnitt, damit `reindex` sie neu berechnen kann
+    let crops = group
+        .iter()
+        .flat_map(|face| (0..face.embeddings.len()).map(|index| face.crop_of(index).map(str::to_string)))
+        .collect();
+    let needs_reenrollment = group.iter().all(|face| face.needs_reenrollment);
+
+    let mut group = group.into_iter();
+    let mut merged = group.next().expect("leere Gruppe");
+    for face in group {
+        merged.embeddings.extend(face.embeddings);
+    }
+    FaceEntry {
+        access,
+        name,
+        notes,
+        valid_until,
+        last_seen,
+        match_count,
+        crop,
+        crops,
+        needs_reenrollment,
+        ..merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Einheitsvektor im Winkel `degrees`
+    fn at(id: &str, degrees: f32) -> FaceEntry {
+        let angle = degrees.to_radians();
+        FaceEntry::with_id(id.to_string(), vec![angle.cos(), angle.sin()], AccessLevel::Allowed)
+    }
+
+    #[test]
+    fn entries_exactly_at_the_threshold_stay_apart() {
+        let faces = [at("a", 0.0), at("b", 20.0)];
+        let similarity = faces[0].similarity(&faces[1].embeddings[0]);
+        assert!(duplicates(&faces, similarity).is_empty());
+        assert_eq!(duplicates(&faces, similarity - 1e-4), [vec![0, 1]]);
+    }
+
+    #[test]
+    fn groups_use_the_mean_similarity() {
+        // b liegt zwischen a und c; a und c sind sich nur zu 0,5 ähnlich
+        let faces = [at("a", 0.0), at("b", 30.0), at("c", 60.0), at("d", 180.0)];
+        // Nach a+b liegt c im Mittel bei (0,5 + 0,87) / 2 ≈ 0,68
+        assert_eq!(duplicates(&faces, 0.8), [vec![0, 1]]);
+        assert_eq!(duplicates(&faces, 0.6), [vec![0, 1, 2]]);
+        assert!(duplicates(&faces, 0.9).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_the_oldest_entry_and_the_strictest_access() {
+        let mut first = at("a", 0.0);
+        first.name = Some("Anna".to_string());
+        first.crop = Some("a.png".to_string());
+        first.match_count = 3;
+        first.pin_hash = Some("hash".to_string());
+        let mut second = at("b", 10.0);
+        second.name = Some("Anna".to_string());
+        second.notes = Some("Lieferant".to_string());
+        second.access = AccessLevel::Denied;
+        second.match_count = 2;
+        second.valid_until = Some(chrono::Local::now());
+        second.add_embedding(vec![0.0, 1.0], Some("b-2.png".to_string()));
+
+        let merged = merge(vec![first.clone(), second.clone()]);
+        assert_eq!(merged.id, "a");
+        assert_eq!(merged.pin_hash.as_deref(), Some("hash"));
+        assert_eq!(merged.access, AccessLevel::Denied);
+        assert_eq!(merged.name.as_deref(), Some("Anna"));
+        assert_eq!(merged.notes.as_deref(), Some("Lieferant"));
+        assert_eq!(merged.match_count, 5);
+        assert_eq!(merged.valid_until, None, "a ist unbefristet");
+        assert_eq!(merged.embeddings.len(), 3);
+        let crops: Vec<_> = (0..3).map(|index| merged.crop_of(index)).collect();
+        assert_eq!(crops, [Some("a.png"), None, Some("b-2.png")]);
+    }
+}
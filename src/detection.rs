@@ -0,0 +1,151 @@
+//! Gesichtssuche mit dem Haarcascade und ihre Parameter
+
+use opencv::{
+    core::{Rect, Size, ToInputArray, Vector},
+    objdetect,
+    prelude::*,
+};
+use serde::{Deserialize, Serialize};
+use crate::error::FacerecError;
+use std::fs;
+
+/// Parameter für `detect_multi_scale`; fehlende Felder in der Konfigurationsdatei erhalten die Standardwerte
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DetectorConfig {
+    /// Verkleinerungsfaktor zwischen zwei Suchstufen (> 1)
+    pub scale_factor: f64,
+    /// Anzahl benachbarter Treffer, die ein Gesicht bestätigen müssen
+    pub min_neighbors: i32,
+    pub flags: i32,
+    /// Kleinste gesuchte Gesichtsgröße (Breite, Höhe)
+    pub min_size: [i32; 2],
+    /// Größte gesuchte Gesichtsgröße (Breite, Höhe); 0,0 = unbegrenzt
+    pub max_size: [i32; 2],
+    /// Zweiter Cascade (z. B. haarcascade_eye.xml), der jeden Kandidaten bestätigen muss; None = keine Bestätigung
+    pub confirm_cascade: Option<String>,
+    /// min_neighbors für den bestätigenden Cascade
+    pub confirm_min_neighbors: i32,
+}
+
+impl Default for DetectorConfig {
+    fn default() -> Self {
+        Self {
+            scale_factor: 1.1,
+            min_neighbors: 3,
+            flags: objdetect::CASCADE_SCALE_IMAGE,
+            min_size: [30, 30],
+            max_size: [200, 200],
+            confirm_cascade: None,
+            confirm_min_neighbors: 3,
+        }
+    }
+}
+
+impl DetectorConfig {
+    /// Liest die Parameter aus einer JSON-Datei
+    pub fn load(path: &str) -> Result<Self, FacerecError> {
+        let invalid = |reason: String| FacerecError::Invalid { path: path.to_string(), reason };
+        let content = fs::read_to_string(path).map_err(|source| FacerecError::Read { path: path.to_string(), source })?;
+        let config: Self = serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        config.validate().map_err(invalid)?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.scale_factor <= 1.0 {
+            return Err("scale_factor muss größer als 1 sein".to_string());
+        }
+        if self.min_neighbors < 0 {
+            return Err("min_neighbors darf nicht negativ sein".to_string());
+        }
+        let [min_width, min_height] = self.min_size;
+        if min_width < 0 || min_height < 0 {
+            return Err("min_size darf nicht negativ sein".to_string());
+        }
+        let [max_width, max_height] = self.max_size;
+        if self.max_size != [0, 0] && (max_width < min_width || max_height < min_height) {
+            return Err("max_size muss mindestens min_size sein (oder 0,0 für unbegrenzt)".to_string());
+        }
+        if self.confirm_min_neighbors < 0 {
+            return Err("confirm_min_neighbors darf nicht negativ sein".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Haarcascade samt Suchparametern und optionalem bestätigendem Cascade
+pub struct FaceDetector {
+    cascade: objdetect::CascadeClassifier,
+    confirm: Option<objdetect::CascadeClassifier>,
+    config: DetectorConfig,
+}
+
+impl FaceDetector {
+    /// Lädt den Cascade und ggf. den bestätigenden; eine fehlende oder unlesbare Datei ist ein Fehler
+    pub fn new(cascade: &str, config: DetectorConfig) -> Result<Self, FacerecError> {
+        let confirm = config.confirm_cascade.as_deref().map(load_cascade).transpose()?;
+        Ok(Self {
+            cascade: load_cascade(cascade)?,
+            confirm,
+            config,
+        })
+    }
+
+    /// Sucht Gesichter im Graustufenbild; mit bestätigendem Cascade bleiben nur bestätigte Kandidaten übrig
+    pub fn detect(&mut self, gray: &(impl MatTraitConst + ToInputArray)) -> Result<Vector<Rect>, FacerecError> {
+        let [min_width, min_height] = self.config.min_size;
+        let [max_width, max_height] = self.config.max_size;
+        let mut faces = Vector::<Rect>::new();
+        self.cascade
+            .detect_multi_scale(
+                gray,
+                &mut faces,
+                self.config.scale_factor,
+                self.config.min_neighbors,
+                self.config.flags,
+                Size::new(min_width, min_height),
+                Size::new(max_width, max_height),
+            )?;
+        if self.confirm.is_none() {
+            return Ok(faces);
+        }
+        let mut confirmed = Vector::<Rect>::new();
+        for face in &faces {
+            if self.confirms(gray, face)? {
+                confirmed.push(face);
+            }
+        }
+        Ok(confirmed)
+    }
+
+    /// Prüft, ob der zweite Cascade innerhalb des Kandidaten anschlägt
+    fn confirms(&mut self, gray: &impl MatTraitConst, face: Rect) -> Result<bool, FacerecError> {
+        let Some(confirm) = self.confirm.as_mut() else {
+            return Ok(true);
+        };
+        let candidate = Mat::roi(gray, face)?;
+        let mut hits = Vector::<Rect>::new();
+        confirm
+            .detect_multi_scale(
+                &candidate,
+                &mut hits,
+                self.config.scale_factor,
+                self.config.confirm_min_neighbors,
+                self.config.flags,
+                Size::new(face.width / 8, face.height / 8),
+                Size::new(face.width, face.height),
+            )?;
+        Ok(!hits.is_empty())
+    }
+}
+
+/// OpenCV liefert für eine fehlende Datei einen leeren Cascade statt eines Fehlers
+fn load_cascade(path: &str) -> Result<objdetect::CascadeClassifier, FacerecError> {
+    let failed = || FacerecError::Cascade { path: path.to_string() };
+    let cascade = objdetect::CascadeClassifier::new(path).map_err(|_| failed())?;
+    if cascade.empty().unwrap_or(true) {
+        return Err(failed());
+    }
+    Ok(cascade)
+}
@@ -0,0 +1,74 @@
+//! Bewertung der Gesichtssuche an Bildern mit bekannten Gesichtsrahmen
+
+use crate::rates::ratio;
+use opencv::core::Rect;
+
+/// Überlappung zweier Rahmen (Schnittfläche durch Vereinigungsfläche)
+pub fn iou(a: Rect, b: Rect) -> f64 {
+    let intersection = (a & b).area() as f64;
+    let union = (a.area() + b.area()) as f64 - intersection;
+    if union <= 0.0 { 0.0 } else { intersection / union }
+}
+
+/// Aufsummierte Treffer über alle Bilder
+#[derive(Default)]
+pub struct DetectionScore {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+}
+
+impl DetectionScore {
+    /// Ordnet gefundene und erwartete Rahmen paarweise zu, die am stärksten überlappenden zuerst.
+    /// Jeder erwartete Rahmen zählt höchstens einmal; Paare unter `min_iou` gelten als verfehlt.
+    pub fn add(&mut self, detected: &[Rect], truth: &[Rect], min_iou: f64) {
+        let mut pairs: Vec<(f64, usize, usize)> = detected
+            .iter()
+            .enumerate()
+            .flat_map(|(d, &found)| truth.iter().enumerate().map(move |(t, &expected)| (iou(found, expected), d, t)))
+            .filter(|(overlap, _, _)| *overlap >= min_iou)
+            .collect();
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let (mut used_detected, mut used_truth) = (vec![false; detected.len()], vec![false; truth.len()]);
+        let mut matched = 0;
+        for (_, d, t) in pairs {
+            if !used_detected[d] && !used_truth[t] {
+                used_detected[d] = true;
+                used_truth[t] = true;
+                matched += 1;
+            }
+        }
+        self.true_positives += matched;
+        self.false_positives += detected.len() - matched;
+        self.false_negatives += truth.len() - matched;
+    }
+
+    pub fn precision(&self) -> f64 {
+        ratio(self.true_positives, self.false_positives)
+    }
+
+    pub fn recall(&self) -> f64 {
+        ratio(self.true_positives, self.false_negatives)
+    }
+
+    pub fn f1(&self) -> f64 {
+        crate::rates::f1(self.precision(), self.recall())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_expected_box_counts_once() {
+        let truth = [Rect::new(0, 0, 100, 100), Rect::new(300, 0, 100, 100)];
+        // Zwei Funde auf demselben Gesicht, das zweite Gesicht verfehlt
+        let detected = [Rect::new(0, 0, 100, 100), Rect::new(10, 10, 100, 100), Rect::new(600, 0, 50, 50)];
+        let mut score = DetectionScore::default();
+        score.add(&detected, &truth, 0.5);
+        assert_eq!((score.true_positives, score.false_positives, score.false_negatives), (1, 2, 1));
+        assert_eq!((score.precision(), score.recall()), (1.0 / 3.0, 0.5));
+        assert_eq!(score.f1(), 0.4);
+    }
+}
@@ -0,0 +1,102 @@
+//! Türsteuerung für einen elektrischen Türöffner: bei erlaubtem Zugang den Entriegelungsbefehl ausführen,
+//! die Tür eine Haltezeit lang offen lassen und danach den Verriegelungsbefehl ausführen.
+//! Der Ablauf läuft in einem eigenen Thread und hält die Erkennung nicht auf; weitere erlaubte Zugänge
+//! während der Haltezeit verlängern sie, ohne erneut zu entriegeln.
+
+use std::process::Command;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+struct State {
+    /// Zeitpunkt der Verriegelung, solange die Tür offen ist
+    relock_at: Option<Instant>,
+    sequence: Option<JoinHandle<()>>,
+}
+
+pub struct Door {
+    unlock: String,
+    lock: String,
+    hold: Duration,
+    /// Die Bedingungsvariable weckt den wartenden Ablauf, wenn beim Beenden sofort verriegelt wird
+    state: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Door {
+    pub fn new(unlock: &str, lock: &str, hold: Duration) -> Self {
+        Self {
+            unlock: unlock.to_string(),
+            lock: lock.to_string(),
+            hold,
+            state: Arc::new((Mutex::new(State::default()), Condvar::new())),
+        }
+    }
+
+    /// Entriegelt für die Haltezeit bzw. verlängert sie, wenn die Tür schon offen ist
+    pub fn open(&self, camera: &str, id: Option<&str>) {
+        let mut state = self.state.0.lock().unwrap();
+        let relock_at = Instant::now() + self.hold;
+        if state.relock_at.is_some() {
+            state.relock_at = Some(relock_at);
+            return;
+        }
+        state.relock_at = Some(relock_at);
+        let unlock = self.unlock.clone();
+        let lock = self.lock.clone();
+        let camera = camera.to_string();
+        let id = id.unwrap_or("").to_string();
+        let shared = Arc::clone(&self.state);
+        let previous = state.sequence.take();
+        state.sequence = Some(thread::spawn(move || {
+            // Ein noch laufendes Verriegeln zuerst abschließen, sonst verriegelt es die frisch geöffnete Tür
+            if let Some(previous) = previous {
+                previous.join().ok();
+            }
+            run("--door-unlock", &unlock, &camera, &id);
+            // Bis zur zuletzt verlängerten Frist warten
+            let (state, changed) = &*shared;
+            let mut state = state.lock().unwrap();
+            while let Some(relock_at) = state.relock_at {
+                let now = Instant::now();
+                if relock_at <= now {
+                    break;
+                }
+                state = changed.wait_timeout(state, relock_at - now).unwrap().0;
+            }
+            state.relock_at = None;
+            drop(state);
+            run("--door-lock", &lock, &camera, &id);
+        }));
+    }
+
+    /// Verriegelt eine offene Tür sofort und wartet auf den Befehl, damit sie beim Beenden nicht offen bleibt
+    pub fn shutdown(&self) {
+        let sequence = {
+            let mut state = self.state.0.lock().unwrap();
+            if state.relock_at.is_some() {
+                state.relock_at = Some(Instant::now());
+            }
+            state.sequence.take()
+        };
+        self.state.1.notify_all();
+        if let Some(sequence) = sequence {
+            sequence.join().ok();
+        }
+    }
+}
+
+/// Führt den Befehl über `sh -c` aus; FACEREC_ID und FACEREC_CAMERA beschreiben den auslösenden Zugang
+fn run(option: &str, command: &str, camera: &str, id: &str) {
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("FACEREC_ID", id)
+        .env("FACEREC_CAMERA", camera)
+        .status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => eprintln!("Warnung: {option} endete mit {status}"),
+        Err(e) => eprintln!("Warnung: {option} konnte nicht gestartet werden: {e}"),
+    }
+}
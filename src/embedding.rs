@@ -0,0 +1,378 @@
+//! Merkmalsextraktion: Embedding-Modell über OpenCV DNN, ersatzweise ein handgefertigter Deskriptor
+
+use opencv::{
+    core::{Mat, Scalar, Size},
+    dnn, imgproc,
+    prelude::*,
+};
+use crate::error::FacerecError;
+use crate::preprocessing::{self, Eyes, Pipeline, Step};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Aufbau der Ersatzmerkmale ohne Modell (160 Werte, insgesamt L2-normiert):
+/// - `[0, 32)`: Grauwert-Histogramm mit 32 gleich breiten Klassen über 0–255
+/// - `[32, 160)`: Gradienten-Histogramme (HOG-artig) auf 4×4 Zellen zu je 16×16 Pixeln, zeilenweise
+///   von oben links; je Zelle 8 Richtungsklassen zu 22,5° über 0–180° (ohne Vorzeichen), gewichtet
+///   mit dem Gradientenbetrag
+///
+/// Beide Teile werden einzeln L2-normiert und mit 1/√2 gewichtet, damit sie gleich stark zählen.
+///
+/// Früher bestanden die Ersatzmerkmale aus den rohen Pixeln eines 100×100-Bilds (`LEGACY_DUMMY_DIMENSION`);
+/// damit aufgebaute Datenbanken passen nicht mehr und müssen mit `reindex` neu berechnet werden.
+const DUMMY_SIZE: i32 = 64;
+const HISTOGRAM_BINS: usize = 32;
+const CELL_SIZE: usize = 16;
+const ORIENTATION_BINS: usize = 8;
+const CELLS: usize = DUMMY_SIZE as usize / CELL_SIZE;
+const DUMMY_DIMENSION: usize = HISTOGRAM_BINS + CELLS * CELLS * ORIENTATION_BINS;
+/// Länge der früheren Ersatzmerkmale aus rohen Pixeln, siehe `DUMMY_SIZE`
+pub const LEGACY_DUMMY_DIMENSION: usize = 10_000;
+
+/// Vorverarbeitung für `blob_from_image`: Pixelwert = (Wert − mean) · scale.
+/// Übliche Werte je Modell:
+/// - ArcFace/InsightFace (ONNX): 112×112, mean 127,5, scale 1/127,5, swap_rb
+/// - SFace (OpenCV Zoo): 112×112, mean 0, scale 1, swap_rb
+/// - FaceNet (Inception-ResNet): 160×160, mean 127,5, scale 1/128, swap_rb
+///
+/// Fehlende Felder in der Konfigurationsdatei erhalten die Standardwerte (112×112, scale 1/255, keine
+/// Vorverarbeitung der Ausschnitte).
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ModelConfig {
+    /// Eingabegröße des Modells (Breite, Höhe)
+    pub input_size: [i32; 2],
+    /// Mittelwert je Kanal (B, G, R), der vor der Skalierung abgezogen wird
+    pub mean: [f64; 3],
+    pub scale: f64,
+    /// Rot- und Blaukanal tauschen (Modelle mit RGB-Eingabe)
+    pub swap_rb: bool,
+    /// Schritte, die vor der Extraktion der Reihe nach auf jeden Ausschnitt angewendet werden (siehe
+    /// `preprocessing`); leer nicht serialisiert, damit die Kennung bisheriger Konfigurationen gleich bleibt
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub preprocessing: Vec<Step>,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        Self {
+            input_size: [112, 112],
+            mean: [0.0; 3],
+            scale: 1.0 / 255.0,
+            swap_rb: true,
+            preprocessing: Vec::new(),
+        }
+    }
+}
+
+/// Vorverarbeitung verbreiteter Modelle
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub enum ModelPreset {
+    Arcface,
+    Sface,
+    Facenet,
+}
+
+impl ModelConfig {
+    pub fn preset(preset: ModelPreset) -> Self {
+        match preset {
+            ModelPreset::Arcface => Self {
+                input_size: [112, 112],
+                mean: [127.5; 3],
+                scale: 1.0 / 127.5,
+                swap_rb: true,
+                preprocessing: Vec::new(),
+            },
+            ModelPreset::Sface => Self {
+                input_size: [112, 112],
+                mean: [0.0; 3],
+                scale: 1.0,
+                swap_rb: true,
+                preprocessing: Vec::new(),
+            },
+            ModelPreset::Facenet => Self {
+                input_size: [160, 160],
+                mean: [127.5; 3],
+                scale: 1.0 / 128.0,
+                swap_rb: true,
+                preprocessing: Vec::new(),
+            },
+        }
+    }
+
+    /// Liest die Vorverarbeitung aus einer JSON-Datei
+    pub fn load(path: &str) -> Result<Self, FacerecError> {
+        let invalid = |reason: String| FacerecError::Invalid { path: path.to_string(), reason };
+        let content = fs::read_to_string(path).map_err(|source| FacerecError::Read { path: path.to_string(), source })?;
+        let config: Self = serde_json::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        let [width, height] = config.input_size;
+        if width <= 0 || height <= 0 {
+            return Err(invalid("input_size muss positiv sein".to_string()));
+        }
+        if config.scale <= 0.0 {
+            return Err(invalid("scale muss positiv sein".to_string()));
+        }
+        preprocessing::validate(&config.preprocessing).map_err(invalid)?;
+        Ok(config)
+    }
+
+    fn input_size(&self) -> Size {
+        Size::new(self.input_size[0], self.input_size[1])
+    }
+}
+
+/// SHA-1 der Modelldatei (als UUID v5 im Namensraum OID), um den Wechsel des Modells zu erkennen
+pub fn fingerprint(model: &str) -> Option<String> {
+    fs::read(model).ok().map(|bytes| Uuid::new_v5(&Uuid::NAMESPACE_OID, &bytes).simple().to_string())
+}
+
+/// Kennung eines Modells samt Vorverarbeitung, `<Hash der Modelldatei>-<Hash der Konfiguration>`;
+/// None ohne lesbare Modelldatei
+pub fn model_identity(model: &str, config: &ModelConfig) -> Option<String> {
+    let fingerprint = fingerprint(model)?;
+    let config = serde_json::to_vec(config).expect("Fehler beim Serialisieren");
+    Some(format!("{fingerprint}-{}", Uuid::new_v5(&Uuid::NAMESPACE_OID, &config).simple()))
+}
+
+/// Erzeugt Embeddings aus Graustufen-Gesichtsausschnitten
+pub enum Embedder {
+    /// Netz, Konfiguration und die daraus vorbereitete Vorverarbeitung
+    Dnn(dnn::Net, ModelConfig, Pipeline),
+    /// Grauwert- und Gradienten-Histogramm ohne Modell (siehe `DUMMY_SIZE`); die Erkennungsgenauigkeit
+    /// ist gering. Von der Konfiguration gilt nur die Vorverarbeitung.
+    Dummy(Pipeline),
+}
+
+impl Embedder {
+    /// Lädt das Modell. Fehlt es oder ist es unbrauchbar, wird mit `allow_dummy` auf die Ersatzmerkmale
+    /// ausgewichen, andernfalls ein Fehler mit Hinweis zur Behebung geliefert.
+    pub fn load(model: &str, config: ModelConfig, allow_dummy: bool) -> Result<Self, FacerecError> {
+        match load_net(model) {
+            Ok(net) => {
+                let pipeline = Pipeline::new(config.preprocessing.clone())?;
+                Ok(Embedder::Dnn(net, config, pipeline))
+            }
+            Err(reason) if allow_dummy => {
+                eprintln!("WARNUNG: {reason}");
+                eprintln!("WARNUNG: Es werden Ersatzmerkmale aus Grauwert- und Gradienten-Histogrammen verwendet – die Erkennung ist sehr ungenau!");
+                Ok(Embedder::Dummy(Pipeline::new(config.preprocessing)?))
+            }
+            Err(reason) => Err(FacerecError::Model(format!(
+                "{reason}. Pfad mit --model angeben oder mit --allow-dummy-features ohne Modell starten."
+            ))),
+        }
+    }
+
+    /// Länge der erzeugten Embeddings; beim Modell durch einen Probelauf auf einem leeren Bild ermittelt
+    pub fn dimension(&mut self) -> Result<usize, FacerecError> {
+        match self {
+            Embedder::Dnn(net, config, _) => Ok(dnn_features(net, config, &probe(config)?)?.len()),
+            Embedder::Dummy(_) => Ok(DUMMY_DIMENSION),
+        }
+    }
+
+    /// Führt einen Probelauf durch, damit die einmalige Optimierung und Speicherbelegung des Netzes
+    /// nicht beim ersten erkannten Gesicht anfällt. Liefert die Dauer des Probelaufs (0 ohne Modell).
+    pub fn warm_up(&mut self) -> Result<Duration, FacerecError> {
+        let Embedder::Dnn(net, config, _) = self else {
+            return Ok(Duration::ZERO);
+        };
+        let start = Instant::now();
+        dnn_features(net, config, &probe(config)?)?;
+        Ok(start.elapsed())
+    }
+
+    /// Kennung von Modell und Vorverarbeitung; gleiche Kennung bedeutet gleiche Embeddings
+    pub fn identity(&self, model: &str) -> String {
+        match self {
+            Embedder::Dnn(_, config, _) => model_identity(model, config).unwrap_or_default(),
+            // Mit dem Aufbau der Ersatzmerkmale ändert sich die Kennung, damit alte Cache-Einträge nicht passen
+            Embedder::Dummy(pipeline) if pipeline.is_empty() => "dummy-histogram-hog".to_string(),
+            Embedder::Dummy(pipeline) => {
+                let steps = serde_json::to_vec(pipeline.steps()).expect("Fehler beim Serialisieren");
+                format!("dummy-histogram-hog-{}", Uuid::new_v5(&Uuid::NAMESPACE_OID, &steps).simple())
+            }
+        }
+    }
+
+    /// Ob die Vorverarbeitung die Lage der Augen braucht (Schritt `align`)
+    pub fn aligns(&self) -> bool {
+        match self {
+            Embedder::Dnn(_, _, pipeline) | Embedder::Dummy(pipeline) => pipeline.aligns(),
+        }
+    }
+
+    /// Embedding eines Ausschnitts nach der konfigurierten Vorverarbeitung
+    pub fn extract(&mut self, face: &Mat) -> Result<Vec<f32>, FacerecError> {
+        self.extract_aligned(face, None)
+    }
+
+    /// Wie `extract`, mit der Lage der Augen im Ausschnitt für den Schritt `align`
+    pub fn extract_aligned(&mut self, face: &Mat, eyes: Option<Eyes>) -> Result<Vec<f32>, FacerecError> {
+        let features = match self {
+            Embedder::Dnn(net, config, pipeline) if pipeline.is_empty() => dnn_features(net, config, face)?,
+            Embedder::Dnn(net, config, pipeline) => {
+                let face = pipeline.apply_with(face, eyes)?;
+                dnn_features(net, config, &face)?
+            }
+            Embedder::Dummy(pipeline) => dummy_features(&pipeline.apply_with(face, eyes)?)?,
+        };
+        Ok(features)
+    }
+}
+
+/// Leeres Bild in Eingabegröße für Probeläufe
+fn probe(config: &ModelConfig) -> opencv::Result<Mat> {
+    let [width, height] = config.input_size;
+    Mat::new_rows_cols_with_default(height, width, opencv::core::CV_8UC1, Scalar::all(0.0))
+}
+
+fn load_net(model: &str) -> Result<dnn::Net, String> {
+    if !Path::new(model).exists() {
+        return Err(format!("Embedding-Modell {model} nicht gefunden"));
+    }
+    let net = dnn::read_net(model, "", "").map_err(|e| format!("Embedding-Modell {model} ist ungültig: {e}"))?;
+    if net.empty().unwrap_or(true) {
+        return Err(format!("Embedding-Modell {model} enthält kein Netz"));
+    }
+    Ok(net)
+}
+
+/// Berechnet das L2-normierte Embedding eines Ausschnitts
+fn dnn_features(net: &mut dnn::Net, config: &ModelConfig, face: &Mat) -> opencv::Result<Vec<f32>> {
+    // Das Modell erwartet drei Kanäle; Ausschnitte aus der Pipeline (auch von IR-Kameras) sind einkanalig
+    let mut bgr = Mat::default();
+    if face.channels() == 1 {
+        imgproc::cvt_color(
+            face,
+            &mut bgr,
+            imgproc::COLOR_GRAY2BGR,
+            0,
+            unsafe { std::mem::zeroed() },
+        )?;
+    } else {
+        bgr = face.try_clone()?;
+    }
+    let [mean_b, mean_g, mean_r] = config.mean;
+    let blob = dnn::blob_from_image(
+        &bgr,
+        config.scale,
+        config.input_size(),
+        Scalar::new(mean_b, mean_g, mean_r, 0.0),
+        config.swap_rb,
+        false,
+        opencv::core::CV_32F,
+    )?;
+    net.set_input(&blob, "", 1.0, Scalar::default())?;
+    let output = net.forward_single("")?;
+    let mut features = output.data_typed::<f32>()?.to_vec();
+    let norm = features.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        features.iter_mut().for_each(|v| *v /= norm);
+    }
+    Ok(features)
+}
+
+/// Ersatzmerkmale aus einem Gesicht, Aufbau siehe `DUMMY_SIZE`
+fn dummy_features(face: &Mat) -> opencv::Result<Vec<f32>> {
+    let mut gray = Mat::default();
+    if face.channels() == 3 {
+        imgproc::cvt_color(face, &mut gray, imgproc::COLOR_BGR2GRAY, 0, unsafe { std::mem::zeroed() })?;
+    } else {
+        gray = face.try_clone()?;
+    }
+    let mut resized = Mat::default();
+    imgproc::resize(
+        &gray,
+        &mut resized,
+        Size::new(DUMMY_SIZE, DUMMY_SIZE),
+        0.0,
+        0.0,
+        imgproc::INTER_LINEAR,
+    )?;
+    Ok(descriptor(resized.data_bytes()?))
+}
+
+/// Deskriptor eines Graubilds mit DUMMY_SIZE×DUMMY_SIZE Pixeln, zeilenweise
+fn descriptor(pixels: &[u8]) -> Vec<f32> {
+    let size = DUMMY_SIZE as usize;
+    let mut histogram = vec![0.0f32; HISTOGRAM_BINS];
+    for &pixel in pixels {
+        histogram[pixel as usize * HISTOGRAM_BINS / 256] += 1.0;
+    }
+
+    let mut gradients = vec![0.0f32; CELLS * CELLS * ORIENTATION_BINS];
+    let pixel = |x: usize, y: usize| pixels[y * size + x] as f32;
+    // Zentrale Differenzen; am Rand wird der Randpixel wiederholt
+    for y in 0..size {
+        for x in 0..size {
+            let dx = pixel((x + 1).min(size - 1), y) - pixel(x.saturating_sub(1), y);
+            let dy = pixel(x, (y + 1).min(size - 1)) - pixel(x, y.saturating_sub(1));
+            let magnitude = (dx * dx + dy * dy).sqrt();
+            if magnitude == 0.0 {
+                continue;
+            }
+            let angle = dy.atan2(dx).rem_euclid(std::f32::consts::PI);
+            let bin = ((angle / std::f32::consts::PI * ORIENTATION_BINS as f32) as usize).min(ORIENTATION_BINS - 1);
+            let cell = (y / CELL_SIZE) * CELLS + x / CELL_SIZE;
+            gradients[cell * ORIENTATION_BINS + bin] += magnitude;
+        }
+    }
+
+    let mut features = Vec::with_capacity(DUMMY_DIMENSION);
+    for mut part in [histogram, gradients] {
+        let norm = part.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            part.iter_mut().for_each(|v| *v /= norm * std::f32::consts::SQRT_2);
+        }
+        features.extend(part);
+    }
+    features
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: usize = DUMMY_SIZE as usize;
+
+    #[test]
+    fn descriptor_of_a_flat_image_is_a_single_grey_bin() {
+        let features = descriptor(&[200; SIZE * SIZE]);
+        assert_eq!(features.len(), DUMMY_DIMENSION);
+        let bin = 200 * HISTOGRAM_BINS / 256;
+        assert!((features[bin] - std::f32::consts::FRAC_1_SQRT_2).abs() < 1e-6);
+        assert!(features.iter().enumerate().all(|(index, &value)| index == bin || value == 0.0));
+    }
+
+    #[test]
+    fn descriptor_places_a_vertical_edge_in_the_horizontal_orientation_bin() {
+        // Links schwarz, rechts weiß: nur waagrechte Gradienten an der Kante zwischen x = 31 und x = 32
+        let pixels: Vec<u8> = (0..SIZE * SIZE).map(|index| if index % SIZE < SIZE / 2 { 0 } else { 255 }).collect();
+        let features = descriptor(&pixels);
+        let norm = features.iter().map(|v| v * v).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-5);
+        let gradients = &features[HISTOGRAM_BINS..];
+        for (index, &value) in gradients.iter().enumerate() {
+            let (cell, bin) = (index / ORIENTATION_BINS, index % ORIENTATION_BINS);
+            let at_edge = [1, 2].contains(&(cell % CELLS)) && bin == 0;
+            assert_eq!(value > 0.0, at_edge, "Zelle {cell}, Klasse {bin}");
+        }
+    }
+
+    #[test]
+    fn model_identity_covers_file_and_config() {
+        let path = std::env::temp_dir().join(format!("facer-model-{}.onnx", std::process::id()));
+        fs::write(&path, b"modell").unwrap();
+        let model = path.to_str().unwrap();
+        let identity = model_identity(model, &ModelConfig::default()).unwrap();
+        assert!(identity.starts_with(&format!("{}-", fingerprint(model).unwrap())));
+        let other = ModelConfig { swap_rb: false, ..ModelConfig::default() };
+        assert_ne!(model_identity(model, &other), Some(identity));
+        fs::remove_file(&path).unwrap();
+        assert_eq!(model_identity(model, &ModelConfig::default()), None);
+    }
+}
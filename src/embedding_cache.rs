@@ -0,0 +1,71 @@
+//! Zwischenspeicher für Embeddings gespeicherter Ausschnitte und Bilder: unveränderte Dateien müssen bei einem
+//! erneuten `reindex` oder `calibrate` nicht noch einmal durch das Modell. Schlüssel ist der Hash des
+//! Dateiinhalts (bei ganzen Bildern zusätzlich der Gesichtssuche); die ganze Datei gilt nur für ein Modell
+//! samt Vorverarbeitung und wird bei einem Wechsel verworfen.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use uuid::Uuid;
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    /// Kennung des Modells, mit dem die Embeddings berechnet wurden
+    model: String,
+    embeddings: HashMap<String, Vec<f32>>,
+}
+
+pub struct EmbeddingCache {
+    path: String,
+    file: CacheFile,
+    /// Neue Einträge seit dem Laden
+    added: usize,
+}
+
+impl EmbeddingCache {
+    /// Lädt den Zwischenspeicher; fehlt er, gehört er zu einem anderen Modell oder ist er unlesbar, beginnt er leer
+    pub fn open(path: &str, model: String) -> Self {
+        let file = match fs::read_to_string(path).map(|content| serde_json::from_str::<CacheFile>(&content)) {
+            Ok(Ok(file)) if file.model == model => file,
+            Ok(Ok(_)) => {
+                crate::say!("Modell geändert: Embedding-Cache {path} wird neu aufgebaut.");
+                CacheFile { model, ..CacheFile::default() }
+            }
+            Ok(Err(e)) => {
+                eprintln!("Warnung: Embedding-Cache {path} ist ungültig und wird neu aufgebaut: {e}");
+                CacheFile { model, ..CacheFile::default() }
+            }
+            Err(_) => CacheFile { model, ..CacheFile::default() },
+        };
+        Self {
+            path: path.to_string(),
+            file,
+            added: 0,
+        }
+    }
+
+    /// Schlüssel einer Bilddatei aus ihrem Inhalt; None, wenn sie nicht lesbar ist
+    pub fn key(path: &str) -> Option<String> {
+        fs::read(path).ok().map(|bytes| Uuid::new_v5(&Uuid::NAMESPACE_OID, &bytes).simple().to_string())
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Vec<f32>> {
+        self.file.embeddings.get(key)
+    }
+
+    pub fn insert(&mut self, key: String, embedding: Vec<f32>) {
+        self.file.embeddings.insert(key, embedding);
+        self.added += 1;
+    }
+
+    /// Schreibt den Zwischenspeicher, falls neue Einträge hinzugekommen sind
+    pub fn save(&self) {
+        if self.added == 0 {
+            return;
+        }
+        let json = serde_json::to_string(&self.file).expect("Fehler beim Serialisieren");
+        if let Err(e) = fs::write(&self.path, json) {
+            eprintln!("Warnung: Embedding-Cache {} konnte nicht gespeichert werden: {e}", self.path);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+//! Erfassung neuer Personen: Rückfrage beim Bediener auf der Konsole oder direkt im Videofenster
+
+use opencv::{
+    core::{Point, Rect, Scalar},
+    imgproc,
+    prelude::*,
+};
+use std::io;
+use std::sync::Mutex;
+use std::sync::mpsc::{self, Sender};
+
+/// Zugang, den der Bediener einer neuen Person gewährt
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AccessType {
+    Allowed,
+    /// Zugang nur für ein begrenztes Zeitfenster
+    Visitor,
+    /// Zugang erlaubt, jede Wiedererkennung wird zur Prüfung vorgemerkt
+    Probation,
+    Denied,
+}
+
+/// Antwort des Bedieners auf die Erfassungsrückfrage
+pub struct Enrollment {
+    pub access: AccessType,
+    pub name: Option<String>,
+}
+
+/// Rückfrage eines Kamera-Threads an das Videofenster im Hauptthread
+pub struct EnrollRequest {
+    pub window: String,
+    reply: Sender<Option<Enrollment>>,
+}
+
+/// Verhindert, dass sich die Rückfragen mehrerer Kameras auf der Konsole überschneiden
+pub static PROMPT_LOCK: Mutex<()> = Mutex::new(());
+
+/// Bei einem Lesefehler gilt die Antwort als leer, die Person wird also nicht erfasst
+fn read_line() -> String {
+    let mut response = String::new();
+    if let Err(e) = io::stdin().read_line(&mut response) {
+        eprintln!("Warnung: Eingabe konnte nicht gelesen werden: {e}");
+    }
+    response.trim().to_string()
+}
+
+/// Fragt auf der Konsole nach Zugang und Namen einer neu erkannten Person
+pub fn prompt_console(camera: &str) -> Enrollment {
+    let _guard = PROMPT_LOCK.lock().unwrap();
+    crate::say!("[{camera}] Neue Person erkannt. Zugang gewähren? (j = ja, n = nein, b = Besucher, p = auf Probe): ");
+    let access = match read_line().to_lowercase().as_str() {
+        "j" => AccessType::Allowed,
+        "b" => AccessType::Visitor,
+        "p" => AccessType::Probation,
+        _ => AccessType::Denied,
+    };
+    crate::say!("[{camera}] Name (optional): ");
+    let name = Some(read_line()).filter(|name| !name.is_empty());
+    Enrollment { access, name }
+}
+
+/// Stellt die Rückfrage im Videofenster und wartet auf die Antwort.
+/// Liefert `None`, wenn die Erfassung abgebrochen wurde (z. B. beim Beenden).
+pub fn prompt_gui(window: &str, requests: &Sender<EnrollRequest>) -> Option<Enrollment> {
+    let (reply, answer) = mpsc::channel();
+    requests
+        .send(EnrollRequest {
+            window: window.to_string(),
+            reply,
+        })
+        .ok()?;
+    answer.recv().ok().flatten()
+}
+
+/// Zustand einer laufenden Rückfrage im Videofenster: erst Zugangsart wählen, dann Namen eingeben
+pub struct GuiPrompt {
+    request: EnrollRequest,
+    access: Option<AccessType>,
+    name: String,
+}
+
+impl GuiPrompt {
+    pub fn new(request: EnrollRequest) -> Self {
+        Self {
+            request,
+            access: None,
+            name: String::new(),
+        }
+    }
+
+    pub fn window(&self) -> &str {
+        &self.request.window
+    }
+
+    /// Verarbeitet einen Tastendruck; liefert `true`, sobald die Antwort abgeschickt wurde
+    pub fn handle_key(&mut self, key: i32) -> bool {
+        let key = key & 0xFF;
+        match self.access {
+            None => {
+                self.access = match u8::try_from(key).map(char::from) {
+                    Ok('j') => Some(AccessType::Allowed),
+                    Ok('n') => Some(AccessType::Denied),
+                    Ok('b') => Some(AccessType::Visitor),
+                    Ok('p') => Some(AccessType::Probation),
+                    _ => None,
+                };
+                false
+            }
+            Some(access) => match key {
+                // Enter bestätigt
+                10 | 13 => {
+                    let name = Some(self.name.trim().to_string()).filter(|name| !name.is_empty());
+                    let _ = self.request.reply.send(Some(Enrollment { access, name }));
+                    true
+                }
+                // Rücktaste
+                8 | 127 => {
+                    self.name.pop();
+                    false
+                }
+                32..=126 => {
+                    self.name.push(key as u8 as char);
+                    false
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// Bricht die Rückfrage ab; die Person wird nicht erfasst
+    pub fn cancel(self) {
+        let _ = self.request.reply.send(None);
+    }
+
+    /// Blendet die Rückfrage am oberen Bildrand ein
+    pub fn draw(&self, frame: &mut Mat) {
+        let text = match self.access {
+            None => "Neue Person: [j] erlauben  [n] verweigern  [b] Besucher  [p] auf Probe".to_string(),
+            Some(_) => format!("Name (Enter bestaetigt): {}_", self.name),
+        };
+        let banner = Rect::new(0, 0, frame.cols(), 36);
+        imgproc::rectangle(frame, banner, Scalar::new(40.0, 40.0, 40.0, 0.0), imgproc::FILLED, imgproc::LINE_8, 0)
+            .unwrap();
+        imgproc::put_text(
+            frame,
+            &text,
+            Point::new(10, 25),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.6,
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            1,
+            imgproc::LINE_AA,
+            false,
+        )
+            .unwrap();
+    }
+}
+
+/// Beantwortet eine wartende Rückfrage mit Abbruch
+pub fn cancel_request(request: EnrollRequest) {
+    GuiPrompt::new(request).cancel();
+}
@@ -0,0 +1,34 @@
+//! Fehler der Bibliothek. Das Programm `facerec` meldet sie als „Fehler: …“ und beendet sich bzw. arbeitet
+//! im laufenden Betrieb mit einer Warnung weiter.
+
+use std::io;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum FacerecError {
+    #[error("{path} konnte nicht gelesen werden: {source}")]
+    Read {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("{path} konnte nicht geschrieben werden: {source}")]
+    Write {
+        path: String,
+        #[source]
+        source: io::Error,
+    },
+    /// Datei lesbar, aber Inhalt oder Parameter ungültig
+    #[error("{path} ist ungültig: {reason}")]
+    Invalid { path: String, reason: String },
+    #[error("Haarcascade {path} konnte nicht geladen werden")]
+    Cascade { path: String },
+    /// Embedding-Modell fehlt oder ist unbrauchbar
+    #[error("{0}")]
+    Model(String),
+    /// Fehler einer OpenCV-Funktion, z. B. bei einem Bild in unerwartetem Format
+    #[error("OpenCV: {0}")]
+    OpenCv(#[from] opencv::Error),
+}
+
+pub type Result<T> = std::result::Result<T, FacerecError>;
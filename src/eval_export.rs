@@ -0,0 +1,71 @@
+//! Weitergabefähiger Auswertungsdatensatz: nur die Embeddings mit anonymen Personennummern und
+//! Zugangsrechten, ohne Bilder, IDs, Namen, Hinweise oder Zeitstempel. Damit lässt sich das Verhalten
+//! des Abgleichs (Schwellwert, Gleichstände) nachstellen, ohne Gesichtsbilder herauszugeben.
+
+use crate::{AccessLevel, FaceEntry};
+use serde::Serialize;
+
+/// Fassung des Dateiformats
+const VERSION: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(untagged)]
+pub enum Embedding {
+    Float(Vec<f32>),
+    /// Normiertes Embedding, je Wert auf -127..=127 gerundet
+    Quantized(Vec<i8>),
+}
+
+#[derive(Serialize)]
+pub struct Sample {
+    /// Laufende Nummer der Person; gleiche Nummer bedeutet gleicher Eintrag
+    pub label: usize,
+    pub access: AccessLevel,
+    pub embedding: Embedding,
+}
+
+#[derive(Serialize)]
+pub struct EvalDataset {
+    pub version: u32,
+    pub dimension: Option<usize>,
+    /// Hash der Modelldatei, damit Datensätze verschiedener Modelle nicht vermischt werden
+    pub model: Option<String>,
+    pub threshold: f32,
+    pub quantized: bool,
+    pub samples: Vec<Sample>,
+}
+
+impl EvalDataset {
+    pub fn new(faces: &[FaceEntry], model: Option<String>, threshold: f32, quantize: bool) -> Self {
+        let samples = faces
+            .iter()
+            .enumerate()
+            .flat_map(|(label, face)| {
+                face.embeddings.iter().map(move |embedding| Sample {
+                    label,
+                    access: face.access,
+                    embedding: if quantize {
+                        Embedding::Quantized(quantize_embedding(embedding))
+                    } else {
+                        Embedding::Float(embedding.clone())
+                    },
+                })
+            })
+            .collect();
+        Self {
+            version: VERSION,
+            dimension: faces.first().and_then(FaceEntry::dimension),
+            model,
+            threshold,
+            quantized: quantize,
+            samples,
+        }
+    }
+}
+
+/// Normiert auf Länge 1 und rundet auf 8 Bit; die Kosinus-Ähnlichkeit bleibt bis auf Rundung erhalten
+fn quantize_embedding(embedding: &[f32]) -> Vec<i8> {
+    let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+    let scale = if norm > 0.0 { 127.0 / norm } else { 0.0 };
+    embedding.iter().map(|v| (v * scale).round().clamp(-127.0, 127.0) as i8).collect()
+}
@@ -0,0 +1,154 @@
+//! Entscheidungen zusätzlich als Tabelle `events` in einer SQLite-Datenbank (--event-db), um Auswertungen
+//! per SQL zu fahren, z. B. Stoßzeiten, häufigste Besucher oder Abweisungen je Kamera. Geschrieben wird über
+//! `rusqlite` (SQLite ist eingebunden); das Audit-Log bleibt unverändert. Beispiel:
+//!
+//! ```sql
+//! SELECT strftime('%H', timestamp) AS stunde, count(*) FROM events GROUP BY stunde ORDER BY 2 DESC;
+//! ```
+
+use rusqlite::{Connection, params};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SCHEMA: &str = "CREATE TABLE IF NOT EXISTS events (
+    timestamp TEXT NOT NULL,
+    camera TEXT NOT NULL,
+    frame INTEGER NOT NULL,
+    track INTEGER NOT NULL,
+    id TEXT,
+    name TEXT,
+    score REAL,
+    decision TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_timestamp ON events (timestamp);
+CREATE INDEX IF NOT EXISTS events_id ON events (id);
+";
+
+/// Eine Zeile der Tabelle `events`; `timestamp` in Ortszeit (`YYYY-MM-DD HH:MM:SS.SSS`), damit die
+/// Datumsfunktionen von SQLite ohne Umrechnung Stunden und Tage liefern
+pub struct Row<'a> {
+    pub timestamp: String,
+    pub camera: &'a str,
+    pub frame: u64,
+    pub track: u64,
+    pub id: Option<&'a str>,
+    pub name: Option<&'a str>,
+    pub score: Option<f32>,
+    /// allow, probation, deny, pending, unknown oder deferred
+    pub decision: &'static str,
+}
+
+const INSERT: &str = "INSERT INTO events (timestamp, camera, frame, track, id, name, score, decision)
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)";
+
+/// Offene Datenbank; jede Zeile wird sofort geschrieben und ist damit gleich abfragbar. Im WAL-Modus mit
+/// `synchronous=NORMAL` kostet das keinen fsync je Zeile, nur noch je Checkpoint.
+pub struct EventDb {
+    connection: Mutex<Connection>,
+    /// Nach dem ersten Schreibfehler nur noch einmal warnen
+    failed: AtomicBool,
+}
+
+impl EventDb {
+    /// Öffnet bzw. legt die Datenbank samt Tabelle an; `:memory:` für eine flüchtige Datenbank
+    pub fn open(path: &str) -> Result<Self, String> {
+        let connection =
+            Connection::open(path).map_err(|e| format!("--event-db {path} konnte nicht geöffnet werden: {e}"))?;
+        connection
+            .execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")
+            .and_then(|()| connection.execute_batch(SCHEMA))
+            .map_err(|e| format!("Tabelle in {path} konnte nicht angelegt werden: {e}"))?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    pub fn insert(&self, row: &Row) -> rusqlite::Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.prepare_cached(INSERT)?.execute(params![
+            row.timestamp,
+            row.camera,
+            row.frame as i64,
+            row.track as i64,
+            row.id,
+            row.name,
+            row.score.filter(|score| score.is_finite()),
+            row.decision,
+        ])?;
+        Ok(())
+    }
+
+    /// Wie `insert`, meldet einen Fehler aber nur als Warnung, damit die Erkennung weiterläuft
+    pub fn record(&self, row: &Row) {
+        if let Err(e) = self.insert(row)
+            && !self.failed.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("Warnung: Ereignis konnte nicht in --event-db geschrieben werden: {e}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row<'a>(id: Option<&'a str>, score: Option<f32>, decision: &'static str) -> Row<'a> {
+        Row {
+            timestamp: "2024-05-01 08:15:00.250".to_string(),
+            camera: "Eingang 'Nord'",
+            frame: 42,
+            track: 7,
+            id,
+            name: None,
+            score,
+            decision,
+        }
+    }
+
+    #[test]
+    fn inserts_rows_with_bound_parameters() {
+        let db = EventDb::open(":memory:").unwrap();
+        db.insert(&row(Some("a'); DROP TABLE events; --"), Some(0.95), "allow")).unwrap();
+        db.insert(&row(None, Some(f32::NAN), "unknown")).unwrap();
+
+        let connection = db.connection.lock().unwrap();
+        let (camera, frame, track): (String, i64, i64) = connection
+            .query_row("SELECT camera, frame, track FROM events LIMIT 1", [], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })
+            .unwrap();
+        assert_eq!((camera.as_str(), frame, track), ("Eingang 'Nord'", 42, 7));
+        let mut statement = connection.prepare("SELECT id, score, decision FROM events ORDER BY rowid").unwrap();
+        let rows: Vec<(Option<String>, Option<f64>, String)> = statement
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].0.as_deref(), Some("a'); DROP TABLE events; --"));
+        assert!((rows[0].1.unwrap() - 0.95).abs() < 1e-6);
+        assert_eq!(rows[1], (None, None, "unknown".to_string()));
+    }
+
+    #[test]
+    fn file_databases_use_the_write_ahead_log() {
+        let path = std::env::temp_dir().join(format!("facerec-events-{}.db", std::process::id()));
+        let db = EventDb::open(path.to_str().unwrap()).unwrap();
+        db.insert(&row(None, None, "unknown")).unwrap();
+        let connection = db.connection.lock().unwrap();
+        let mode: String = connection.query_row("PRAGMA journal_mode", [], |row| row.get(0)).unwrap();
+        let synchronous: i64 = connection.query_row("PRAGMA synchronous", [], |row| row.get(0)).unwrap();
+        assert_eq!((mode.as_str(), synchronous), ("wal", 1));
+        drop(connection);
+        drop(db);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{suffix}", path.display()));
+        }
+    }
+
+    #[test]
+    fn open_fails_for_unusable_path() {
+        assert!(EventDb::open("/nonexistent-directory/events.db").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! Prüfung und Reparatur der Gesichtsdatenbank (`facerec fsck`) sowie Prüfung der Embeddings (`facerec check-db`)
+
+use crate::{FaceEntry, binary, database_path, load_database, or_exit, write_face_data};
+use facer::FacerecError;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+/// Zieldatei für aussortierte Einträge
+const QUARANTINE: &str = "./face_data.quarantine.json";
+
+/// Prüft die Datenbank und meldet doppelte IDs, unlesbare Einträge sowie leere, ungültige (NaN/∞)
+/// oder in der Dimension abweichende Embeddings. Mit `fix` werden betroffene Einträge nach
+/// face_data.quarantine.json verschoben. Liefert `true`, wenn die Datenbank danach fehlerfrei ist.
+pub fn check_database(fix: bool) -> bool {
+    let path = database_path();
+    if binary::is_binary(path) {
+        eprintln!("Fehler: fsck prüft nur JSON-Datenbanken; {path} zuvor mit `facerec convert` umwandeln");
+        return false;
+    }
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Fehler: Konnte {path} nicht öffnen: {e}");
+            return false;
+        }
+    };
+    // Aktuelles Format mit Kopf oder ältere reine Liste
+    let (header_dimension, raw) = match serde_json::from_str::<Value>(&content) {
+        Ok(Value::Array(raw)) => (None, raw),
+        Ok(Value::Object(mut database)) if database.get("faces").is_some_and(Value::is_array) => {
+            let dimension = database.get("dimension").and_then(Value::as_u64).map(|d| d as usize);
+            let Some(Value::Array(raw)) = database.remove("faces") else {
+                unreachable!()
+            };
+            (dimension, raw)
+        }
+        Ok(_) => {
+            eprintln!("Fehler: {path} enthält keine Liste von Einträgen; eine Reparatur ist nicht möglich");
+            return false;
+        }
+        Err(e) => {
+            eprintln!("Fehler: {path} ist kein gültiges JSON ({e}); eine Reparatur ist nicht möglich");
+            return false;
+        }
+    };
+
+    let mut good = Vec::new();
+    let mut bad = Vec::new();
+    let mut parsed = Vec::new();
+    for (index, value) in raw.into_iter().enumerate() {
+        match serde_json::from_value::<FaceEntry>(value.clone()) {
+            Ok(entry) => parsed.push((index, entry, value)),
+            Err(e) => {
+                println!("Eintrag {index}: unlesbar ({e})");
+                bad.push(value);
+            }
+        }
+    }
+
+    let expected = expected_dimension(header_dimension, parsed.iter().map(|(_, entry, _)| entry));
+
+    let mut seen = HashSet::new();
+    for (index, entry, value) in parsed {
+        let mismatched = entry
+            .embeddings
+            .iter()
+            .find(|embedding| expected.is_some_and(|expected| embedding.len() != expected));
+        let problem = if entry.embeddings.is_empty() || entry.embeddings.iter().any(Vec::is_empty) {
+            Some("leeres Embedding".to_string())
+        } else if entry.embeddings.iter().flatten().any(|v| !v.is_finite()) {
+            Some("Embedding enthält NaN oder Unendlich".to_string())
+        } else if let Some(embedding) = mismatched {
+            Some(format!(
+                "Dimension {} statt {}",
+                embedding.len(),
+                expected.unwrap_or_default()
+            ))
+        } else if !seen.insert(entry.id.clone()) {
+            Some("doppelte ID".to_string())
+        } else {
+            None
+        };
+        match problem {
+            Some(problem) => {
+                println!("Eintrag {index} ({}): {problem}", entry.id);
+                bad.push(value);
+            }
+            None => good.push(entry),
+        }
+    }
+
+    let problems = bad.len();
+    if problems == 0 {
+        println!("{} Einträge geprüft, keine Probleme gefunden.", good.len());
+        true
+    } else if fix {
+        or_exit(quarantine(bad));
+        or_exit(write_face_data(&good));
+        println!("{problems} Einträge nach {QUARANTINE} verschoben, {} verbleiben.", good.len());
+        true
+    } else {
+        println!("{problems} Probleme gefunden; mit --fix werden die betroffenen Einträge aussortiert.");
+        false
+    }
+}
+
+/// Maßgeblich ist die Dimension im Kopf, sonst gilt die häufigste als die des aktuellen Extraktors
+fn expected_dimension<'a>(header: Option<usize>, entries: impl Iterator<Item = &'a FaceEntry>) -> Option<usize> {
+    let mut dimensions: HashMap<usize, usize> = HashMap::new();
+    for entry in entries {
+        for embedding in entry.embeddings.iter().filter(|embedding| !embedding.is_empty()) {
+            *dimensions.entry(embedding.len()).or_default() += 1;
+        }
+    }
+    header.or_else(|| {
+        dimensions
+            .into_iter()
+            .max_by_key(|&(dimension, count)| (count, dimension))
+            .map(|(dimension, _)| dimension)
+    })
+}
+
+/// Gibt je Eintrag Dimension und Betrag der Embeddings aus und markiert abweichende Dimensionen,
+/// nicht L2-normierte Embeddings (Betrag weiter als `tolerance` von 1 entfernt) sowie NaN/∞.
+/// Ändert nichts; liefert `true`, wenn kein Eintrag auffällt.
+pub fn check_embeddings(dimension: Option<usize>, tolerance: f32) -> bool {
+    let database = load_database();
+    let expected = dimension.or_else(|| expected_dimension(database.dimension, database.faces.iter()));
+    let mut flagged = 0;
+    for entry in &database.faces {
+        let dimensions: HashSet<usize> = entry.embeddings.iter().map(Vec::len).collect();
+        let norms: Vec<f32> = entry
+            .embeddings
+            .iter()
+            .map(|embedding| embedding.iter().map(|v| v * v).sum::<f32>().sqrt())
+            .collect();
+        let mut problems = Vec::new();
+        if entry.embeddings.iter().flatten().any(|v| !v.is_finite()) {
+            problems.push("NaN oder Unendlich".to_string());
+        }
+        if let Some(expected) = expected
+            && dimensions.iter().any(|&dimension| dimension != expected)
+        {
+            problems.push(format!("abweichende Dimension (erwartet {expected})"));
+        }
+        let unnormalized = norms.iter().filter(|norm| (*norm - 1.0).abs() > tolerance).count();
+        if unnormalized > 0 {
+            problems.push(format!("{unnormalized} nicht normiert"));
+        }
+        let mut dimensions: Vec<usize> = dimensions.into_iter().collect();
+        dimensions.sort_unstable();
+        let dimensions: Vec<String> = dimensions.iter().map(usize::to_string).collect();
+        let min = norms.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = norms.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let magnitude = if norms.is_empty() { "–".to_string() } else { format!("{min:.4}–{max:.4}") };
+        let marker = if problems.is_empty() { String::new() } else { format!(" ⚠ {}", problems.join(", ")) };
+        println!(
+            "{}: {} Aufnahmen, Dimension {}, Betrag {magnitude}{marker}",
+            entry.id,
+            entry.embeddings.len(),
+            if dimensions.is_empty() { "–".to_string() } else { dimensions.join("/") },
+        );
+        if !problems.is_empty() {
+            flagged += 1;
+        }
+    }
+    let expected = expected.map_or("unbekannt".to_string(), |expected| expected.to_string());
+    println!("{} Einträge geprüft (erwartete Dimension {expected}), {flagged} auffällig.", database.faces.len());
+    flagged == 0
+}
+
+/// Hängt aussortierte Einträge an die Quarantänedatei an; ohne sie wird die Datenbank nicht bereinigt
+fn quarantine(mut entries: Vec<Value>) -> Result<(), FacerecError> {
+    let mut existing: Vec<Value> = fs::read_to_string(QUARANTINE)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default();
+    existing.append(&mut entries);
+    let json = serde_json::to_string_pretty(&existing).expect("Fehler beim Serialisieren");
+    fs::write(QUARANTINE, json).map_err(|source| FacerecError::Write { path: QUARANTINE.to_string(), source })
+}
@@ -0,0 +1,146 @@
+//! Geführte Erfassung: der Bediener wird durch mehrere Blickrichtungen geleitet, je Richtung wird
+//! eine gute Aufnahme gespeichert. Ergebnis ist ein Eintrag mit mehreren Embeddings.
+
+use crate::landmarks::Pose;
+use opencv::{
+    core::{self, Mat, Point, Rect, Scalar},
+    imgproc,
+    prelude::*,
+};
+use std::ops::RangeInclusive;
+
+/// Eine Anweisung samt der Kopfhaltung, in der die Aufnahme gelingt
+struct Step {
+    instruction: &'static str,
+    yaw: RangeInclusive<f32>,
+    pitch: RangeInclusive<f32>,
+}
+
+const STEPS: [Step; 4] = [
+    Step {
+        instruction: "Bitte gerade in die Kamera schauen",
+        yaw: -10.0..=10.0,
+        pitch: -10.0..=10.0,
+    },
+    Step {
+        instruction: "Bitte den Kopf leicht nach links drehen",
+        yaw: -40.0..=-15.0,
+        pitch: -15.0..=15.0,
+    },
+    Step {
+        instruction: "Bitte den Kopf leicht nach rechts drehen",
+        yaw: 15.0..=40.0,
+        pitch: -15.0..=15.0,
+    },
+    Step {
+        instruction: "Bitte leicht nach oben schauen",
+        yaw: -15.0..=15.0,
+        pitch: -35.0..=-12.0,
+    },
+];
+
+/// Aufeinanderfolgende passende Frames, bevor eine Aufnahme gespeichert wird
+const HOLD_FRAMES: u32 = 5;
+
+/// Zustand eines Frames aus Sicht der aktuellen Anweisung
+pub enum Observation {
+    /// Kein oder mehr als ein Gesicht im Bild
+    NoFace,
+    /// Gesicht zu klein oder unscharf
+    PoorQuality(&'static str),
+    Pose(Pose),
+}
+
+/// Ergebnis eines Frames
+pub enum Progress {
+    Waiting,
+    /// Die Haltung passt; der Frame soll als Aufnahme dieses Schritts gespeichert werden
+    Capture,
+}
+
+/// Zustandsautomat der geführten Erfassung
+pub struct GuidedEnrollment {
+    step: usize,
+    hold: u32,
+    hint: &'static str,
+}
+
+impl GuidedEnrollment {
+    pub fn new() -> Self {
+        Self {
+            step: 0,
+            hold: 0,
+            hint: "",
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.step >= STEPS.len()
+    }
+
+    /// Bewertet einen Frame; nach `HOLD_FRAMES` passenden Frames in Folge ist der Schritt aufgenommen
+    pub fn observe(&mut self, observation: Observation) -> Progress {
+        let Some(step) = STEPS.get(self.step) else {
+            return Progress::Waiting;
+        };
+        let matches = match observation {
+            Observation::NoFace => {
+                self.hint = "genau ein Gesicht ins Bild";
+                false
+            }
+            Observation::PoorQuality(reason) => {
+                self.hint = reason;
+                false
+            }
+            Observation::Pose(pose) => {
+                let matches = step.yaw.contains(&pose.yaw) && step.pitch.contains(&pose.pitch);
+                self.hint = if matches { "Position halten" } else { "" };
+                matches
+            }
+        };
+        self.hold = if matches { self.hold + 1 } else { 0 };
+        if self.hold < HOLD_FRAMES {
+            return Progress::Waiting;
+        }
+        self.step += 1;
+        self.hold = 0;
+        self.hint = "";
+        Progress::Capture
+    }
+
+    /// Blendet die aktuelle Anweisung und den Fortschritt am oberen Bildrand ein
+    pub fn draw(&self, frame: &mut Mat) {
+        let text = match STEPS.get(self.step) {
+            Some(step) if self.hint.is_empty() => {
+                format!("[{}/{}] {}", self.step + 1, STEPS.len(), step.instruction)
+            }
+            Some(step) => format!("[{}/{}] {} ({})", self.step + 1, STEPS.len(), step.instruction, self.hint),
+            None => "Erfassung abgeschlossen".to_string(),
+        };
+        let banner = Rect::new(0, 0, frame.cols(), 36);
+        imgproc::rectangle(frame, banner, Scalar::new(40.0, 40.0, 40.0, 0.0), imgproc::FILLED, imgproc::LINE_8, 0)
+            .unwrap();
+        imgproc::put_text(
+            frame,
+            &text,
+            Point::new(10, 25),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            0.6,
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            1,
+            imgproc::LINE_AA,
+            false,
+        )
+            .unwrap();
+    }
+}
+
+/// Schärfe eines Graustufenausschnitts als Varianz des Laplace-Operators; unscharfe Bilder liegen niedrig
+pub fn sharpness(gray: &Mat) -> f64 {
+    let mut laplacian = Mat::default();
+    imgproc::laplacian(gray, &mut laplacian, core::CV_64F, 1, 1.0, 0.0, core::BORDER_DEFAULT).unwrap();
+    let (mut mean, mut stddev) = (Mat::default(), Mat::default());
+    core::mean_std_dev(&laplacian, &mut mean, &mut stddev, &core::no_array()).unwrap();
+    let deviation = *stddev.at::<f64>(0).unwrap();
+    deviation * deviation
+}
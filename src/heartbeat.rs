@@ -0,0 +1,150 @@
+//! Regelmäßige Statusmeldung für die unbeaufsichtigte Überwachung (z. B. durch einen externen Watchdog)
+
+use crate::metrics::METRICS;
+use chrono::Local;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Eine Kamera gilt als getrennt, wenn so lange kein Frame mehr verarbeitet wurde
+const DISCONNECTED_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+struct CameraState<'a> {
+    camera: &'a str,
+    connected: bool,
+    /// Sekunden seit dem letzten verarbeiteten Frame; None, wenn noch keiner ankam
+    last_frame_age_s: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct Status<'a> {
+    timestamp: String,
+    uptime_s: u64,
+    frames: u64,
+    fps: f64,
+    /// Seit Start ausgelassene Frames
+    frames_skipped: u64,
+    /// Seit der letzten Meldung wurden Frames ausgelassen, die Verarbeitung hält nicht Schritt
+    skipping: bool,
+    gallery_size: i64,
+    cameras: Vec<CameraState<'a>>,
+}
+
+/// Merkt sich den letzten Frame je Quelle und meldet in festem Takt den Zustand
+pub struct Heartbeat {
+    started: Instant,
+    cameras: Vec<String>,
+    last_frame: Mutex<HashMap<String, Instant>>,
+}
+
+impl Heartbeat {
+    pub fn new(cameras: Vec<String>) -> Self {
+        Self {
+            started: Instant::now(),
+            cameras,
+            last_frame: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Vermerkt einen verarbeiteten Frame der Quelle
+    pub fn frame(&self, camera: &str) {
+        self.last_frame.lock().unwrap().insert(camera.to_string(), Instant::now());
+    }
+
+    /// Gibt alle `interval` eine Statuszeile aus und sendet sie optional per HTTP-POST an `url`,
+    /// bis `stop` gesetzt wird
+    pub fn run(&self, interval: Duration, url: Option<&str>, stop: &AtomicBool) {
+        let mut last_tick = Instant::now();
+        let mut last_frames = METRICS.frames.get();
+        let mut last_skipped = METRICS.frames_skipped.get();
+        while !stop.load(Ordering::Relaxed) {
+            thread::sleep(Duration::from_millis(200));
+            if last_tick.elapsed() < interval {
+                continue;
+            }
+            let frames = METRICS.frames.get();
+            let fps = (frames - last_frames) as f64 / last_tick.elapsed().as_secs_f64();
+            (last_tick, last_frames) = (Instant::now(), frames);
+            let skipped = METRICS.frames_skipped.get();
+            let skipping = skipped > last_skipped;
+            last_skipped = skipped;
+
+            let last_frame = self.last_frame.lock().unwrap();
+            let cameras: Vec<CameraState> = self
+                .cameras
+                .iter()
+                .map(|camera| {
+                    let age = last_frame.get(camera).map(Instant::elapsed);
+                    CameraState {
+                        camera,
+                        connected: age.is_some_and(|age| age < DISCONNECTED_AFTER),
+                        last_frame_age_s: age.map(|age| age.as_secs_f64()),
+                    }
+                })
+                .collect();
+            let status = Status {
+                timestamp: Local::now().to_rfc3339(),
+                uptime_s: self.started.elapsed().as_secs(),
+                frames,
+                fps,
+                frames_skipped: skipped,
+                skipping,
+                gallery_size: METRICS.gallery_size.get(),
+                cameras,
+            };
+            let connected: Vec<String> = status
+                .cameras
+                .iter()
+                .map(|state| format!("{} {}", state.camera, if state.connected { "verbunden" } else { "GETRENNT" }))
+                .collect();
+            let skipping = if status.skipping {
+                format!(" ({} ausgelassen)", status.frames_skipped)
+            } else {
+                String::new()
+            };
+            crate::say!(
+                "Status: Laufzeit {} s, {} Frames{skipping}, {:.1} FPS, {} Gesichter gespeichert, {}",
+                status.uptime_s,
+                status.frames,
+                status.fps,
+                status.gallery_size,
+                connected.join(", ")
+            );
+            if let Some(url) = url {
+                let body = serde_json::to_string(&status).expect("Fehler beim Serialisieren");
+                if let Err(e) = post(url, &body) {
+                    eprintln!("Warnung: Statusmeldung an {url} fehlgeschlagen: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Minimaler HTTP/1.1-POST ohne TLS (`http://host:port/pfad`), ausreichend für einen Watchdog im lokalen Netz
+pub fn post(url: &str, body: &str) -> Result<(), String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| "nur http:// wird unterstützt".to_string())?;
+    let (host, path) = rest.split_once('/').map_or((rest, "/".to_string()), |(host, path)| (host, format!("/{path}")));
+    let address = if host.contains(':') { host.to_string() } else { format!("{host}:80") };
+    let mut stream = TcpStream::connect(&address).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(Duration::from_secs(5))).map_err(|e| e.to_string())?;
+    write!(
+        stream,
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )
+        .map_err(|e| e.to_string())?;
+    let mut status_line = [0u8; 12];
+    stream.read_exact(&mut status_line).map_err(|e| e.to_string())?;
+    match &status_line[9..10] {
+        b"2" => Ok(()),
+        _ => Err(String::from_utf8_lossy(&status_line).trim().to_string()),
+    }
+}
@@ -0,0 +1,66 @@
+//! Verteilung der besten Ähnlichkeiten über eine Sitzung (--score-histogram), zur Wahl des Schwellwerts
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Anzahl der Klassen im Bereich 0..1
+const BINS: usize = 20;
+/// Breite des längsten Balkens in Zeichen
+const BAR_WIDTH: u64 = 50;
+
+/// Zählt die Ähnlichkeiten je Klasse; wird von allen Kamera-Threads gemeinsam genutzt
+pub struct ScoreHistogram {
+    counts: Mutex<[u64; BINS]>,
+}
+
+impl ScoreHistogram {
+    pub fn new() -> Self {
+        Self {
+            counts: Mutex::new([0; BINS]),
+        }
+    }
+
+    /// Erfasst die Ähnlichkeit des besten Treffers; Werte außerhalb von 0..1 fallen in die Randklassen
+    pub fn record(&self, score: f32) {
+        let bin = ((score.clamp(0.0, 1.0) * BINS as f32) as usize).min(BINS - 1);
+        self.counts.lock().unwrap()[bin] += 1;
+    }
+
+    /// Gibt das Histogramm aus und schreibt es optional als CSV (untere Grenze, obere Grenze, Anzahl)
+    pub fn report(&self, threshold: f32, csv: Option<&str>) {
+        let counts = self.counts.lock().unwrap();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            crate::say!("Keine Ähnlichkeiten erfasst.");
+            return;
+        }
+        let max = counts.iter().copied().max().unwrap_or(1);
+        crate::say!("Verteilung der besten Ähnlichkeiten über {total} Gesichter (Schwellwert {threshold}):");
+        for (bin, &count) in counts.iter().enumerate() {
+            let (low, high) = bounds(bin);
+            let marker = if (low..high).contains(&threshold) { '<' } else { ' ' };
+            let bar = "#".repeat((count * BAR_WIDTH / max) as usize);
+            crate::say!("  {low:.2}–{high:.2} {count:>7} {bar}{marker}");
+        }
+        if let Some(path) = csv
+            && let Err(e) = write_csv(path, &counts)
+        {
+            eprintln!("Warnung: Histogramm {path} nicht geschrieben: {e}");
+        }
+    }
+}
+
+fn write_csv(path: &str, counts: &[u64; BINS]) -> io::Result<()> {
+    let mut file = File::create(path)?;
+    writeln!(file, "low,high,count")?;
+    for (bin, count) in counts.iter().enumerate() {
+        let (low, high) = bounds(bin);
+        writeln!(file, "{low:.2},{high:.2},{count}")?;
+    }
+    Ok(())
+}
+
+fn bounds(bin: usize) -> (f32, f32) {
+    (bin as f32 / BINS as f32, (bin + 1) as f32 / BINS as f32)
+}
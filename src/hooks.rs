@@ -0,0 +1,48 @@
+//! Aktion bei der Erfassung einer neuen Person, z. B. eine Begrüßung oder ein Hinweis an den Administrator.
+//! Läuft im Hintergrund; Fehler werden gemeldet, halten die Erkennung aber nicht auf.
+
+use crate::AccessLevel;
+use crate::heartbeat::post;
+use serde::Serialize;
+use std::process::Command;
+use std::thread;
+
+/// Daten des neuen Eintrags; als JSON an die Webhook-Adresse, als Umgebungsvariablen an den Befehl
+#[derive(Serialize)]
+pub struct Enrolled {
+    pub timestamp: String,
+    pub camera: String,
+    pub id: String,
+    pub name: Option<String>,
+    pub access: AccessLevel,
+    pub allowed: bool,
+}
+
+/// Startet den Befehl (über `sh -c`) und/oder sendet den Webhook
+pub fn fire(command: Option<&str>, url: Option<&str>, event: Enrolled) {
+    let command = command.map(str::to_string);
+    let url = url.map(str::to_string);
+    thread::spawn(move || {
+        if let Some(command) = command {
+            let status = Command::new("sh")
+                .arg("-c")
+                .arg(&command)
+                .env("FACEREC_ID", &event.id)
+                .env("FACEREC_NAME", event.name.as_deref().unwrap_or(""))
+                .env("FACEREC_ALLOWED", event.allowed.to_string())
+                .env("FACEREC_CAMERA", &event.camera)
+                .status();
+            match status {
+                Ok(status) if status.success() => {}
+                Ok(status) => eprintln!("Warnung: --on-enroll endete mit {status}"),
+                Err(e) => eprintln!("Warnung: --on-enroll konnte nicht gestartet werden: {e}"),
+            }
+        }
+        if let Some(url) = url {
+            let body = serde_json::to_string(&event).expect("Fehler beim Serialisieren");
+            if let Err(e) = post(&url, &body) {
+                eprintln!("Warnung: Erfassungsmeldung an {url} fehlgeschlagen: {e}");
+            }
+        }
+    });
+}
@@ -0,0 +1,148 @@
+//! Erkennung von Gesichtsmerkmalen (68-Punkte-Schema, OpenCV FacemarkLBF)
+
+use opencv::{
+    core::{Point, Point2f, Ptr, Rect, Scalar, Vector},
+    face, imgproc,
+    prelude::*,
+};
+use facer::FacerecError;
+use facer::preprocessing::Eyes;
+use std::ops::Range;
+
+/// Indizes der Augenpunkte im 68-Punkte-Schema
+const LEFT_EYE: Range<usize> = 36..42;
+const RIGHT_EYE: Range<usize> = 42..48;
+const NOSE_TIP: usize = 30;
+const MOUTH: Range<usize> = 48..68;
+/// Relative Lage der Nasenspitze zwischen Augenlinie und Mund bei frontalem Blick
+const NEUTRAL_NOSE_DEPTH: f32 = 0.55;
+
+/// Landmarken-Detektor auf Basis eines trainierten LBF-Modells (z. B. lbfmodel.yaml)
+pub struct LandmarkDetector {
+    facemark: Ptr<face::Facemark>,
+}
+
+impl LandmarkDetector {
+    pub fn new(model_path: &str) -> Result<Self, FacerecError> {
+        let mut facemark = face::create_facemark_lbf()?;
+        facemark.load_model(model_path).map_err(|e| {
+            FacerecError::Model(format!("Landmarken-Modell {model_path} konnte nicht geladen werden: {e}"))
+        })?;
+        Ok(Self { facemark })
+    }
+
+    /// Bestimmt die Landmarken aller Gesichter; die Reihenfolge entspricht `faces`.
+    /// Schlägt die Anpassung fehl, ist die Liste für jedes Gesicht leer.
+    pub fn detect(&mut self, gray: &Mat, faces: &Vector<Rect>) -> Vec<Vec<Point2f>> {
+        let mut landmarks = Vector::<Vector<Point2f>>::new();
+        if faces.is_empty() || !self.facemark.fit(gray, faces, &mut landmarks).unwrap_or(false) {
+            return vec![Vec::new(); faces.len()];
+        }
+        landmarks.iter().map(|points| points.to_vec()).collect()
+    }
+}
+
+/// Augenmittelpunkte relativ zum Gesichtsrahmen `face`, für die Ausrichtung (`align`) in der Vorverarbeitung
+pub fn eyes(points: &[Point2f], face: Rect) -> Option<Eyes> {
+    if points.len() < 68 || face.width <= 0 || face.height <= 0 {
+        return None;
+    }
+    let relative = |p: Point2f| {
+        ((p.x - face.x as f32) as f64 / face.width as f64, (p.y - face.y as f32) as f64 / face.height as f64)
+    };
+    Some(Eyes {
+        left: relative(centroid(&points[LEFT_EYE])),
+        right: relative(centroid(&points[RIGHT_EYE])),
+    })
+}
+
+/// Abstand der beiden Augenmittelpunkte in Pixeln
+pub fn inter_eye_distance(points: &[Point2f]) -> Option<f32> {
+    if points.len() < 68 {
+        return None;
+    }
+    let left = centroid(&points[LEFT_EYE]);
+    let right = centroid(&points[RIGHT_EYE]);
+    Some((left.x - right.x).hypot(left.y - right.y))
+}
+
+/// Grobe Kopfhaltung in Grad
+#[derive(Clone, Copy, Debug)]
+pub struct Pose {
+    /// Drehung nach links/rechts; 0 = frontal
+    pub yaw: f32,
+    /// Neigung nach oben/unten; positiv = nach unten
+    pub pitch: f32,
+}
+
+/// Schätzt die Kopfhaltung aus der Lage der Nasenspitze relativ zu Augen und Mund.
+/// Die Näherung kommt ohne 3D-Modell aus und genügt, um stark abgewandte Gesichter zu erkennen.
+pub fn estimate_pose(points: &[Point2f]) -> Option<Pose> {
+    if points.len() < 68 {
+        return None;
+    }
+    let left = centroid(&points[LEFT_EYE]);
+    let right = centroid(&points[RIGHT_EYE]);
+    let eye_distance = (right.x - left.x).hypot(right.y - left.y);
+    if eye_distance <= f32::EPSILON {
+        return None;
+    }
+    // Achsen entlang und senkrecht zur Augenlinie, damit eine seitliche Kopfneigung nicht mitzählt
+    let (ux, uy) = ((right.x - left.x) / eye_distance, (right.y - left.y) / eye_distance);
+    let (vx, vy) = (-uy, ux);
+    let middle = Point2f::new((left.x + right.x) / 2.0, (left.y + right.y) / 2.0);
+    let along = |p: Point2f| (p.x - middle.x) * ux + (p.y - middle.y) * uy;
+    let across = |p: Point2f| (p.x - middle.x) * vx + (p.y - middle.y) * vy;
+
+    let nose = points[NOSE_TIP];
+    let mouth_depth = across(centroid(&points[MOUTH]));
+    if mouth_depth <= f32::EPSILON {
+        return None;
+    }
+    let yaw = (2.0 * along(nose) / eye_distance).clamp(-1.0, 1.0).asin();
+    let pitch = (2.0 * (across(nose) / mouth_depth - NEUTRAL_NOSE_DEPTH)).clamp(-1.0, 1.0).asin();
+    Some(Pose {
+        yaw: yaw.to_degrees(),
+        pitch: pitch.to_degrees(),
+    })
+}
+
+/// Linienzüge des 68-Punkte-Schemas: Bereich und ob der Zug geschlossen ist
+const OUTLINES: [(Range<usize>, bool); 9] = [
+    (0..17, false),  // Kinnlinie
+    (17..22, false), // linke Augenbraue
+    (22..27, false), // rechte Augenbraue
+    (27..31, false), // Nasenrücken
+    (31..36, false), // Nasenflügel
+    (LEFT_EYE, true),
+    (RIGHT_EYE, true),
+    (48..60, true), // Lippen außen
+    (60..68, true), // Lippen innen
+];
+
+/// Zeichnet die Landmarken samt Verbindungslinien zur Diagnose von Ausrichtung und Kopfhaltung
+pub fn draw(frame: &mut Mat, points: &[Point2f]) {
+    let color = Scalar::new(255.0, 255.0, 0.0, 0.0); // cyan
+    let to_pixel = |p: Point2f| Point::new(p.x.round() as i32, p.y.round() as i32);
+    if points.len() >= 68 {
+        for (range, closed) in OUTLINES {
+            let outline = &points[range];
+            let segments = outline.windows(2).map(|pair| (pair[0], pair[1]));
+            let closing = closed.then(|| (outline[outline.len() - 1], outline[0]));
+            for (from, to) in segments.chain(closing) {
+                imgproc::line(frame, to_pixel(from), to_pixel(to), color, 1, imgproc::LINE_AA, 0).unwrap();
+            }
+        }
+    }
+    for &point in points {
+        imgproc::circle(frame, to_pixel(point), 2, color, imgproc::FILLED, imgproc::LINE_AA, 0).unwrap();
+    }
+}
+
+fn centroid(points: &[Point2f]) -> Point2f {
+    let n = points.len() as f32;
+    let (sum_x, sum_y) = points
+        .iter()
+        .fold((0.0, 0.0), |(x, y), p| (x + p.x, y + p.y));
+    Point2f::new(sum_x / n, sum_y / n)
+}
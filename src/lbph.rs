@@ -0,0 +1,90 @@
+//! Klassischer Abgleich mit OpenCVs LBPH-Erkenner als Alternative zu Embedding und Kosinus-Ähnlichkeit.
+//! Trainiert wird aus den gespeicherten Gesichtsausschnitten; jede Neuerfassung wird nachtrainiert.
+
+use crate::{FaceEntry, FacerecError, MATCH_THRESHOLD};
+use opencv::{
+    core::{Mat, Ptr, Size, Vector},
+    face, imgcodecs, imgproc,
+    prelude::*,
+};
+
+/// Einheitliche Kantenlänge der Trainings- und Suchausschnitte
+const FACE_SIZE: i32 = 100;
+
+/// LBPH-Modell samt Zuordnung der Labels zu Einträgen der Datenbank
+pub struct LbphBackend {
+    recognizer: Ptr<face::LBPHFaceRecognizer>,
+    /// Label i gehört zum Eintrag ids[i]
+    ids: Vec<String>,
+    /// LBPH-Distanz, die dem Schwellwert der Ähnlichkeit entspricht
+    threshold: f64,
+}
+
+impl LbphBackend {
+    /// Trainiert das Modell aus den Ausschnitten der Einträge; Einträge ohne lesbaren Ausschnitt fehlen im Modell
+    pub fn train(faces: &[FaceEntry], threshold: f64) -> Result<Self, FacerecError> {
+        let mut backend = Self {
+            recognizer: face::LBPHFaceRecognizer::create_def()?,
+            ids: Vec::new(),
+            threshold,
+        };
+        let mut images = Vector::<Mat>::new();
+        let mut labels = Vector::<i32>::new();
+        for entry in faces {
+            let Some(crop) = entry
+                .crop
+                .as_deref()
+                .and_then(|path| imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE).ok())
+                .filter(|crop| !crop.empty())
+            else {
+                eprintln!("Warnung: {} hat keinen lesbaren Ausschnitt und wird von LBPH nicht erkannt", entry.id);
+                continue;
+            };
+            images.push(normalize(&crop)?);
+            labels.push(backend.ids.len() as i32);
+            backend.ids.push(entry.id.clone());
+        }
+        if !images.is_empty() {
+            backend.recognizer.train(&images, &labels)?;
+        }
+        Ok(backend)
+    }
+
+    /// Nimmt einen neu erfassten Ausschnitt in das Modell auf
+    pub fn update(&mut self, id: &str, face: &Mat) -> Result<(), FacerecError> {
+        let images = Vector::<Mat>::from_iter([normalize(face)?]);
+        let label = self.ids.len() as i32;
+        let labels = Vector::<i32>::from_iter([label]);
+        // Das erste Training muss über train laufen, danach ergänzt update das Modell
+        if self.ids.is_empty() {
+            self.recognizer.train(&images, &labels)
+        } else {
+            self.recognizer.update(&images, &labels)
+        }?;
+        self.ids.push(id.to_string());
+        Ok(())
+    }
+
+    /// Liefert die ID des nächstgelegenen Eintrags und eine Ähnlichkeit in (0, 1].
+    /// Die Distanz wird so umgerechnet, dass `threshold` genau MATCH_THRESHOLD entspricht, damit
+    /// Schwellwert, Glättung und Richtlinie unverändert greifen.
+    pub fn predict(&self, face: &Mat) -> Result<Option<(String, f32)>, FacerecError> {
+        if self.ids.is_empty() {
+            return Ok(None);
+        }
+        let mut label = -1;
+        let mut distance = 0.0;
+        self.recognizer.predict(&normalize(face)?, &mut label, &mut distance)?;
+        let Some(id) = usize::try_from(label).ok().and_then(|label| self.ids.get(label)) else {
+            return Ok(None);
+        };
+        let similarity = MATCH_THRESHOLD.powf((distance / self.threshold) as f32);
+        Ok(Some((id.clone(), similarity)))
+    }
+}
+
+fn normalize(face: &Mat) -> opencv::Result<Mat> {
+    let mut resized = Mat::default();
+    imgproc::resize(face, &mut resized, Size::new(FACE_SIZE, FACE_SIZE), 0.0, 0.0, imgproc::INTER_LINEAR)?;
+    Ok(resized)
+}
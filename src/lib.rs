@@ -0,0 +1,44 @@
+//! Erkennungslogik von facerec als Bibliothek: Gesichtssuche, Merkmalsextraktion, Abgleich und die Galerie
+//! samt Datenbankdatei, ohne Kameraschleife und Rückfragen. Das Programm `facerec` baut darauf auf.
+//! Fehler werden als `FacerecError` zurückgegeben, statt das Programm zu beenden.
+//!
+//! ```no_run
+//! use facer::{Embedder, FaceDetector, Matcher};
+//! use facer::detection::DetectorConfig;
+//! use facer::embedding::ModelConfig;
+//! use facer::matching::IndexKind;
+//! use opencv::{core::Mat, imgcodecs, prelude::*};
+//!
+//! let faces = facer::storage::try_read_database("face_data.json")?.faces;
+//! let matcher = Matcher::new(faces, IndexKind::BruteForce);
+//! let mut detector = FaceDetector::new("haarcascade_frontalface_default.xml", DetectorConfig::default())?;
+//! let mut embedder = Embedder::load("face_embedding.onnx", ModelConfig::default(), false)?;
+//! let gray = imgcodecs::imread("besucher.jpg", imgcodecs::IMREAD_GRAYSCALE).unwrap();
+//! for face in detector.detect(&gray)? {
+//!     let crop = Mat::roi(&gray, face).unwrap().try_clone().unwrap();
+//!     if let Some((entry, score)) = matcher.identify(&embedder.extract(&crop)?) {
+//!         println!("{} ({score:.2})", entry.id);
+//!     }
+//! }
+//! # Ok::<(), facer::FacerecError>(())
+//! ```
+
+pub mod binary;
+pub mod detection;
+pub mod embedding;
+pub mod error;
+pub mod matching;
+pub mod pca;
+pub mod preprocessing;
+pub mod storage;
+#[cfg(test)]
+mod test_rng;
+
+pub use detection::FaceDetector;
+pub use embedding::Embedder;
+pub use error::{FacerecError, Result};
+pub use matching::Matcher;
+pub use storage::{AccessLevel, Database, FaceEntry};
+
+/// Kurzname für die Gesichtssuche, passend zu `Embedder` und `Matcher`
+pub type Detector = FaceDetector;
@@ -1,201 +1,4252 @@
+mod audit_query;
+mod batch;
+mod calibration;
+mod camera_lock;
+mod capture;
+mod clustering;
+mod dedupe;
+mod detection_eval;
+mod door;
+mod embedding_cache;
+mod event_db;
+mod eval_export;
+mod enrollment;
+mod fsck;
+mod guided;
+mod heartbeat;
+mod histogram;
+mod hooks;
+mod landmarks;
+mod lbph;
+mod metrics;
+mod overrides;
+mod pin;
+mod policy;
+mod profiling;
+mod protocol;
+mod raw_input;
+mod rates;
+mod snapshots;
+mod timecode;
+mod tracking;
+
+use base64::prelude::*;
+use facer::{FacerecError, binary, detection, embedding, matching, pca};
+use facer::preprocessing::{Pipeline, Step};
+use facer::storage::{AccessLevel, Database, FaceEntry, deterministic_id, store_database, try_read_database};
+use chrono::{DateTime, Local, TimeDelta};
+use camera_lock::CameraLock;
+use capture::{Captured, FrameSlot, Put};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use detection::{DetectorConfig, FaceDetector};
+use detection_eval::DetectionScore;
+use door::Door;
+use embedding::{Embedder, ModelConfig, ModelPreset};
+use embedding_cache::EmbeddingCache;
+use event_db::EventDb;
+use eval_export::EvalDataset;
+use enrollment::{AccessType, EnrollRequest, GuiPrompt};
+use heartbeat::Heartbeat;
+use histogram::ScoreHistogram;
+use guided::{GuidedEnrollment, Observation, Progress};
+use landmarks::{LandmarkDetector, estimate_pose, inter_eye_distance};
+use lbph::LbphBackend;
+use matching::{
+    Candidate, Gallery, IndexKind, MATCH_THRESHOLD, NeighborIndex, SHORTLIST, TieBreak, TiePreference, break_ties,
+    cosine_similarity,
+};
+use metrics::METRICS;
+use notify::{RecursiveMode, Watcher};
+use overrides::{Override, OverrideLists};
+use pca::Projection;
+use pin::{PinChecks, PinStatus};
+use policy::{AccessPolicy, Decision, DefaultPolicy, FrameContext, StrictPolicy};
+use profiling::{DetectionCadence, DriftMonitor, Profiler, Schedule, Stage, Throttle};
+use protocol::{Framing, Message};
+use raw_input::{RawInput, RawReader};
+use snapshots::{BestFrames, SnapshotStore};
+use timecode::Timecode;
+use tracking::Tracker;
 use opencv::{
-    core::{Vector, Size, Scalar, Point},
-    highgui, imgproc, objdetect, prelude::*, videoio,
+    core::{self, Vector, Size, Scalar, Point, Rect, ToInputArray},
+    highgui, imgcodecs, imgproc, prelude::*, videoio,
 };
-use serde::{Deserialize, Serialize};
-use std::fs::{File, OpenOptions};
-use std::io::{self, Read, Write};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender, SyncSender};
+use std::sync::{Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
 use uuid::Uuid;
 
-const DATABASE: &str = "./face_data.json";
+const DATABASE: &str = "./face_data.json";
+const CASCADE: &str = "./haarcascade_frontalface_default.xml";
+const CROP_DIR: &str = "./face_crops";
+const MODEL: &str = "./face_embedding.onnx";
+
+/// Bereits getroffene Entscheidungen des aktuellen Frames je ID und Zugang (mit Warten auf Unterstützung),
+/// damit eine doppelt erkannte Person ihre Aktionen nur einmal auslöst. Alle Rahmen werden weiterhin gezeichnet.
+#[derive(Default)]
+struct FrameActions {
+    decided: HashMap<(String, bool), bool>,
+}
+
+impl FrameActions {
+    /// Ob ein früherer Rahmen derselben ID schon gleich entschieden wurde, und wenn ja, ob er in der Kulanzzeit
+    /// wartet. Gesichter ohne ID und abweichende Entscheidungen werden nie zusammengefasst.
+    fn earlier(&self, id: Option<&str>, allowed: bool) -> Option<bool> {
+        self.decided.get(&(id?.to_string(), allowed)).copied()
+    }
+
+    fn record(&mut self, decision: &FaceDecision) {
+        if let Some(id) = &decision.id
+            && !decision.deferred
+        {
+            self.decided.entry((id.clone(), decision.allowed)).or_insert(decision.pending);
+        }
+    }
+}
+
+/// Zweiter Faktor: die Erkennung allein öffnet noch nicht. None, solange die PIN aussteht. Jeder Rahmen wird
+/// für sich geprüft, auch wenn dieselbe ID in diesem Frame schon bestätigt wurde (etwa ein Foto neben der Person).
+fn second_factor(verdict: Decision, status: PinStatus) -> Option<Decision> {
+    match status {
+        PinStatus::Confirmed => Some(verdict),
+        PinStatus::Denied => Some(Decision::Deny),
+        PinStatus::Waiting => None,
+    }
+}
+
+/// Entscheidung für ein einzelnes erkanntes Gesicht
+#[derive(Serialize, Clone)]
+struct FaceDecision {
+    track: u64, // Spur-ID über aufeinanderfolgende Frames
+    bbox: [i32; 4], // x, y, Breite, Höhe
+    id: Option<String>, // None, wenn weder erkannt noch erfasst
+    score: Option<f32>, // geglättete Ähnlichkeit, None bei Neuerfassung
+    // Ohne Treffer: Ähnlichkeit des besten Kandidaten; fehlt unter --score-floor (kein aussagekräftiger Treffer)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    best_score: Option<f32>,
+    allowed: bool,
+    review: bool, // Zugang auf Probe: Ereignis zur Prüfung vorgemerkt
+    pending: bool, // verweigert, aber noch in der Kulanzzeit vor dem Alarm
+    deferred: bool, // Ausschnitt zu schlecht: keine Entscheidung, es wird auf einen besseren Frame gewartet
+    #[serde(rename = "override", skip_serializing_if = "Option::is_none")]
+    overridden: Option<Override>, // Liste, die das gespeicherte Zugangsrecht überstimmt hat
+}
+
+impl FaceDecision {
+    /// Kurzbezeichnung der Entscheidung für Auswertungen
+    fn outcome(&self) -> &'static str {
+        if self.deferred {
+            "deferred"
+        } else if self.allowed && self.review {
+            "probation"
+        } else if self.allowed {
+            "allow"
+        } else if self.pending {
+            "pending"
+        } else if self.id.is_none() {
+            "unknown"
+        } else {
+            "deny"
+        }
+    }
+}
+
+/// Eine Zeile der Ergebnisdatei im Videomodus
+#[derive(Serialize)]
+struct FrameResult<'a> {
+    frame: u64,
+    timestamp_ms: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timecode: Option<&'a str>,
+    faces: &'a [FaceDecision],
+}
+
+#[derive(Parser)]
+#[command(name = "facerec", about = "Gesichtserkennung mit Zugangskontrolle")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    run: RunArgs,
+    #[command(flatten)]
+    model: ModelArgs,
+    #[command(flatten)]
+    detector: DetectorArgs,
+    /// Anzahl der rotierten Sicherungen, die vor jedem Schreiben der Datenbank angelegt werden (0 = keine)
+    #[arg(long, global = true, default_value_t = 3)]
+    backups: usize,
+    /// Gesichtsdatenbank; mit der Endung .bin im binären Format (schneller bei großen Galerien)
+    #[arg(long, global = true, default_value = DATABASE)]
+    database: String,
+}
+
+/// Einstellungen des Embedding-Modells, gültig für alle Befehle
+#[derive(Args)]
+struct ModelArgs {
+    /// Embedding-Modell in einem von OpenCV DNN lesbaren Format (z. B. ONNX)
+    #[arg(long, global = true, default_value = MODEL)]
+    model: String,
+    /// Fehlt das Modell, mit ungenauen Ersatzmerkmalen aus Pixelwerten weiterarbeiten
+    #[arg(long, global = true)]
+    allow_dummy_features: bool,
+    /// Vorverarbeitung eines verbreiteten Modells (Eingabegröße, Mittelwert, Skalierung)
+    #[arg(long, global = true, value_enum)]
+    model_preset: Option<ModelPreset>,
+    /// JSON-Datei mit der Vorverarbeitung des Modells (input_size, mean, scale, swap_rb, preprocessing);
+    /// hat Vorrang vor --model-preset
+    #[arg(long, global = true)]
+    model_config: Option<String>,
+    /// Embeddings unveränderter Bilder bei `reindex` und `calibrate` aus dieser Datei übernehmen statt neu zu
+    /// berechnen; nach einem Modellwechsel wird sie neu aufgebaut
+    #[arg(long, global = true)]
+    embedding_cache: Option<String>,
+}
+
+impl ModelArgs {
+    /// Lädt den Extraktor oder beendet das Programm mit einer verständlichen Fehlermeldung
+    fn embedder(&self) -> Embedder {
+        Embedder::load(&self.model, self.config(), self.allow_dummy_features).unwrap_or_else(|e| {
+            eprintln!("Fehler: {e}");
+            std::process::exit(1);
+        })
+    }
+
+    /// Wie `embedder` für Befehle ohne Landmarken; ein Schritt `align` kann dann nur skalieren
+    fn unaligned_embedder(&self) -> Embedder {
+        let embedder = self.embedder();
+        warn_unaligned(&embedder);
+        embedder
+    }
+
+    fn config(&self) -> ModelConfig {
+        match (&self.model_config, self.model_preset) {
+            (Some(path), _) => ModelConfig::load(path).unwrap_or_else(|e| {
+                eprintln!("Fehler: {e}");
+                std::process::exit(1);
+            }),
+            (None, Some(preset)) => ModelConfig::preset(preset),
+            (None, None) => ModelConfig::default(),
+        }
+    }
+
+    /// Hash der Modelldatei und ihrer Vorverarbeitung, denn beide bestimmen die Embeddings;
+    /// None ohne lesbares Modell (Ersatzmerkmale)
+    fn fingerprint(&self) -> Option<String> {
+        embedding::model_identity(&self.model, &self.config())
+    }
+
+    /// Zwischenspeicher aus --embedding-cache für die Embeddings dieses Extraktors
+    fn embedding_cache(&self, embedder: &Embedder) -> Option<EmbeddingCache> {
+        let path = self.embedding_cache.as_deref()?;
+        Some(EmbeddingCache::open(path, embedder.identity(&self.model)))
+    }
+}
+
+/// Ohne Lage der Augen werden die Ausschnitte nicht ausgerichtet und passen schlechter zu ausgerichteten Aufnahmen
+fn warn_unaligned(embedder: &Embedder) {
+    if embedder.aligns() {
+        eprintln!("Warnung: ohne --landmark-model richtet `align` die Ausschnitte nicht aus, sondern skaliert sie nur");
+    }
+}
+
+/// Ob zwei Kennungen aus `ModelArgs::fingerprint` dasselbe Modell bezeichnen. Ältere Datenbanken vermerken
+/// nur den Hash der Modelldatei ohne Vorverarbeitung; er passt zu jeder Vorverarbeitung desselben Modells.
+fn same_model(a: &str, b: &str) -> bool {
+    a == b || (!a.contains('-') || !b.contains('-')) && a.split('-').next() == b.split('-').next()
+}
+
+/// Einstellungen der Gesichtssuche, gültig für alle Befehle
+#[derive(Args)]
+struct DetectorArgs {
+    /// JSON-Datei mit Parametern für detect_multi_scale (scale_factor, min_neighbors, flags, min_size, max_size)
+    #[arg(long, global = true)]
+    detector_config: Option<String>,
+    /// Zweiter Cascade, der jedes gefundene Gesicht bestätigen muss (überschreibt confirm_cascade der Konfiguration)
+    #[arg(long, global = true)]
+    confirm_cascade: Option<String>,
+}
+
+impl DetectorArgs {
+    /// Liest die Parameter (bzw. die Standardwerte) oder beendet das Programm mit einer Fehlermeldung
+    fn config(&self) -> DetectorConfig {
+        let mut config = match &self.detector_config {
+            Some(path) => DetectorConfig::load(path).unwrap_or_else(|e| {
+                eprintln!("Fehler: {e}");
+                std::process::exit(1);
+            }),
+            None => DetectorConfig::default(),
+        };
+        if let Some(path) = &self.confirm_cascade {
+            config.confirm_cascade = Some(path.clone());
+        }
+        config
+    }
+
+    fn detector(&self) -> FaceDetector {
+        or_exit(FaceDetector::new(CASCADE, self.config()))
+    }
+}
+
+/// Unkenntlichmachen von Gesichtern in der Ausgabe (Anzeige, Ergebnisbilder, abgelegte Ausschnitte).
+/// Erkannt wird weiterhin auf dem unveränderten Bild.
+#[derive(Args)]
+struct PrivacyArgs {
+    /// Gesichter ohne zugeordneten Eintrag (Unbeteiligte) weichzeichnen
+    #[arg(long)]
+    blur_unknown: bool,
+    /// Gesichter mit zugeordnetem Eintrag weichzeichnen
+    #[arg(long)]
+    blur_known: bool,
+}
+
+impl PrivacyArgs {
+    fn masks(&self, decision: &FaceDecision) -> bool {
+        if decision.id.is_some() { self.blur_known } else { self.blur_unknown }
+    }
+}
+
+/// Optionen für die Erkennungsschleife
+#[derive(Args)]
+struct RunArgs {
+    /// Kameraindex; mehrfach angeben, um mehrere Kameras gleichzeitig zu betreiben
+    #[arg(long, default_value = "0", conflicts_with_all = ["video", "raw_input"])]
+    camera_index: Vec<i32>,
+    /// Videodatei statt der Kamera verarbeiten
+    #[arg(long, conflicts_with = "raw_input")]
+    video: Option<String>,
+    /// Unkomprimierte Frames von der Standardeingabe oder einer Named Pipe lesen,
+    /// z. B. `format=bgr24,1280x720` oder `format=gray,640x480,path=/tmp/kamera.fifo`.
+    /// Wie bei einer Live-Kamera wird stets der frischeste Frame verarbeitet.
+    #[arg(long, value_parser = RawInput::parse)]
+    raw_input: Option<RawInput>,
+    /// Stromsparbetrieb: nur alle so viele Sekunden einen einzelnen, frischen Frame verarbeiten
+    #[arg(long, value_parser = parse_seconds, conflicts_with_all = ["video", "raw_input"])]
+    interval: Option<f64>,
+    /// Kameraeinstellung nach dem Öffnen setzen, z. B. `exposure=-6`, `gain=0` oder `autofocus=0`;
+    /// mehrfach angeben. Feste Belichtung und Schärfe machen die Embeddings gleichmäßiger.
+    #[arg(long, value_parser = parse_cam_prop, conflicts_with_all = ["video", "raw_input"])]
+    cam_prop: Vec<CameraProperty>,
+    /// Frames, die bei Verarbeitungsbeginn mehr als so viele Sekunden hinter Echtzeit liegen, auslassen,
+    /// damit Ereignisse zeitnah bleiben. Bei Videodateien zählt der Abstand zur Abspielposition.
+    #[arg(long, value_parser = parse_seconds)]
+    max_lag: Option<f64>,
+    /// Nur in jedem so vielten Frame erkennen und entscheiden; dazwischen werden die Rahmen des letzten
+    /// erkannten Frames angezeigt und keine Ergebnisse geschrieben. Mit --target-fps die kleinste Schrittweite.
+    #[arg(long, default_value_t = 1)]
+    detect_every: u32,
+    /// Schrittweite von --detect-every laufend an die gemessene Verarbeitungszeit anpassen, um diese
+    /// Bildrate der Anzeige zu halten
+    #[arg(long, value_parser = parse_fps)]
+    target_fps: Option<f64>,
+    /// Größte Schrittweite, die --target-fps wählen darf
+    #[arg(long, default_value_t = 10, requires = "target_fps")]
+    max_detect_every: u32,
+    /// Alle Entscheidungen als JSONL an diese Datei anhängen
+    #[arg(long)]
+    audit_log: Option<String>,
+    /// IDs im Audit-Log durch einen gesalzenen Hash ersetzen; Ereignisse bleiben einander zuordenbar,
+    /// lassen sich aber nicht ohne das Salz mit der Datenbank verknüpfen
+    #[arg(long, requires = "audit_log")]
+    hash_audit_ids: bool,
+    /// Datei mit dem Salz für --hash-audit-ids; wird beim ersten Start angelegt
+    #[arg(long, default_value = "./audit_salt")]
+    audit_salt_file: String,
+    /// Entscheidungen zusätzlich in die Tabelle `events` dieser SQLite-Datenbank schreiben (Zeitpunkt, Kamera,
+    /// ID, Name, Ähnlichkeit, Entscheidung), um sie per SQL auszuwerten
+    #[arg(long)]
+    event_db: Option<String>,
+    #[command(flatten)]
+    privacy: PrivacyArgs,
+    /// Leerlauf melden, wenn so viele Sekunden kein Gesicht zu sehen war, und das Wiedererscheinen (Audit-Log und Konsole)
+    #[arg(long)]
+    idle_after: Option<f64>,
+    /// Ergebnisse als Nachrichten auf stdout für einen Elternprozess (Aufbau siehe `protocol`);
+    /// Meldungen für Menschen gehen dann nach stderr. Ohne Videofenster wird nicht auf der Konsole nach
+    /// unbekannten Gesichtern gefragt; sie werden nur gemeldet.
+    #[arg(long, value_enum)]
+    protocol: Option<Framing>,
+    /// Jede Entscheidung samt allen Metadaten des erkannten Eintrags als JSON-Zeile auf stdout ausgeben
+    #[arg(long)]
+    verbose_events: bool,
+    /// Ohne Treffer wird die beste Ähnlichkeit erst ab diesem Wert als `best_score` gemeldet;
+    /// darunter ist sie Rauschen und entfällt
+    #[arg(long, default_value_t = 0.3)]
+    score_floor: f32,
+    /// Alle so viele Sekunden eine Statuszeile (Laufzeit, Frames, FPS, Galerie, Kameras) ausgeben
+    #[arg(long, value_parser = parse_seconds)]
+    heartbeat: Option<f64>,
+    /// Statusmeldung zusätzlich als JSON per HTTP-POST an diese Adresse senden (nur http://)
+    #[arg(long, requires = "heartbeat")]
+    heartbeat_url: Option<String>,
+    /// Befehl (über `sh -c`), der einmal je neu erfasster Person läuft; erhält FACEREC_ID, FACEREC_NAME,
+    /// FACEREC_ALLOWED und FACEREC_CAMERA als Umgebungsvariablen
+    #[arg(long)]
+    on_enroll: Option<String>,
+    /// Neu erfasste Personen zusätzlich als JSON per HTTP-POST an diese Adresse melden (nur http://)
+    #[arg(long)]
+    on_enroll_url: Option<String>,
+    /// Befehl (über `sh -c`), der bei erlaubtem Zugang die Tür entriegelt; erhält FACEREC_ID und FACEREC_CAMERA
+    #[arg(long, requires = "door_lock")]
+    door_unlock: Option<String>,
+    /// Befehl, der die Tür nach der Haltezeit wieder verriegelt
+    #[arg(long, requires = "door_unlock")]
+    door_lock: Option<String>,
+    /// Sekunden, die die Tür nach dem letzten erlaubten Zugang entriegelt bleibt
+    #[arg(long, default_value_t = 5.0, value_parser = parse_seconds, requires = "door_unlock")]
+    door_hold: f64,
+    /// Prometheus-Metriken unter dieser Adresse bereitstellen (z. B. 0.0.0.0:9100)
+    #[arg(long)]
+    metrics_addr: Option<String>,
+    /// Ereignisse und Ergebnisse zusätzlich mit SMPTE-Timecode aus Abspielposition und Bildrate versehen
+    /// (nur im Videomodus)
+    #[arg(long, requires = "video")]
+    timecode: bool,
+    /// Ergebnisse pro Frame als JSONL schreiben (nur im Videomodus)
+    #[arg(long, requires = "video")]
+    results: Option<String>,
+    /// LBF-Modell für die Erkennung von Gesichtsmerkmalen (z. B. lbfmodel.yaml)
+    #[arg(long)]
+    landmark_model: Option<String>,
+    /// Mindestabstand der Augen in Pixeln; kleinere (zu weit entfernte) Gesichter werden übersprungen
+    #[arg(long, requires = "landmark_model")]
+    min_eye_distance: Option<f32>,
+    /// Erkannte Gesichtsmerkmale zur Diagnose ins Bild zeichnen
+    #[arg(long, requires = "landmark_model")]
+    draw_landmarks: bool,
+    /// Gesichter, die weiter als diese Gradzahl zur Seite gedreht sind, nicht entscheiden
+    #[arg(long, requires = "landmark_model")]
+    max_yaw: Option<f32>,
+    /// Gesichter, die weiter als diese Gradzahl nach oben oder unten geneigt sind, nicht entscheiden
+    #[arg(long, requires = "landmark_model")]
+    max_pitch: Option<f32>,
+    /// Gesichter unter dieser Höhe in Pixeln nicht entscheiden, sondern als „Analyse läuft“ anzeigen
+    /// und auf einen besseren Frame warten
+    #[arg(long)]
+    defer_min_size: Option<i32>,
+    /// Ebenso für Ausschnitte unter dieser Schärfe (Varianz des Laplace-Operators)
+    #[arg(long)]
+    defer_min_sharpness: Option<f64>,
+    /// Am unteren Rand jedes Gesichtsrahmens einen Balken mit der Ähnlichkeit des besten Treffers im Verhältnis zum Schwellwert zeigen
+    #[arg(long)]
+    confidence_bar: bool,
+    /// Je Frame nur das größte Gesicht entscheiden; die übrigen gelten als übersprungen
+    #[arg(long)]
+    select_largest: bool,
+    /// Übersprungene Gesichter neutral umrahmen
+    #[arg(long)]
+    mark_skipped: bool,
+    /// Gesichter nur innerhalb dieses Bereichs suchen (x,y,Breite,Höhe)
+    #[arg(long, value_parser = parse_region)]
+    region: Option<Rect>,
+    /// Frame vor der Gesichtssuche um diesen Faktor verkleinern (0 < Faktor ≤ 1);
+    /// ausgeschnitten wird weiterhin in voller Auflösung
+    #[arg(long, default_value_t = 1.0, value_parser = parse_scale)]
+    detect_scale: f64,
+    /// Digitaler Zoom für entfernte Gesichter: Gesichter unter dieser Höhe (Pixel) werden samt Umgebung
+    /// auf diese Höhe vergrößert und darin erneut gesucht; Embedding und Ausschnitt stammen aus dem Zoom
+    #[arg(long)]
+    zoom: Option<i32>,
+    /// Angezeigtes Bild um diesen Faktor verkleinern (0 < Faktor ≤ 1), z. B. bei 4K-Kameras;
+    /// Erkennung und Abgleich laufen weiter auf dem vollen Frame
+    #[arg(long, default_value_t = 1.0, value_parser = parse_scale)]
+    display_scale: f64,
+    /// Helligkeitsnormalisierung des ganzen Frames vor Erkennung und Merkmalsextraktion; entspricht den
+    /// gleichnamigen Schritten der Vorverarbeitung (siehe `preprocessing`)
+    #[arg(long, value_enum, default_value = "none")]
+    normalize: Normalization,
+    /// Clip-Limit für CLAHE
+    #[arg(long, default_value_t = 2.0)]
+    clahe_clip_limit: f64,
+    /// Gamma-Wert für die Gammakorrektur (> 1 hellt dunkle Bereiche auf)
+    #[arg(long, default_value_t = 1.5)]
+    gamma: f64,
+    /// IDs neuer Gesichter aus dem Embedding ableiten statt zufällig zu vergeben
+    #[arg(long)]
+    deterministic_ids: bool,
+    /// Anzahl Frames, über die die Ähnlichkeit je verfolgtem Gesicht gemittelt wird (1 = keine Glättung)
+    #[arg(long, default_value_t = 1)]
+    smoothing_window: usize,
+    /// Anzahl Frames, die ein verdecktes Gesicht samt seiner letzten Entscheidung weiter angezeigt wird
+    #[arg(long, default_value_t = 0)]
+    track_persistence: u32,
+    /// Bewegungspfad jeder Spur (Mittelpunkte der letzten Erkennungen) ins Bild zeichnen
+    #[arg(long)]
+    draw_trails: bool,
+    /// Anzahl der Frames, über die der Bewegungspfad reicht
+    #[arg(long, default_value_t = 30, requires = "draw_trails")]
+    trail_length: usize,
+    /// Laufzeit je Pipeline-Stufe messen und beim Beenden ausgeben
+    #[arg(long)]
+    profile: bool,
+    /// Verfahren für den Abgleich mit der Datenbank
+    #[arg(long, value_enum, default_value = "embedding")]
+    backend: Backend,
+    /// LBPH-Distanz, ab der ein Gesicht als unbekannt gilt (nur mit --backend lbph)
+    #[arg(long, default_value_t = 80.0)]
+    lbph_threshold: f64,
+    /// Gespeicherte Embeddings mit sehr sicheren Treffern fortlaufend nachführen (z. B. nach Frisurwechsel)
+    #[arg(long)]
+    adaptive: bool,
+    /// Gewicht der aktuellen Beobachtung beim Nachführen
+    #[arg(long, default_value_t = 0.05, requires = "adaptive")]
+    adaptive_rate: f32,
+    /// Mindestähnlichkeit (ungeglättet), ab der ein Treffer das Embedding nachführt; deutlich über dem Schwellwert
+    #[arg(long, default_value_t = 0.97, requires = "adaptive")]
+    adaptive_min_score: f32,
+    /// Mindestabstand der Ähnlichkeit zwischen bestem und zweitbestem Eintrag; knappere Treffer werden abgewiesen,
+    /// ohne eine Erfassung anzubieten (0 = keine Prüfung)
+    #[arg(long, default_value_t = 0.0)]
+    match_margin: f32,
+    /// Verfahren der Suche: exakter Durchlauf oder approximativer HNSW-Index für sehr große Galerien
+    #[arg(long, value_enum, default_value = "brute-force")]
+    index: IndexKind,
+    /// Im Raum der mit `fit-pca` bestimmten Projektion abgleichen; schneller bei großen Galerien,
+    /// gespeichert bleiben die vollen Embeddings
+    #[arg(long)]
+    pca: bool,
+    /// Ähnlichkeitsabstand, innerhalb dessen Treffer als gleichauf gelten
+    #[arg(long, default_value_t = 0.001)]
+    tie_epsilon: f32,
+    /// Bevorzugter Eintrag bei Gleichstand
+    #[arg(long, value_enum, default_value = "recency")]
+    tie_break: TiePreference,
+    /// Jeden erkannten Gesichtsausschnitt mit Metadaten (crops.jsonl) in diesem Ordner ablegen
+    #[arg(long)]
+    dump_crops: Option<String>,
+    /// Beste Ähnlichkeit jedes Gesichts sammeln und beim Beenden als Histogramm ausgeben
+    #[arg(long)]
+    score_histogram: bool,
+    /// Histogramm zusätzlich als CSV in diese Datei schreiben
+    #[arg(long, requires = "score_histogram")]
+    score_histogram_csv: Option<String>,
+    /// Neue Erfassungen nur für die Sitzung im Speicher halten; gespeichert wird nur mit der S-Taste
+    #[arg(long)]
+    no_auto_save: bool,
+    /// Die Datenbank neu laden, sobald ein anderer Prozess sie ändert (z. B. `facerec enroll` oder `update`),
+    /// damit Erfassungen und Sperren ohne Neustart wirken. Gemeldet werden Änderungen vom Dateisystem; solange
+    /// eigene Erfassungen ungespeichert sind oder sich Modell oder PCA-Projektion geändert haben, bleibt es beim
+    /// bisherigen Stand
+    #[arg(long, conflicts_with = "no_auto_save")]
+    watch: bool,
+    /// Neue Personen direkt im Videofenster statt auf der Konsole erfassen
+    #[arg(long)]
+    gui_enroll: bool,
+    /// Nur vorab erfasste Personen zulassen: unbekannte Gesichter werden verweigert und nie gespeichert
+    #[arg(long, conflicts_with = "gui_enroll")]
+    strict: bool,
+    /// Datei mit IDs (eine je Zeile), deren Zugang unabhängig von der Datenbank verweigert wird;
+    /// Änderungen gelten ohne Neustart
+    #[arg(long)]
+    denylist: Option<String>,
+    /// Ebenso für IDs, deren Zugang immer erlaubt wird; die Sperrliste hat Vorrang
+    #[arg(long)]
+    allowlist: Option<String>,
+    /// Unbekannte Gesichter erst nach so vielen Sekunden ruhig im Bild erfassen („Bitte stillhalten“ mit
+    /// Restzeit), statt im ersten, oft verwackelten oder angeschnittenen Frame
+    #[arg(long)]
+    enroll_hold: Option<f64>,
+    /// Größte Bewegung des Rahmens während --enroll-hold, relativ zur Gesichtsbreite; darüber beginnt die
+    /// Wartezeit neu
+    #[arg(long, default_value_t = 0.1, requires = "enroll_hold")]
+    enroll_max_motion: f32,
+    /// Mindestschärfe (Varianz des Laplace-Operators) während --enroll-hold; darunter beginnt die Wartezeit neu
+    #[arg(long, requires = "enroll_hold")]
+    enroll_min_sharpness: Option<f64>,
+    /// Bei der Rückfrage abgewiesene Personen nicht in der Datenbank speichern; Alarm, Audit-Log und
+    /// Beweisbild bleiben erhalten
+    #[arg(long)]
+    no_store_denied: bool,
+    /// Erkannte Personen mit hinterlegter PIN (siehe `update --set-pin`) erst nach Eingabe der PIN
+    /// auf der Konsole einlassen; eine falsche PIN verweigert den Zugang für die ganze Spur.
+    /// Nicht mit --protocol, dort gehört stdin dem Elternprozess.
+    #[arg(long, conflicts_with = "protocol")]
+    require_pin: bool,
+    /// Aktionen (Begrüßung, Alarm, Zählung) für jeden Rahmen auslösen, auch wenn dieselbe ID im selben
+    /// Frame mehrfach erkannt wird, z. B. durch eine Spiegelung; sonst nur einmal je Frame
+    #[arg(long)]
+    no_dedupe_ids: bool,
+    /// Gültigkeitsdauer eines Besucherzugangs in Stunden
+    #[arg(long, default_value_t = 8)]
+    visitor_hours: i64,
+    /// Mindestabstand in Sekunden zwischen zwei Alarmen für dieselbe abgewiesene Person
+    #[arg(long, default_value_t = 10.0, value_parser = parse_seconds)]
+    alert_interval: f64,
+    /// Bei jedem Alarm ein Beweisbild in diesem Ordner ablegen: den schärfsten der letzten Frames der Person
+    #[arg(long)]
+    alert_snapshots: Option<String>,
+    /// Höchstzahl aufbewahrter Alarmbilder; ältere werden beim Schreiben neuer gelöscht
+    #[arg(long, requires = "alert_snapshots")]
+    max_snapshots: Option<usize>,
+    /// Aufbewahrungsdauer der Alarmbilder in Stunden
+    #[arg(long, value_parser = parse_seconds, requires = "alert_snapshots")]
+    snapshot_ttl: Option<f64>,
+    /// Kulanzzeit in Sekunden: eine abgewiesene Person sieht zunächst nur einen Hinweis,
+    /// der Alarm folgt erst, wenn sie so lange im Bild bleibt (0 = sofort alarmieren)
+    #[arg(long, default_value_t = 0.0, value_parser = parse_seconds)]
+    alert_grace: f64,
+}
+
+/// Verfahren für den Abgleich mit der Datenbank
+#[derive(Clone, Copy, ValueEnum)]
+enum Backend {
+    /// Embedding des Modells und Kosinus-Ähnlichkeit
+    Embedding,
+    /// OpenCVs LBPH-Erkenner, trainiert aus den gespeicherten Ausschnitten (kein DNN nötig)
+    Lbph,
+}
+
+/// Ausgabeformat von `embed`
+#[derive(Clone, Copy, ValueEnum)]
+enum EmbeddingFormat {
+    /// JSON-Array der Werte
+    Json,
+    /// Base64 der f32-Werte (Little-Endian)
+    Base64,
+}
+
+/// Verfahren zur Helligkeitsnormalisierung
+#[derive(Clone, Copy, ValueEnum)]
+enum Normalization {
+    None,
+    Equalize,
+    Clahe,
+    Gamma,
+}
+
+impl Normalization {
+    /// Dieselben Schritte wie in der Vorverarbeitung der Ausschnitte (siehe `preprocessing`), nur auf den Frame
+    fn steps(self, clahe_clip_limit: f64, gamma: f64) -> Vec<Step> {
+        match self {
+            Normalization::None => Vec::new(),
+            Normalization::Equalize => vec![Step::Equalize],
+            Normalization::Clahe => vec![Step::Clahe { clip_limit: clahe_clip_limit, tiles: 8 }],
+            Normalization::Gamma => vec![Step::Gamma { gamma }],
+        }
+    }
+}
+
+/// Liest einen Bereich im Format x,y,Breite,Höhe
+fn parse_region(value: &str) -> Result<Rect, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("ungültige Zahl: {e}"))?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Rect::new(x, y, width, height)),
+        _ => Err("erwartet x,y,Breite,Höhe mit positiver Breite und Höhe".to_string()),
+    }
+}
+
+/// Einstellung der Kamera, die nach dem Öffnen gesetzt wird (`--cam-prop`)
+#[derive(Clone)]
+struct CameraProperty {
+    name: String,
+    id: i32,
+    value: f64,
+}
+
+/// Bekannte Namen für `--cam-prop` und ihre OpenCV-Kennung
+const CAMERA_PROPERTIES: &[(&str, i32)] = &[
+    ("width", videoio::CAP_PROP_FRAME_WIDTH),
+    ("height", videoio::CAP_PROP_FRAME_HEIGHT),
+    ("fps", videoio::CAP_PROP_FPS),
+    ("brightness", videoio::CAP_PROP_BRIGHTNESS),
+    ("contrast", videoio::CAP_PROP_CONTRAST),
+    ("saturation", videoio::CAP_PROP_SATURATION),
+    ("hue", videoio::CAP_PROP_HUE),
+    ("gain", videoio::CAP_PROP_GAIN),
+    ("exposure", videoio::CAP_PROP_EXPOSURE),
+    ("auto_exposure", videoio::CAP_PROP_AUTO_EXPOSURE),
+    ("sharpness", videoio::CAP_PROP_SHARPNESS),
+    ("gamma", videoio::CAP_PROP_GAMMA),
+    ("backlight", videoio::CAP_PROP_BACKLIGHT),
+    ("zoom", videoio::CAP_PROP_ZOOM),
+    ("focus", videoio::CAP_PROP_FOCUS),
+    ("autofocus", videoio::CAP_PROP_AUTOFOCUS),
+    ("auto_wb", videoio::CAP_PROP_AUTO_WB),
+    ("wb_temperature", videoio::CAP_PROP_WB_TEMPERATURE),
+    ("buffersize", videoio::CAP_PROP_BUFFERSIZE),
+];
+
+/// Liest `NAME=WERT`, z. B. `exposure=-6` oder `autofocus=0`
+fn parse_cam_prop(value: &str) -> Result<CameraProperty, String> {
+    let (name, number) = value.split_once('=').ok_or("erwartet NAME=WERT")?;
+    let name = name.trim().to_lowercase();
+    let Some(&(_, id)) = CAMERA_PROPERTIES.iter().find(|(known, _)| *known == name) else {
+        let known: Vec<&str> = CAMERA_PROPERTIES.iter().map(|(known, _)| *known).collect();
+        return Err(format!("unbekannte Eigenschaft {name} (bekannt: {})", known.join(", ")));
+    };
+    let value = number.trim().parse().map_err(|e| format!("ungültige Zahl: {e}"))?;
+    Ok(CameraProperty { name, id, value })
+}
+
+/// Liest einen Verkleinerungsfaktor im Bereich (0, 1]
+fn parse_scale(value: &str) -> Result<f64, String> {
+    let scale: f64 = value.parse().map_err(|e| format!("ungültige Zahl: {e}"))?;
+    if scale > 0.0 && scale <= 1.0 {
+        Ok(scale)
+    } else {
+        Err("erwartet einen Faktor größer 0 und höchstens 1".to_string())
+    }
+}
+
+/// Dauer in Sekunden (bzw. Stunden); negative und unendliche Werte wären als `Duration` nicht darstellbar
+fn parse_seconds(value: &str) -> Result<f64, String> {
+    let seconds: f64 = value.parse().map_err(|e| format!("ungültige Zahl: {e}"))?;
+    if seconds.is_finite() && seconds >= 0.0 {
+        Ok(seconds)
+    } else {
+        Err("erwartet eine endliche Dauer von mindestens 0".to_string())
+    }
+}
+
+/// Bildrate größer 0; aus ihr wird die Zeit je Frame berechnet
+fn parse_fps(value: &str) -> Result<f64, String> {
+    let fps: f64 = value.parse().map_err(|e| format!("ungültige Zahl: {e}"))?;
+    if fps.is_finite() && fps > 0.0 {
+        Ok(fps)
+    } else {
+        Err("erwartet eine Bildrate größer 0".to_string())
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Berechnet die Embeddings aller gespeicherten Gesichter aus ihren Ausschnitten neu
+    Reindex,
+    /// Erfasst das größte Gesicht eines Bildes in der Datenbank
+    Enroll {
+        /// Bild mit dem zu erfassenden Gesicht
+        image: String,
+        /// Zugang verweigern statt erlauben
+        #[arg(long)]
+        deny: bool,
+        /// Zugang auf Probe: erlaubt, aber jede Wiedererkennung wird zur Prüfung vorgemerkt
+        #[arg(long, conflicts_with = "deny")]
+        probation: bool,
+        /// Explizite ID statt einer zufälligen UUID
+        #[arg(long, conflicts_with = "deterministic_id")]
+        id: Option<String>,
+        /// Einen bestehenden Eintrag mit der unter --id angegebenen ID ersetzen
+        #[arg(long, requires = "id")]
+        replace: bool,
+        /// ID aus dem Embedding ableiten, damit wiederholte Erfassungen dieselbe ID erhalten
+        #[arg(long)]
+        deterministic_id: bool,
+        /// Hinweis, der bei jeder Wiedererkennung angezeigt wird
+        #[arg(long)]
+        notes: Option<String>,
+        /// Das Gesicht als weitere Aufnahme zu diesem bestehenden Eintrag hinzufügen
+        #[arg(long, value_name = "ID", conflicts_with_all = ["id", "deterministic_id", "deny", "probation"])]
+        add_to: Option<String>,
+    },
+    /// Erfasst eine Person geführt vor der Kamera: je Blickrichtung eine Aufnahme in einem Eintrag
+    EnrollGuided {
+        /// Kameraindex
+        #[arg(long, default_value_t = 0)]
+        camera_index: i32,
+        /// LBF-Modell für die Gesichtsmerkmale, aus denen die Kopfhaltung geschätzt wird
+        #[arg(long)]
+        landmark_model: String,
+        /// Name der Person
+        #[arg(long)]
+        name: Option<String>,
+        /// Zugang verweigern statt erlauben
+        #[arg(long)]
+        deny: bool,
+        /// Zugang auf Probe
+        #[arg(long, conflicts_with = "deny")]
+        probation: bool,
+        /// Mindesthöhe des Gesichts in Pixeln
+        #[arg(long, default_value_t = 100)]
+        min_face_size: i32,
+        /// Mindestschärfe (Varianz des Laplace-Operators) einer Aufnahme
+        #[arg(long, default_value_t = 60.0)]
+        min_sharpness: f64,
+    },
+    /// Ändert einen gespeicherten Eintrag
+    Update {
+        /// ID des Eintrags
+        id: String,
+        /// Neuer Hinweis; eine leere Angabe entfernt ihn
+        #[arg(long)]
+        notes: Option<String>,
+        /// PIN für den zweiten Faktor (--require-pin) auf der Konsole abfragen und setzen
+        #[arg(long)]
+        set_pin: bool,
+        /// Hinterlegte PIN entfernen
+        #[arg(long, conflicts_with = "set_pin")]
+        remove_pin: bool,
+    },
+    /// Vergleicht die größten Gesichter zweier Bilder (1:1-Verifikation, ohne Datenbank).
+    /// Exit-Code 0 bei Übereinstimmung, sonst 1.
+    Verify {
+        first: String,
+        second: String,
+    },
+    /// Gibt das normierte Embedding des größten Gesichts eines Bildes aus (ohne Datenbank)
+    Embed {
+        image: String,
+        #[arg(long, value_enum, default_value = "json")]
+        format: EmbeddingFormat,
+    },
+    /// Erkennt die Gesichter eines Standbilds und schreibt ein beschriftetes Ergebnisbild
+    Annotate {
+        input: String,
+        output: String,
+        #[command(flatten)]
+        privacy: PrivacyArgs,
+    },
+    /// Bestimmt den Schwellwert aus beschrifteten Bildpaaren und gibt die Kennzahlen aus
+    Calibrate {
+        /// CSV-Datei mit Zeilen `bild_a,bild_b,gleiche_person` (true/false)
+        #[arg(long)]
+        pairs: String,
+        /// Statt des besten F1-Werts den niedrigsten Schwellwert mit höchstens dieser Falschakzeptanzrate wählen
+        #[arg(long)]
+        target_far: Option<f64>,
+    },
+    /// Misst Präzision und Trefferquote der Gesichtssuche an Bildern mit bekannten Gesichtsrahmen,
+    /// z. B. um die Parameter aus --detector-config abzustimmen
+    DetectEval {
+        /// Ordner mit den Bildern
+        #[arg(long)]
+        dir: String,
+        /// JSON-Datei mit den erwarteten Rahmen je Bild: `{"bild.jpg": [[x, y, breite, höhe], ...]}`
+        #[arg(long)]
+        annotations: String,
+        /// Mindestüberlappung (IoU), ab der ein gefundener Rahmen als Treffer zählt
+        #[arg(long, default_value_t = 0.5)]
+        min_iou: f64,
+    },
+    /// Verarbeitet die Videodateien einer Manifestdatei nacheinander im Videomodus (streng, ohne Erfassung)
+    /// und fasst alle Treffer zusammen; Aufbau des Manifests siehe `batch`
+    Batch {
+        manifest: String,
+        /// Zusammenfassung aller Dateien als JSON
+        #[arg(long, default_value = "batch_summary.json")]
+        summary: String,
+    },
+    /// Durchsucht ein Audit-Log (--audit-log) und gibt die passenden Ereignisse als JSONL aus
+    Log {
+        /// Audit-Log-Datei
+        path: String,
+        /// Nur Ereignisse ab diesem Zeitpunkt (Datum wie 2024-01-01 oder RFC 3339)
+        #[arg(long, value_parser = audit_query::parse_time)]
+        since: Option<DateTime<Local>>,
+        /// Nur Ereignisse vor diesem Zeitpunkt
+        #[arg(long, value_parser = audit_query::parse_time)]
+        until: Option<DateTime<Local>>,
+        /// Nur Ereignisse dieser ID
+        #[arg(long)]
+        id: Option<String>,
+        /// Nur Ereignisse dieser Kamera
+        #[arg(long)]
+        camera: Option<String>,
+        /// Nur verweigerte Zugänge
+        #[arg(long)]
+        denied_only: bool,
+        /// Salzdatei eines mit --hash-audit-ids geschriebenen Logs; --id wird dann gehasht verglichen
+        #[arg(long, requires = "id")]
+        audit_salt_file: Option<String>,
+    },
+    /// Bestimmt aus der Galerie eine PCA-Projektion für den verkleinerten Abgleich (--pca) und legt sie
+    /// im Kopf der Datenbank ab
+    FitPca {
+        /// Anzahl der Dimensionen nach der Projektion
+        #[arg(long, default_value_t = 128)]
+        dimensions: usize,
+    },
+    /// Exportiert die Embeddings der Datenbank mit anonymen Personennummern und Zugangsrechten, aber ohne
+    /// Bilder, IDs und Namen, z. B. um einen Fehler im Abgleich nachvollziehbar zu melden
+    ExportEval {
+        /// Zieldatei (JSON)
+        output: String,
+        /// Werte auf 8 Bit runden; kleiner und weniger genau als das Original
+        #[arg(long)]
+        quantize: bool,
+    },
+    /// Gruppiert die Gesichter aller Bilder eines Ordners nach Ähnlichkeit (ohne Datenbank)
+    Cluster {
+        /// Ordner mit Bildern oder Gesichtsausschnitten
+        dir: String,
+        /// Mindestähnlichkeit, ab der zwei Gruppen zusammengelegt werden
+        #[arg(long, default_value_t = MATCH_THRESHOLD)]
+        threshold: f32,
+    },
+    /// Führt nahezu doppelte Einträge derselben Person nach Rückfrage zusammen
+    Dedupe {
+        /// Mindestähnlichkeit, ab der Einträge als dieselbe Person gelten
+        #[arg(long, default_value_t = 0.95)]
+        threshold: f32,
+        /// Ohne Rückfrage zusammenführen
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Prüft die Datenbank auf doppelte IDs und ungültige Embeddings; Exit-Code 1 bei verbleibenden Problemen
+    Fsck {
+        /// Betroffene Einträge in face_data.quarantine.json verschieben
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Zeigt Dimension und Betrag der Embeddings je Eintrag und markiert abweichende Dimensionen, nicht
+    /// normierte Embeddings und NaN/∞, z. B. nach einem Import; ändert nichts. Exit-Code 1 bei Auffälligkeiten.
+    CheckDb {
+        /// Erwartete Dimension; Standard ist die Dimension im Kopf bzw. die häufigste
+        #[arg(long)]
+        dimension: Option<usize>,
+        /// Erlaubte Abweichung des Betrags von 1
+        #[arg(long, default_value_t = 0.01)]
+        tolerance: f32,
+    },
+    /// Überträgt eine Datenbank zwischen JSON und dem binären Format; das Format folgt der Dateiendung (.bin = binär)
+    Convert {
+        input: String,
+        /// Zieldatei; darf noch nicht existieren
+        output: String,
+    },
+    /// Leert die Datenbank nach Rückfrage
+    Clear {
+        /// Ohne Rückfrage leeren
+        #[arg(long)]
+        yes: bool,
+        /// Die bisherige Datenbank vorher in eine Sicherung mit Zeitstempel kopieren
+        #[arg(long)]
+        backup: bool,
+    },
+}
+
+/// Pfad der Datenbank, gesetzt über `--database`
+static DATABASE_PATH: OnceLock<String> = OnceLock::new();
+
+fn database_path() -> &'static str {
+    DATABASE_PATH.get().map_or(DATABASE, String::as_str)
+}
+
+/// Modell laut Kopf der geladenen Datenbank; wird beim Schreiben wieder in den Kopf übernommen.
+/// Ändert sich nur, wenn alle Embeddings mit dem aktuellen Modell stammen (neue Datenbank, `reindex`).
+static DATABASE_MODEL: Mutex<Option<String>> = Mutex::new(None);
+/// Ebenso die Projektion; gilt nur für Embeddings des Modells, aus denen sie bestimmt wurde
+static DATABASE_PROJECTION: Mutex<Option<Projection>> = Mutex::new(None);
+
+/// Lädt die Datenbank samt Kopf
+fn load_database() -> Database {
+    let database = read_database(database_path());
+    *DATABASE_MODEL.lock().unwrap() = database.model.clone();
+    *DATABASE_PROJECTION.lock().unwrap() = database.projection.clone();
+    database
+}
+
+/// Liest eine Datenbank im Format ihrer Dateiendung (JSON oder binär); eine leere Datei ist eine neue Datenbank.
+/// Eine unlesbare Datei beendet das Programm, statt sie als leer zu behandeln und beim nächsten Speichern zu überschreiben.
+fn read_database(path: &str) -> Database {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() == 0) {
+        return Database::default();
+    }
+    match try_read_database(path) {
+        Err(e @ FacerecError::Invalid { .. }) => {
+            eprintln!("Fehler: {e}");
+            let backups: Vec<String> = (1..)
+                .map(|n| format!("{path}.bak.{n}"))
+                .take_while(|path| Path::new(path).exists())
+                .collect();
+            if !backups.is_empty() {
+                eprintln!("Vorhandene Sicherungen: {}", backups.join(", "));
+            }
+            eprintln!("Mit `facerec fsck --fix` unlesbare Einträge aussondern oder eine Sicherung zurückkopieren.");
+            std::process::exit(1);
+        }
+        result => or_exit(result),
+    }
+}
+
+/// Lädt bekannte Gesichter aus der Datenbank
+fn load_face_data() -> Vec<FaceEntry> {
+    load_database().faces
+}
+
+/// Anzahl der vorgehaltenen Sicherungen, gesetzt über `--backups`
+static BACKUPS: AtomicUsize = AtomicUsize::new(0);
+
+/// Sichert die Datenbank vor dem Überschreiben: face_data.json.bak.1 ist die neueste Sicherung,
+/// ältere rücken nach, die älteste über der Höchstzahl entfällt
+fn rotate_backups() -> Result<(), FacerecError> {
+    let keep = BACKUPS.load(Ordering::Relaxed);
+    let path = database_path();
+    if keep == 0 || !Path::new(path).exists() {
+        return Ok(());
+    }
+    let backup = |n: usize| format!("{path}.bak.{n}");
+    let _ = fs::remove_file(backup(keep));
+    for n in (1..keep).rev() {
+        let _ = fs::rename(backup(n), backup(n + 1));
+    }
+    fs::copy(path, backup(1)).map(drop).map_err(|source| FacerecError::Write { path: backup(1), source })
+}
+
+/// Änderungsdatum nach dem letzten eigenen Schreiben, damit --watch es nicht für eine fremde Änderung hält
+static LAST_WRITE: Mutex<Option<SystemTime>> = Mutex::new(None);
+
+/// Überschreibt die Datenbank mit der übergebenen Liste
+fn write_face_data(data: &[FaceEntry]) -> Result<(), FacerecError> {
+    rotate_backups()?;
+    let model = DATABASE_MODEL.lock().unwrap().clone();
+    let projection = DATABASE_PROJECTION.lock().unwrap().clone();
+    store_database(database_path(), data, model.as_deref(), projection.as_ref())?;
+    *LAST_WRITE.lock().unwrap() = database_modified();
+    Ok(())
+}
+
+/// Für Befehle, die ohne das Ergebnis nicht weiterkommen: beendet das Programm mit der Fehlermeldung
+fn or_exit<T>(result: Result<T, impl Into<FacerecError>>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("Fehler: {}", e.into());
+        std::process::exit(1);
+    })
+}
+
+fn database_modified() -> Option<SystemTime> {
+    fs::metadata(database_path()).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// Ändert die Metadaten eines gespeicherten Eintrags
+fn update_face(id: &str, notes: Option<&str>, set_pin: bool, remove_pin: bool) {
+    let mut data = load_face_data();
+    let Some(entry) = data.iter_mut().find(|entry| entry.id == id) else {
+        eprintln!("Fehler: kein Eintrag mit der ID {id}");
+        std::process::exit(1);
+    };
+    if let Some(notes) = notes {
+        entry.notes = Some(notes.to_string()).filter(|notes| !notes.is_empty());
+    }
+    if set_pin {
+        println!("Neue PIN (4 bis 8 Ziffern): ");
+        let pin = pin::read_pin();
+        if !pin::is_valid(&pin) {
+            eprintln!("Fehler: die PIN muss aus 4 bis 8 Ziffern bestehen");
+            std::process::exit(1);
+        }
+        println!("PIN wiederholen: ");
+        if pin::read_pin() != pin {
+            eprintln!("Fehler: die PINs stimmen nicht überein");
+            std::process::exit(1);
+        }
+        entry.pin_hash = Some(pin::hash(&pin));
+    }
+    if remove_pin {
+        entry.pin_hash = None;
+    }
+    or_exit(write_face_data(&data));
+    println!("Eintrag {id} aktualisiert.");
+}
+
+/// Fragt auf der Konsole nach; nur „j“ bestätigt
+fn confirm(question: &str) -> bool {
+    println!("{question} (j/n): ");
+    let mut response = String::new();
+    let read = io::stdin().read_line(&mut response);
+    or_exit(read.map_err(|source| FacerecError::Read { path: "Standardeingabe".to_string(), source }));
+    response.trim().to_lowercase() == "j"
+}
+
+/// Leert die Datenbank, optional nach einer Sicherung der bisherigen Datei
+fn clear_face_data(yes: bool, backup: bool) {
+    let count = load_face_data().len();
+    if !yes && !confirm(&format!("{count} Einträge unwiderruflich löschen?")) {
+        println!("Abgebrochen.");
+        return;
+    }
+    // Ohne Datenbankdatei gibt es nichts zu sichern
+    if backup && Path::new(database_path()).exists() {
+        let path = format!("{}.{}", database_path(), Local::now().format("%Y%m%d-%H%M%S"));
+        or_exit(fs::copy(database_path(), &path).map_err(|source| FacerecError::Write { path: path.clone(), source }));
+        println!("Sicherung unter {path} abgelegt.");
+    }
+    or_exit(write_face_data(&[]));
+    println!("{count} Einträge gelöscht.");
+}
+
+/// Sucht Gruppen nahezu gleicher Einträge, zeigt sie an und führt sie nach Rückfrage zusammen, siehe `dedupe`
+fn dedupe_faces(threshold: f32, yes: bool) {
+    let faces = load_face_data();
+    let groups = dedupe::duplicates(&faces, threshold);
+    if groups.is_empty() {
+        println!("Keine Einträge über dem Schwellwert {threshold} gefunden.");
+        return;
+    }
+    let label = |face: &FaceEntry| match &face.name {
+        Some(name) => format!("{} ({name})", face.id),
+        None => face.id.clone(),
+    };
+    for group in &groups {
+        let merged: Vec<String> = group[1..].iter().map(|&index| label(&faces[index])).collect();
+        println!("{} ← {}", label(&faces[group[0]]), merged.join(", "));
+        if group[1..].iter().any(|&index| faces[index].pin_hash.is_some()) {
+            println!("  Hinweis: PINs der zusammengeführten Einträge entfallen; die PIN von {} bleibt.", faces[group[0]].id);
+        }
+    }
+    let removed: usize = groups.iter().map(|group| group.len() - 1).sum();
+    if !yes && !confirm(&format!("{} Gruppen zusammenführen und {removed} Einträge entfernen?", groups.len())) {
+        println!("Abgebrochen.");
+        return;
+    }
+    // Jede Gruppe ersetzt ihren ersten Eintrag an dessen Stelle, die übrigen entfallen
+    let mut slots: Vec<Option<FaceEntry>> = faces.into_iter().map(Some).collect();
+    for group in &groups {
+        let members: Vec<FaceEntry> = group.iter().map(|&index| slots[index].take().unwrap()).collect();
+        slots[group[0]] = Some(dedupe::merge(members));
+    }
+    let faces: Vec<FaceEntry> = slots.into_iter().flatten().collect();
+    or_exit(write_face_data(&faces));
+    println!("{} Gruppen zusammengeführt, {removed} Einträge entfernt; {} Einträge verbleiben.", groups.len(), faces.len());
+}
+
+/// Bestimmt die Projektion aus allen gespeicherten Aufnahmen, siehe `pca`
+fn fit_pca(dimensions: usize) {
+    let faces = load_face_data();
+    let samples: Vec<&[f32]> = faces.iter().flat_map(|face| face.embeddings.iter().map(Vec::as_slice)).collect();
+    if samples.len() < dimensions {
+        eprintln!("Warnung: nur {} Aufnahmen für {dimensions} Dimensionen; die Projektion ist wenig aussagekräftig", samples.len());
+    }
+    let Some((projection, explained)) = Projection::fit(&samples, dimensions) else {
+        eprintln!("Fehler: die Datenbank enthält keine Embeddings");
+        std::process::exit(1);
+    };
+    println!(
+        "Projektion von {} auf {} Dimensionen bestimmt, erklärt {:.1} % der Varianz.",
+        projection.input_dimension().unwrap_or_default(),
+        projection.components.len(),
+        explained * 100.0
+    );
+    *DATABASE_PROJECTION.lock().unwrap() = Some(projection);
+    or_exit(write_face_data(&faces));
+}
+
+/// Schreibt den anonymisierten Auswertungsdatensatz, siehe `eval_export`
+fn export_eval(output: &str, quantize: bool) {
+    let faces = load_face_data();
+    let model = DATABASE_MODEL.lock().unwrap().clone();
+    let dataset = EvalDataset::new(&faces, model, MATCH_THRESHOLD, quantize);
+    let json = serde_json::to_string(&dataset).expect("Fehler beim Serialisieren");
+    or_exit(fs::write(output, json).map_err(|source| FacerecError::Write { path: output.to_string(), source }));
+    println!("{} Embeddings von {} Personen nach {output} exportiert.", dataset.samples.len(), faces.len());
+}
+
+/// Überträgt eine Datenbank in ein anderes Format (JSON ↔ binär, nach Dateiendung)
+fn convert_database(input: &str, output: &str) {
+    if Path::new(output).exists() {
+        eprintln!("Fehler: {output} existiert bereits");
+        std::process::exit(1);
+    }
+    let database = read_database(input);
+    or_exit(store_database(output, &database.faces, database.model.as_deref(), database.projection.as_ref()));
+    println!("{} Einträge von {input} nach {output} übertragen.", database.faces.len());
+}
+
+/// Speichert den Gesichtsausschnitt als Bild, damit das Embedding später neu berechnet werden kann
+/// Legt einen Ausschnitt als `<name>.png` ab: `name` ist die ID, bei weiteren Aufnahmen mit laufender Nummer
+fn save_face_crop(name: &str, face: &Mat) -> Option<String> {
+    if let Err(e) = fs::create_dir_all(CROP_DIR) {
+        eprintln!("Warnung: Ordner {CROP_DIR} für die Ausschnitte nicht angelegt: {e}");
+        return None;
+    }
+    let path = format!("{CROP_DIR}/{name}.png");
+    match imgcodecs::imwrite(&path, face, &Vector::new()) {
+        Ok(true) => Some(path),
+        _ => {
+            eprintln!("Warnung: Ausschnitt {path} konnte nicht gespeichert werden");
+            None
+        }
+    }
+}
+
+/// Berechnet die Embeddings aller Einträge mit dem aktuellen Extraktor neu, jede Aufnahme aus ihrem Ausschnitt.
+/// IDs und Zugangsrechte bleiben erhalten. Aufnahmen ohne Ausschnitt bleiben unverändert, außer die Dimension
+/// ändert sich; Einträge, von denen keine Aufnahme neu berechnet werden kann, werden zur Neuerfassung markiert.
+fn reindex_faces(model: &ModelArgs) {
+    let mut embedder = model.unaligned_embedder();
+    let dimension = or_exit(embedder.dimension());
+    let fingerprint = model.fingerprint();
+    let mut cache = model.embedding_cache(&embedder);
+    let mut data = load_face_data();
+    let mut reindexed = 0;
+    let mut dropped = 0;
+    let mut cached = 0;
+    for entry in data.iter_mut() {
+        let mut embeddings = Vec::new();
+        let mut crops = Vec::new();
+        let mut lost = Vec::new();
+        let mut renewed = 0;
+        for (index, old) in entry.embeddings.iter().enumerate() {
+            let crop = entry.crop_of(index);
+            let features = crop.and_then(|path| reembed(path, &mut embedder, cache.as_mut(), &mut cached));
+            match features {
+                Some(features) => {
+                    embeddings.push(features);
+                    renewed += 1;
+                }
+                None if old.len() == dimension => embeddings.push(old.clone()),
+                None => {
+                    lost.push(index + 1);
+                    continue;
+                }
+            }
+            crops.push(crop.map(str::to_string));
+        }
+        // Ohne eine neu berechnete Aufnahme bleibt der Eintrag, wie er ist, bis er neu erfasst wird
+        if renewed == 0 {
+            entry.needs_reenrollment = true;
+            continue;
+        }
+        for shot in &lost {
+            eprintln!(
+                "Warnung: Aufnahme {shot} von {} hat keinen verwendbaren Ausschnitt, passt nicht zur Dimension \
+                 {dimension} und wird verworfen",
+                entry.id
+            );
+        }
+        dropped += lost.len();
+        entry.embeddings = embeddings;
+        // Bleibt nur der Ausschnitt des Eintrags selbst, genügt `crop`
+        entry.crops = if crops == [entry.crop.clone()] { Vec::new() } else { crops };
+        entry.needs_reenrollment = false;
+        reindexed += 1;
+    }
+    if let Some(cache) = &cache {
+        cache.save();
+        println!("{cached} Embeddings aus dem Zwischenspeicher übernommen.");
+    }
+    // Ersetzte Embeddings stammen nun vom aktuellen Modell
+    if fingerprint.is_some() {
+        *DATABASE_MODEL.lock().unwrap() = fingerprint;
+    }
+    // Die Hauptachsen passen nicht mehr zu den neuen Embeddings
+    if DATABASE_PROJECTION.lock().unwrap().take().is_some() {
+        println!("Die PCA-Projektion wurde verworfen; mit `facerec fit-pca` neu bestimmen.");
+    }
+    or_exit(write_face_data(&data));
+    println!(
+        "{reindexed} von {} Einträgen neu indiziert, {} müssen neu erfasst werden.",
+        data.len(),
+        data.iter().filter(|e| e.needs_reenrollment).count()
+    );
+    if dropped > 0 {
+        println!("{dropped} Aufnahmen ohne Ausschnitt verworfen; bei Bedarf mit `enroll --add-to` neu hinzufügen.");
+    }
+}
+
+/// Embedding eines gespeicherten Ausschnitts, aus dem Zwischenspeicher oder neu berechnet; None mit Warnung,
+/// wenn der Ausschnitt fehlt oder nicht ausgewertet werden kann
+fn reembed(
+    path: &str,
+    embedder: &mut Embedder,
+    cache: Option<&mut EmbeddingCache>,
+    cached: &mut usize,
+) -> Option<Vec<f32>> {
+    let key = cache.as_ref().and_then(|_| EmbeddingCache::key(path));
+    if let Some(embedding) = key.as_deref().and_then(|key| cache.as_ref()?.get(key)) {
+        *cached += 1;
+        return Some(embedding.clone());
+    }
+    // Fehlende oder beschädigte Dateien liefert imread als leere Matrix statt als Fehler
+    let features = match imgcodecs::imread(path, imgcodecs::IMREAD_GRAYSCALE) {
+        Ok(crop) if crop.empty() => {
+            eprintln!("Warnung: Ausschnitt {path} fehlt oder ist beschädigt, Aufnahme wird übersprungen");
+            return None;
+        }
+        Ok(crop) => embedder.extract(&crop).map_err(|e| {
+            eprintln!("Warnung: Embedding für {path} fehlgeschlagen: {e}; Aufnahme wird übersprungen");
+        }),
+        Err(e) => {
+            eprintln!("Warnung: Ausschnitt {path} konnte nicht gelesen werden: {e}");
+            return None;
+        }
+    }
+    .ok()?;
+    if let (Some(cache), Some(key)) = (cache, key) {
+        cache.insert(key, features.clone());
+    }
+    Some(features)
+}
+
+/// Gemeinsam genutzte Gesichtsdatenbank: hält die Einträge im Speicher und schreibt Änderungen zurück
+struct FaceStore {
+    /// Embedding-Dimension laut Kopf der Datei
+    dimension: Option<usize>,
+    faces: Mutex<Vec<FaceEntry>>,
+    tie_break: TieBreak,
+    /// Vorauswahl der Kandidaten für den exakten Abgleich; wird beim Neuladen ausgetauscht
+    index: Mutex<Box<dyn NeighborIndex>>,
+    index_kind: IndexKind,
+    /// Mit --pca: Projektion und die projizierten Aufnahmen je Eintrag in derselben Reihenfolge wie `faces`;
+    /// Metadaten wie `last_seen` kommen immer aus `faces`
+    projection: Option<Projection>,
+    projected: Mutex<Vec<Vec<Vec<f32>>>>,
+    /// Geforderter Abstand zwischen bestem und zweitbestem Treffer; 0 = keine Prüfung
+    margin: f32,
+    /// Neue Einträge sofort schreiben (Standard) oder nur im Speicher halten
+    auto_save: bool,
+    unsaved: AtomicUsize,
+    /// Anzahl der Anpassungen durch --adaptive
+    adapted: AtomicUsize,
+}
+
+/// Anpassungen durch --adaptive, nach denen die Datenbank zwischengespeichert wird
+const ADAPT_SAVE_INTERVAL: usize = 50;
+
+impl FaceStore {
+    fn load() -> Self {
+        let Database { dimension, faces, .. } = load_database();
+        METRICS.gallery_size.set(faces.len() as i64);
+        Self {
+            dimension,
+            index: Mutex::new(matching::build(IndexKind::BruteForce, &faces)),
+            index_kind: IndexKind::BruteForce,
+            projection: None,
+            projected: Mutex::new(Vec::new()),
+            faces: Mutex::new(faces),
+            tie_break: TieBreak::default(),
+            margin: 0.0,
+            auto_save: true,
+            unsaved: AtomicUsize::new(0),
+            adapted: AtomicUsize::new(0),
+        }
+    }
+
+    fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Baut den Index für die Vorauswahl mit dem gewählten Verfahren neu auf
+    fn with_index(mut self, kind: IndexKind) -> Self {
+        self.index_kind = kind;
+        self.index = Mutex::new(match &self.projection {
+            Some(_) => matching::build(kind, &*self.projected.lock().unwrap()),
+            None => matching::build(kind, &*self.faces.lock().unwrap()),
+        });
+        self
+    }
+
+    /// Gleicht mit der gespeicherten PCA-Projektion in verkleinerter Dimension ab
+    fn with_projection(mut self, enabled: bool) -> Self {
+        if !enabled {
+            return self;
+        }
+        let Some(projection) = DATABASE_PROJECTION.lock().unwrap().clone() else {
+            eprintln!("Fehler: die Datenbank enthält keine PCA-Projektion; zuerst `facerec fit-pca` ausführen");
+            std::process::exit(1);
+        };
+        if self.dimension.is_some_and(|dimension| projection.input_dimension() != Some(dimension)) {
+            eprintln!("Fehler: die PCA-Projektion passt nicht zur Dimension der Embeddings; `facerec fit-pca` erneut ausführen");
+            std::process::exit(1);
+        }
+        let projected: Vec<Vec<Vec<f32>>> = self
+            .faces
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|face| Self::project_entry(&projection, face))
+            .collect();
+        self.index = Mutex::new(matching::build(self.index_kind, &projected));
+        self.projected = Mutex::new(projected);
+        self.projection = Some(projection);
+        self
+    }
+
+    fn project_entry(projection: &Projection, face: &FaceEntry) -> Vec<Vec<f32>> {
+        face.embeddings.iter().map(|embedding| projection.project(embedding)).collect()
+    }
+
+    /// Beendet das Programm, wenn die gespeicherten Embeddings nicht zur Ausgabe des Modells passen.
+    /// Ein Vergleich unterschiedlich langer Vektoren würde sonst stillschweigend nur den gemeinsamen Anfang vergleichen.
+    fn ensure_dimension(&self, dimension: usize) {
+        let faces = self.faces.lock().unwrap();
+        let mismatched = faces
+            .iter()
+            .filter(|face| face.embeddings.iter().any(|embedding| embedding.len() != dimension))
+            .count();
+        if self.dimension.is_some_and(|stored| stored != dimension) || mismatched > 0 {
+            let stored = self.dimension.or_else(|| {
+                faces.iter().flat_map(|face| &face.embeddings).map(Vec::len).find(|&len| len != dimension)
+            });
+            eprintln!(
+                "Fehler: die Datenbank enthält Embeddings der Dimension {} ({mismatched} von {} Einträgen betroffen), \
+                 das Modell liefert {dimension}. Mit `facerec reindex` neu berechnen.",
+                stored.map_or("?".to_string(), |stored| stored.to_string()),
+                faces.len()
+            );
+            if stored == Some(embedding::LEGACY_DUMMY_DIMENSION) {
+                eprintln!(
+                    "Hinweis: {} Werte lieferten die früheren Ersatzmerkmale aus rohen Pixeln \
+                     (--allow-dummy-features); sie bestehen jetzt aus Histogrammen und sind nicht vergleichbar.",
+                    embedding::LEGACY_DUMMY_DIMENSION
+                );
+            }
+            std::process::exit(1);
+        }
+    }
+
+    /// Warnt deutlich, wenn die Datenbank mit einem anderen Modell aufgebaut wurde: gleiche Dimension,
+    /// aber nicht vergleichbare Embeddings, also stillschweigend falsche Treffer.
+    /// Ist kein Modell vermerkt (neue oder ältere Datenbank), gilt fortan das aktuelle; enthält eine ältere
+    /// Datenbank schon Einträge, lässt sich das nicht prüfen, und es wird gewarnt.
+    fn ensure_model(&self, model: &ModelArgs) {
+        let Some(current) = model.fingerprint() else {
+            return;
+        };
+        let mut stored = DATABASE_MODEL.lock().unwrap();
+        match stored.as_deref() {
+            Some(stored) if !same_model(&current, stored) => {
+                eprintln!("WARNUNG: Die Datenbank wurde mit einem anderen Embedding-Modell aufgebaut als {}!", model.model);
+                eprintln!("WARNUNG: Gespeichert {stored}, geladen {current}. Treffer sind unzuverlässig;");
+                eprintln!("WARNUNG: mit `facerec reindex` alle Embeddings mit dem aktuellen Modell neu berechnen.");
+            }
+            // Ein älterer Kopf ohne Vorverarbeitung wird beim nächsten Schreiben vervollständigt
+            Some(_) => *stored = Some(current),
+            None => {
+                let faces = self.faces.lock().unwrap().len();
+                if faces > 0 {
+                    eprintln!(
+                        "Warnung: die Datenbank vermerkt kein Embedding-Modell; für ihre {faces} Einträge wird {} \
+                         angenommen. Stammen sie von einem anderen Modell, mit `facerec reindex` neu berechnen.",
+                        model.model
+                    );
+                }
+                *stored = Some(current);
+            }
+        }
+    }
+
+    fn with_margin(mut self, margin: f32) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Liefert den ähnlichsten Eintrag, unabhängig vom Schwellwert
+    fn find_best_match(&self, features: &[f32]) -> Option<Candidate> {
+        let faces = self.faces.lock().unwrap();
+        let projected = self.projected.lock().unwrap();
+        // Bewertet wird in der Projektion, falls vorhanden; Gleichstände entscheiden die gespeicherten Einträge
+        let (gallery, features): (&dyn Gallery, Vec<f32>) = match &self.projection {
+            Some(projection) => (&*projected, projection.project(features)),
+            None => (&*faces, features.to_vec()),
+        };
+        let shortlist: Vec<usize> = self.index.lock().unwrap().search(gallery, &features, SHORTLIST);
+        let scored: Vec<(&FaceEntry, f32)> = shortlist
+            .iter()
+            .map(|&entry| (&faces[entry], matching::similarity(gallery.embeddings(entry), &features)))
+            .collect();
+        let (face, score) = break_ties(scored.iter().copied(), self.tie_break)?;
+        let runner_up = scored
+            .iter()
+            .filter(|(other, _)| other.id != face.id)
+            .map(|&(_, score)| score)
+            .max_by(f32::total_cmp);
+        Some(Candidate {
+            face: face.clone(),
+            score,
+            runner_up,
+        })
+    }
+
+    fn get(&self, id: &str) -> Option<FaceEntry> {
+        self.faces.lock().unwrap().iter().find(|face| face.id == id).cloned()
+    }
+
+    fn contains(&self, id: &str) -> bool {
+        self.faces.lock().unwrap().iter().any(|face| face.id == id)
+    }
+
+    /// Wertet den Eintrag unter der Sperre aus, ohne ihn samt Aufnahmen zu kopieren
+    fn with_entry<R>(&self, id: &str, f: impl FnOnce(&FaceEntry) -> R) -> Option<R> {
+        self.faces.lock().unwrap().iter().find(|face| face.id == id).map(f)
+    }
+
+    fn name_of(&self, id: &str) -> Option<String> {
+        self.with_entry(id, |face| face.name.clone()).flatten()
+    }
+
+    /// Vermerkt eine Wiedererkennung; gespeichert wird beim nächsten Schreiben bzw. mit `save`
+    fn record_match(&self, id: &str, timestamp: DateTime<Local>) {
+        let mut faces = self.faces.lock().unwrap();
+        if let Some(face) = faces.iter_mut().find(|face| face.id == id) {
+            face.last_seen = Some(timestamp);
+            face.match_count += 1;
+        }
+    }
+
+    /// Zieht die ähnlichste Aufnahme des Eintrags um `rate` in Richtung der aktuellen Beobachtung
+    /// (gleitender Mittelwert, danach L2-normiert). Gespeichert wird alle `ADAPT_SAVE_INTERVAL` Anpassungen.
+    fn adapt(&self, id: &str, features: &[f32], rate: f32) {
+        let mut faces = self.faces.lock().unwrap();
+        let Some(embedding) = faces
+            .iter_mut()
+            .find(|face| face.id == id)
+            .and_then(|face| {
+                face.embeddings
+                    .iter_mut()
+                    .max_by(|a, b| cosine_similarity(a, features).total_cmp(&cosine_similarity(b, features)))
+            })
+        else {
+            return;
+        };
+        for (stored, observed) in embedding.iter_mut().zip(features) {
+            *stored = (1.0 - rate) * *stored + rate * observed;
+        }
+        let norm = embedding.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|v| *v /= norm);
+        }
+        if let Some(projection) = &self.projection
+            && let Some(position) = faces.iter().position(|face| face.id == id)
+        {
+            self.projected.lock().unwrap()[position] = Self::project_entry(projection, &faces[position]);
+        }
+        let adapted = self.adapted.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.auto_save
+            && adapted.is_multiple_of(ADAPT_SAVE_INTERVAL)
+            && let Err(e) = write_face_data(&faces)
+        {
+            eprintln!("Warnung: angepasste Embeddings nicht gespeichert: {e}");
+        }
+    }
+
+    /// Ersetzt die Galerie durch den neu gelesenen Stand der Datei und baut Projektion und Index neu auf.
+    /// Abgelehnt wird ein Stand, dessen Embeddings nicht zur laufenden Erkennung passen, und jeder Stand, solange
+    /// eigene Erfassungen noch nicht gespeichert sind – sie gingen sonst verloren. Liefert die Anzahl der Einträge.
+    fn reload(&self, database: Database) -> Result<usize, String> {
+        let mut faces = self.faces.lock().unwrap();
+        let unsaved = self.unsaved();
+        if unsaved > 0 {
+            return Err(format!("{unsaved} eigene Erfassungen sind noch nicht gespeichert"));
+        }
+        if let Some(dimension) = self.dimension.or_else(|| faces.first().and_then(FaceEntry::dimension))
+            && database.faces.iter().any(|face| face.embeddings.iter().any(|embedding| embedding.len() != dimension))
+        {
+            return Err(format!("die Embeddings haben nicht die Dimension {dimension}"));
+        }
+        if let (Some(current), Some(stored)) = (DATABASE_MODEL.lock().unwrap().as_deref(), database.model.as_deref())
+            && !same_model(current, stored)
+        {
+            return Err("die Embeddings stammen von einem anderen Modell".to_string());
+        }
+        // Die Projektion ist beim Start in den Index eingegangen; eine neue gilt erst nach einem Neustart
+        if self.projection.is_some() && database.projection != self.projection {
+            return Err("die PCA-Projektion hat sich geändert; zum Übernehmen neu starten".to_string());
+        }
+        // Ohne --pca ungenutzt, aber beim nächsten Speichern weiterzugeben
+        *DATABASE_PROJECTION.lock().unwrap() = database.projection;
+        *faces = database.faces;
+        let mut projected = self.projected.lock().unwrap();
+        let index = match &self.projection {
+            Some(projection) => {
+                *projected = faces.iter().map(|face| Self::project_entry(projection, face)).collect();
+                matching::build(self.index_kind, &*projected)
+            }
+            None => matching::build(self.index_kind, &*faces),
+        };
+        *self.index.lock().unwrap() = index;
+        METRICS.gallery_size.set(faces.len() as i64);
+        Ok(faces.len())
+    }
+
+    /// Schreibt den aktuellen Stand der Datenbank; schlägt das fehl, bleiben die Erfassungen als ungespeichert
+    /// vermerkt und die Erkennung läuft weiter
+    fn save(&self) {
+        match write_face_data(&self.faces.lock().unwrap()) {
+            Ok(()) => self.unsaved.store(0, Ordering::Relaxed),
+            Err(e) => eprintln!("Warnung: Datenbank nicht gespeichert: {e}"),
+        }
+    }
+
+    /// Ohne automatisches Speichern bleiben neue Einträge nur für die laufende Sitzung im Speicher
+    fn with_auto_save(mut self, auto_save: bool) -> Self {
+        self.auto_save = auto_save;
+        self
+    }
+
+    /// Fügt einen Eintrag hinzu (bzw. ersetzt den mit derselben ID) und speichert die Datenbank
+    fn add(&self, entry: FaceEntry) {
+        let mut faces = self.faces.lock().unwrap();
+        let mut projected = self.projected.lock().unwrap();
+        let mut index = self.index.lock().unwrap();
+        let replaced = faces.iter().position(|face| face.id == entry.id);
+        let position = replaced.unwrap_or(faces.len());
+        if let Some(projection) = &self.projection {
+            let projected_entry = Self::project_entry(projection, &entry);
+            match replaced {
+                Some(position) => projected[position] = projected_entry,
+                None => projected.push(projected_entry),
+            }
+        }
+        match replaced {
+            Some(position) => faces[position] = entry,
+            None => faces.push(entry),
+        }
+        let gallery: &dyn Gallery = if self.projection.is_some() { &*projected } else { &*faces };
+        // Die alten Aufnahmen eines ersetzten Eintrags blieben sonst im Index und fänden ihn weiter
+        if replaced.is_some() {
+            *index = matching::build(self.index_kind, gallery);
+        } else {
+            index.insert(position, gallery.embeddings(position));
+        }
+        if !self.auto_save {
+            self.unsaved.fetch_add(1, Ordering::Relaxed);
+        } else if let Err(e) = write_face_data(&faces) {
+            eprintln!("Warnung: neuer Eintrag nicht gespeichert: {e}; er bleibt für diese Sitzung im Speicher");
+            self.unsaved.fetch_add(1, Ordering::Relaxed);
+        }
+        METRICS.gallery_size.set(faces.len() as i64);
+    }
+
+    /// Anzahl der Erfassungen dieser Sitzung, die noch nicht gespeichert wurden
+    fn unsaved(&self) -> usize {
+        self.unsaved.load(Ordering::Relaxed)
+    }
+}
+
+/// Eine Zeile im Audit-Log
+#[derive(Serialize)]
+struct Event<'a> {
+    timestamp: String,
+    camera: &'a str,
+    frame: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timecode: Option<&'a str>,
+    #[serde(flatten)]
+    decision: &'a FaceDecision,
+}
+
+impl<'a> Event<'a> {
+    fn new(ctx: &FrameContext<'a>, decision: &'a FaceDecision) -> Self {
+        Self {
+            timestamp: ctx.timestamp.to_rfc3339(),
+            camera: ctx.camera,
+            frame: ctx.frame_index,
+            media_ms: ctx.media_ms,
+            timecode: ctx.timecode,
+            decision,
+        }
+    }
+}
+
+/// Übergang einer Kamera zwischen Leerlauf und Anwesenheit
+#[derive(Serialize, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+enum Presence {
+    /// Seit der eingestellten Dauer kein Gesicht
+    Idle,
+    /// Erstes Gesicht nach dem Leerlauf
+    Active,
+}
+
+/// Zeile im Audit-Log für einen Leerlauf-Übergang
+#[derive(Serialize)]
+struct PresenceEvent<'a> {
+    timestamp: String,
+    camera: &'a str,
+    frame: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    media_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timecode: Option<&'a str>,
+    presence: Presence,
+    /// Sekunden seit dem letzten Gesicht
+    idle_s: f64,
+}
+
+/// Meldet, wenn eine Kamera die eingestellte Dauer lang kein Gesicht sieht, und wenn danach wieder eines erscheint
+struct IdleMonitor {
+    after: TimeDelta,
+    /// Zeitpunkt des letzten Frames mit Gesicht (bzw. des Starts)
+    last_face: Option<DateTime<Local>>,
+    idle: bool,
+}
+
+impl IdleMonitor {
+    fn new(after: TimeDelta) -> Self {
+        Self {
+            after,
+            last_face: None,
+            idle: false,
+        }
+    }
+
+    /// Liefert den Übergang samt Dauer seit dem letzten Gesicht, falls sich der Zustand ändert
+    fn update(&mut self, timestamp: DateTime<Local>, face_seen: bool) -> Option<(Presence, TimeDelta)> {
+        let since = timestamp - *self.last_face.get_or_insert(timestamp);
+        if face_seen {
+            self.last_face = Some(timestamp);
+            return std::mem::take(&mut self.idle).then_some((Presence::Active, since));
+        }
+        if !self.idle && since >= self.after {
+            self.idle = true;
+            return Some((Presence::Idle, since));
+        }
+        None
+    }
+}
+
+/// Gespeicherte Metadaten des erkannten Eintrags, ohne die Embeddings
+#[derive(Serialize)]
+struct FaceMetadata<'a> {
+    name: &'a Option<String>,
+    access: AccessLevel,
+    notes: &'a Option<String>,
+    valid_until: &'a Option<DateTime<Local>>,
+    last_seen: &'a Option<DateTime<Local>>,
+    match_count: u64,
+}
+
+/// Ereignis samt Metadaten des Eintrags für --verbose-events
+#[derive(Serialize)]
+struct VerboseEvent<'a> {
+    #[serde(flatten)]
+    event: Event<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    face: Option<FaceMetadata<'a>>,
+}
+
+impl<'a> VerboseEvent<'a> {
+    fn new(ctx: &FrameContext<'a>, decision: &'a FaceDecision, face: Option<&'a FaceEntry>) -> Self {
+        Self {
+            event: Event::new(ctx, decision),
+            face: face.map(|face| FaceMetadata {
+                name: &face.name,
+                access: face.access,
+                notes: &face.notes,
+                valid_until: &face.valid_until,
+                last_seen: &face.last_seen,
+                match_count: face.match_count,
+            }),
+        }
+    }
+}
+
+/// Liest das Salz für gehashte Audit-IDs oder legt ein neues an. Ohne dieselbe Datei sind die Hashes
+/// früherer Läufe nicht mehr zuordenbar, sie sollte daher wie die Datenbank gesichert werden.
+fn load_or_create_salt(path: &str) -> Uuid {
+    match fs::read_to_string(path) {
+        Ok(content) => Uuid::parse_str(content.trim()).unwrap_or_else(|e| {
+            eprintln!("Fehler: {path} enthält kein gültiges Salz: {e}");
+            std::process::exit(1);
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let salt = Uuid::new_v4();
+            let written = fs::write(path, salt.to_string());
+            or_exit(written.map_err(|source| FacerecError::Write { path: path.to_string(), source }));
+            say!("Neues Salz für Audit-IDs in {path} angelegt.");
+            salt
+        }
+        Err(source) => or_exit(Err(FacerecError::Read { path: path.to_string(), source })),
+    }
+}
+
+/// Protokolliert alle Entscheidungen als JSONL; wird von allen Kameras gemeinsam genutzt
+struct AuditLog {
+    path: String,
+    file: Mutex<File>,
+    /// Gesetzt: IDs werden als gesalzener Hash protokolliert
+    salt: Option<Uuid>,
+    /// Nach dem ersten Schreibfehler nur noch einmal warnen
+    failed: AtomicBool,
+}
+
+impl AuditLog {
+    fn open(path: &str) -> Result<Self, FacerecError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|source| FacerecError::Write { path: path.to_string(), source })?;
+        Ok(Self {
+            path: path.to_string(),
+            file: Mutex::new(file),
+            salt: None,
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    fn with_salt(mut self, salt: Uuid) -> Self {
+        self.salt = Some(salt);
+        self
+    }
+
+    fn record(&self, ctx: &FrameContext, decision: &FaceDecision) {
+        let anonymized;
+        let decision = match &self.salt {
+            Some(salt) => {
+                anonymized = FaceDecision {
+                    // UUID v5 ist ein SHA-1-Hash über Salz und ID
+                    id: decision.id.as_deref().map(|id| audit_query::hashed_id(salt, id)),
+                    ..decision.clone()
+                };
+                &anonymized
+            }
+            None => decision,
+        };
+        self.write(&Event::new(ctx, decision));
+    }
+
+    /// Ein Schreibfehler (z. B. volle Platte) hält die Erkennung nicht an; er wird einmal gemeldet
+    fn write(&self, event: &impl Serialize) {
+        let line = serde_json::to_string(event).expect("Fehler beim Serialisieren");
+        if let Err(e) = writeln!(self.file.lock().unwrap(), "{line}")
+            && !self.failed.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("Warnung: Audit-Log {} nicht geschrieben: {e}", self.path);
+        }
+    }
+}
+
+/// Unterdrückt wiederholte Alarme für dieselbe Person, solange sie im Bild bleibt
+struct AlertDebounce {
+    interval: Duration,
+    last_alert: Mutex<HashMap<String, Instant>>,
+    /// Dauer, die eine abgewiesene Person im Bild sein muss, bevor alarmiert wird
+    grace: Duration,
+    /// Erste und letzte Abweisung je Person während der Kulanzzeit
+    denied_since: Mutex<HashMap<String, (Instant, Instant)>>,
+}
+
+/// Reaktion auf eine abgewiesene Person
+enum Alert {
+    /// Erste Abweisung innerhalb der Kulanzzeit: Hinweis statt Alarm
+    Warn,
+    /// Weiterhin innerhalb der Kulanzzeit
+    Grace,
+    Raise,
+    /// Alarm bereits kürzlich ausgelöst
+    Suppressed,
+}
+
+/// Wer so lange nicht mehr abgewiesen wurde, beginnt wieder mit der vollen Kulanzzeit
+const GRACE_RESET: Duration = Duration::from_secs(3);
+
+impl AlertDebounce {
+    fn new(interval: Duration, grace: Duration) -> Self {
+        Self {
+            interval,
+            last_alert: Mutex::new(HashMap::new()),
+            grace,
+            denied_since: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Bestimmt die Reaktion auf eine Abweisung von `key`: während der Kulanzzeit nur ein Hinweis,
+    /// danach ein entprellter Alarm
+    fn assess(&self, key: &str) -> Alert {
+        if !self.grace.is_zero() {
+            let mut denied_since = self.denied_since.lock().unwrap();
+            let now = Instant::now();
+            denied_since.retain(|_, (_, last)| now.duration_since(*last) < GRACE_RESET);
+            let first = !denied_since.contains_key(key);
+            let (since, last) = denied_since.entry(key.to_string()).or_insert((now, now));
+            *last = now;
+            if now.duration_since(*since) < self.grace {
+                return if first { Alert::Warn } else { Alert::Grace };
+            }
+        }
+        if self.should_alert(key) { Alert::Raise } else { Alert::Suppressed }
+    }
+
+    /// Liefert `true`, wenn für `key` ein Alarm ausgelöst werden soll, und merkt sich den Zeitpunkt
+    fn should_alert(&self, key: &str) -> bool {
+        let mut last_alert = self.last_alert.lock().unwrap();
+        let now = Instant::now();
+        // Abgelaufene Einträge entfernen, damit die Tabelle nicht unbegrenzt wächst
+        last_alert.retain(|_, at| now.duration_since(*at) < self.interval);
+        if last_alert.contains_key(key) {
+            return false;
+        }
+        last_alert.insert(key.to_string(), now);
+        true
+    }
+}
+
+/// Metadaten eines abgelegten Ausschnitts (crops.jsonl)
+#[derive(Serialize)]
+struct CropRecord<'a> {
+    file: &'a str,
+    #[serde(flatten)]
+    event: Event<'a>,
+}
+
+/// Legt jeden erkannten Gesichtsausschnitt samt Metadaten ab, etwa zum späteren Beschriften
+struct CropDump {
+    dir: String,
+    metadata: Mutex<File>,
+    next: AtomicUsize,
+    bytes: AtomicU64,
+    /// Nach dem ersten Schreibfehler in crops.jsonl nur noch einmal warnen
+    failed: AtomicBool,
+}
+
+/// Nach jeweils so vielen geschriebenen Bytes wird auf den Platzbedarf hingewiesen
+const CROP_DUMP_WARN_BYTES: u64 = 500 * 1024 * 1024;
+
+impl CropDump {
+    fn open(dir: &str) -> Result<Self, FacerecError> {
+        fs::create_dir_all(dir).map_err(|source| FacerecError::Write { path: dir.to_string(), source })?;
+        let path = format!("{dir}/crops.jsonl");
+        let metadata = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|source| FacerecError::Write { path, source })?;
+        eprintln!("Hinweis: alle erkannten Gesichter werden in {dir} abgelegt; der Ordner wächst mit jeder Erkennung.");
+        Ok(Self {
+            dir: dir.to_string(),
+            metadata: Mutex::new(metadata),
+            next: AtomicUsize::new(0),
+            bytes: AtomicU64::new(0),
+            failed: AtomicBool::new(false),
+        })
+    }
+
+    fn save(&self, ctx: &FrameContext, decision: &FaceDecision, face: &Mat) {
+        let n = self.next.fetch_add(1, Ordering::Relaxed);
+        let file = format!("{}-{n:08}.png", ctx.timestamp.format("%Y%m%d-%H%M%S"));
+        let path = format!("{}/{file}", self.dir);
+        if !matches!(imgcodecs::imwrite(&path, face, &Vector::new()), Ok(true)) {
+            eprintln!("Warnung: Ausschnitt {path} konnte nicht gespeichert werden");
+            return;
+        }
+        let record = CropRecord {
+            file: &file,
+            event: Event::new(ctx, decision),
+        };
+        let line = serde_json::to_string(&record).expect("Fehler beim Serialisieren");
+        if let Err(e) = writeln!(self.metadata.lock().unwrap(), "{line}")
+            && !self.failed.swap(true, Ordering::Relaxed)
+        {
+            eprintln!("Warnung: {}/crops.jsonl nicht geschrieben: {e}", self.dir);
+        }
+
+        let size = fs::metadata(&path).map_or(0, |meta| meta.len());
+        let before = self.bytes.fetch_add(size, Ordering::Relaxed);
+        if (before + size) / CROP_DUMP_WARN_BYTES > before / CROP_DUMP_WARN_BYTES {
+            eprintln!(
+                "Warnung: bereits {} MB an Ausschnitten in {} abgelegt",
+                (before + size) / (1024 * 1024),
+                self.dir
+            );
+        }
+    }
+}
+
+/// Bildquelle einer Erkennungsschleife
+enum Source<'a> {
+    Camera(i32),
+    Video(&'a str),
+    Raw(&'a RawInput),
+}
+
+impl Source<'_> {
+    fn label(&self) -> String {
+        match self {
+            Source::Camera(index) => format!("Kamera {index}"),
+            Source::Video(path) => path.to_string(),
+            Source::Raw(input) => input.label(),
+        }
+    }
+
+    fn window(&self) -> String {
+        format!("Gesichtserkennung ({})", self.label())
+    }
+
+    /// Öffnet die Quelle für den Aufnahme-Thread
+    fn open_input(&self, properties: &[CameraProperty]) -> Input {
+        match self {
+            Source::Raw(raw) => Input::Raw(raw.open().unwrap_or_else(|e| {
+                eprintln!("Fehler: {} konnte nicht geöffnet werden: {e}", self.label());
+                std::process::exit(1);
+            })),
+            _ => Input::Capture(self.open(properties)),
+        }
+    }
+
+    /// Live-Quellen liefern weiter, während verarbeitet wird; Videodateien warten auf die Verarbeitung
+    fn is_live(&self) -> bool {
+        !matches!(self, Source::Video(_))
+    }
+
+    /// Sperrt den Kameraindex für diese Instanz (siehe `camera_lock`); None bei anderen Quellen
+    fn lock(&self) -> Option<CameraLock> {
+        let Source::Camera(index) = self else {
+            return None;
+        };
+        Some(CameraLock::acquire(*index).unwrap_or_else(|e| {
+            eprintln!("Fehler: {e}");
+            std::process::exit(1);
+        }))
+    }
+
+    /// Öffnet die Quelle; `properties` gelten nur für Kameras
+    fn open(&self, properties: &[CameraProperty]) -> videoio::VideoCapture {
+        self.try_open(properties).unwrap_or_else(|e| {
+            eprintln!("Fehler: {e}");
+            std::process::exit(1);
+        })
+    }
+
+    fn try_open(&self, properties: &[CameraProperty]) -> Result<videoio::VideoCapture, String> {
+        let cam = match self {
+            Source::Camera(index) => videoio::VideoCapture::new(*index, videoio::CAP_ANY),
+            Source::Video(path) => videoio::VideoCapture::from_file(path, videoio::CAP_ANY),
+            Source::Raw(_) => unreachable!("Rohdaten werden ohne VideoCapture gelesen"),
+        };
+        let mut cam = cam.map_err(|e| format!("{} konnte nicht geöffnet werden: {e}", self.label()))?;
+        if !cam.is_opened().unwrap_or(false) {
+            return Err(format!("{} nicht gefunden", self.label()));
+        }
+        if let Source::Camera(_) = self {
+            // Vor dem ersten Bild setzen: Auflösung und Format ändern sich sonst erst nach dem Anlaufen,
+            // und manche Treiber starten den Datenstrom dafür neu
+            for property in properties {
+                if !cam.set(property.id, property.value).unwrap_or(false) {
+                    eprintln!("Warnung: [{}] {} wird von der Kamera nicht unterstützt", self.label(), property.name);
+                }
+            }
+            // Eine belegte Kamera lässt sich oft öffnen, liefert aber keine Bilder
+            let mut probe = Mat::default();
+            let delivers = (0..CAMERA_PROBE_READS).any(|_| cam.read(&mut probe).unwrap_or(false) && !probe.empty());
+            if !delivers {
+                return Err(format!(
+                    "{} liefert keine Bilder; sie scheint von einem anderen Prozess belegt zu sein",
+                    self.label()
+                ));
+            }
+        }
+        Ok(cam)
+    }
+}
+
+/// Zustand, den sich alle Erkennungsschleifen teilen
+struct Shared<'a> {
+    args: &'a RunArgs,
+    policy: &'a dyn AccessPolicy,
+    store: FaceStore,
+    audit_log: Option<AuditLog>,
+    event_db: Option<EventDb>,
+    alerts: AlertDebounce,
+    crop_dump: Option<CropDump>,
+    snapshots: Option<SnapshotStore>,
+    scores: Option<ScoreHistogram>,
+    heartbeat: Option<Heartbeat>,
+    stop: AtomicBool,
+    /// Rückfragen zur Erfassung an das Videofenster; None: Rückfrage auf der Konsole
+    enroll_tx: Option<Sender<EnrollRequest>>,
+    detector: DetectorConfig,
+    /// Gemeinsames LBPH-Modell, wenn `--backend lbph` gewählt ist
+    lbph: Option<Mutex<LbphBackend>>,
+    overrides: Option<OverrideLists>,
+    door: Option<Door>,
+}
+
+/// Gesichtserkennung mithilfe einer oder mehrerer Kameras (oder einer Videodatei) und OpenCV.
+/// Jede Quelle läuft in einem eigenen Thread; angezeigt wird im Hauptthread, da highgui nicht threadsicher ist.
+/// Die endgültige Zugangsentscheidung trifft `policy`.
+fn recognize_face_from_camera(args: &RunArgs, model: &ModelArgs, detector: DetectorConfig, policy: &dyn AccessPolicy) {
+    let sources: Vec<Source> = match (&args.video, &args.raw_input) {
+        (Some(path), _) => vec![Source::Video(path)],
+        (None, Some(input)) => vec![Source::Raw(input)],
+        (None, None) => args.camera_index.iter().map(|&index| Source::Camera(index)).collect(),
+    };
+    if let Some(framing) = args.protocol {
+        protocol::start(framing);
+    }
+    // Das Modell lädt im Hintergrund, während die Fenster erscheinen und Quellen und Datenbank geöffnet werden
+    say!("Embedding-Modell wird geladen …");
+    for source in &sources {
+        show_loading(&source.window());
+    }
+    let (mut embedders, inputs, _locks, store) = thread::scope(|scope| {
+        let loader = scope.spawn(|| {
+            let mut embedders: Vec<Embedder> = sources.iter().map(|_| model.embedder()).collect();
+            // Der erste Durchlauf des Netzes dauert ein Vielfaches; er soll nicht das erste Gesicht verzögern
+            for embedder in &mut embedders {
+                let duration = or_exit(embedder.warm_up());
+                if !duration.is_zero() {
+                    say!("Embedding-Modell aufgewärmt ({} ms).", duration.as_millis());
+                }
+            }
+            embedders
+        });
+        // Vor dem Öffnen sperren, damit eine zweite Instanz nicht erst an der belegten Kamera scheitert
+        let locks: Vec<CameraLock> = sources.iter().filter_map(Source::lock).collect();
+        let inputs: Vec<Input> = sources.iter().map(|source| source.open_input(&args.cam_prop)).collect();
+        let store = FaceStore::load()
+            .with_tie_break(TieBreak {
+                epsilon: args.tie_epsilon,
+                prefer: args.tie_break,
+            })
+            .with_projection(args.pca)
+            .with_index(args.index)
+            .with_margin(args.match_margin)
+            .with_auto_save(!args.no_auto_save);
+        // Fenster bedienbar halten, bis das Modell bereit ist
+        while !loader.is_finished() {
+            or_exit(highgui::wait_key(50));
+        }
+        (loader.join().unwrap(), inputs, locks, store)
+    });
+    if let Some(addr) = &args.metrics_addr
+        && let Err(e) = metrics::serve(addr)
+    {
+        eprintln!("Warnung: Metrik-Endpunkt {addr} konnte nicht gestartet werden: {e}; weiter ohne Metriken");
+    }
+    let dimension = embedders.first_mut().map(|embedder| or_exit(embedder.dimension()));
+    if let Some(dimension) = dimension {
+        store.ensure_dimension(dimension);
+        store.ensure_model(model);
+    }
+    protocol::send(&Message::Hello {
+        version: protocol::VERSION,
+        app_version: env!("CARGO_PKG_VERSION"),
+        dimension: dimension.filter(|_| matches!(args.backend, Backend::Embedding)),
+        sources: sources.iter().map(Source::label).collect(),
+    });
+    let lbph = matches!(args.backend, Backend::Lbph)
+        .then(|| Mutex::new(or_exit(LbphBackend::train(&store.faces.lock().unwrap(), args.lbph_threshold))));
+    let overrides = (args.denylist.is_some() || args.allowlist.is_some()).then(|| {
+        OverrideLists::open(args.denylist.as_deref(), args.allowlist.as_deref()).unwrap_or_else(|e| {
+            eprintln!("Fehler: {e}");
+            std::process::exit(1);
+        })
+    });
+    let (enroll_tx, enroll_rx) = mpsc::channel::<EnrollRequest>();
+    let shared = Shared {
+        args,
+        policy,
+        store,
+        audit_log: args.audit_log.as_deref().map(|path| {
+            let log = or_exit(AuditLog::open(path));
+            if args.hash_audit_ids { log.with_salt(load_or_create_salt(&args.audit_salt_file)) } else { log }
+        }),
+        event_db: args.event_db.as_deref().map(|path| {
+            EventDb::open(path).unwrap_or_else(|e| {
+                eprintln!("Fehler: {e}");
+                std::process::exit(1);
+            })
+        }),
+        alerts: AlertDebounce::new(
+            Duration::from_secs_f64(args.alert_interval),
+            Duration::from_secs_f64(args.alert_grace),
+        ),
+        crop_dump: args.dump_crops.as_deref().map(|dir| or_exit(CropDump::open(dir))),
+        snapshots: args.alert_snapshots.as_deref().map(|dir| {
+            let ttl = args.snapshot_ttl.map(|hours| Duration::from_secs_f64(hours * 3600.0));
+            or_exit(SnapshotStore::open(dir, args.max_snapshots, ttl))
+        }),
+        scores: args.score_histogram.then(ScoreHistogram::new),
+        heartbeat: args.heartbeat.map(|_| Heartbeat::new(sources.iter().map(Source::label).collect())),
+        stop: AtomicBool::new(false),
+        enroll_tx: args.gui_enroll.then_some(enroll_tx),
+        detector,
+        lbph,
+        overrides,
+        door: args.door_unlock.as_deref().zip(args.door_lock.as_deref()).map(|(unlock, lock)| {
+            Door::new(unlock, lock, Duration::from_secs_f64(args.door_hold))
+        }),
+    };
+    let (frame_tx, frame_rx) = mpsc::sync_channel::<(String, Mat)>(sources.len() * 2);
+
+    thread::scope(|scope| {
+        for ((source, embedder), input) in sources.iter().zip(embedders).zip(inputs) {
+            let frame_tx = frame_tx.clone();
+            let shared = &shared;
+            scope.spawn(move || {
+                let slot = &FrameSlot::new();
+                let interval = args.interval.map(Duration::from_secs_f64);
+                let timecode = args.timecode.then(|| input.fps().and_then(Timecode::new)).flatten();
+                if args.timecode && timecode.is_none() {
+                    eprintln!("Warnung: {} meldet keine Bildrate; Ereignisse erhalten keinen Timecode", source.label());
+                }
+                thread::scope(|inner| {
+                    inner.spawn(move || capture_frames(source, input, &args.cam_prop, &shared.stop, slot, interval));
+                    process_source(source, slot, embedder, shared, frame_tx, timecode);
+                    // Aufnahme beenden, auch wenn die Verarbeitung vorzeitig abbricht
+                    slot.close();
+                });
+            });
+        }
+        drop(frame_tx);
+        if let (Some(heartbeat), Some(seconds)) = (&shared.heartbeat, args.heartbeat) {
+            let url = args.heartbeat_url.as_deref();
+            let stop = &shared.stop;
+            scope.spawn(move || heartbeat.run(Duration::from_secs_f64(seconds), url, stop));
+        }
+        if args.watch {
+            let shared = &shared;
+            scope.spawn(move || watch_database(shared));
+        }
+
+        // Letzter Frame je Fenster, damit eine Rückfrage auch bei angehaltener Quelle sichtbar bleibt
+        let mut last_frames: HashMap<String, Mat> = HashMap::new();
+        let mut prompt: Option<GuiPrompt> = None;
+        loop {
+            match frame_rx.recv_timeout(Duration::from_millis(10)) {
+                Ok((window, frame)) => {
+                    last_frames.insert(window, frame);
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                // Alle Quellen beendet
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+            if prompt.is_none() {
+                prompt = enroll_rx.try_recv().ok().map(GuiPrompt::new);
+            }
+            for (window, frame) in &last_frames {
+                match prompt.as_ref().filter(|prompt| prompt.window() == window) {
+                    Some(prompt) => {
+                        let mut overlay = or_exit(frame.try_clone());
+                        prompt.draw(&mut overlay);
+                        or_exit(highgui::imshow(window, &overlay));
+                    }
+                    None => or_exit(highgui::imshow(window, frame)),
+                }
+            }
+
+            let key = or_exit(highgui::wait_key(1));
+            if key == 27 {
+                // ESC-Taste zum Beenden
+                shared.stop.store(true, Ordering::Relaxed);
+            } else if let Some(active) = prompt.as_mut() {
+                if key >= 0 && active.handle_key(key) {
+                    prompt = None;
+                }
+            } else if args.no_auto_save && key & 0xFF == i32::from(b's') {
+                // S-Taste: Erfassungen der Sitzung ausdrücklich speichern
+                let unsaved = shared.store.unsaved();
+                shared.store.save();
+                say!("{unsaved} neue Erfassungen gespeichert.");
+            }
+            if shared.stop.load(Ordering::Relaxed) {
+                // Wartende Kamera-Threads freigeben, sonst endet die Schleife nie
+                if let Some(active) = prompt.take() {
+                    active.cancel();
+                }
+                while let Ok(request) = enroll_rx.try_recv() {
+                    enrollment::cancel_request(request);
+                }
+            }
+        }
+        // Alle Quellen beendet: auch die Statusmeldung anhalten
+        shared.stop.store(true, Ordering::Relaxed);
+    });
+    if args.no_auto_save {
+        let unsaved = shared.store.unsaved();
+        if unsaved > 0 {
+            say!("{unsaved} Erfassungen dieser Sitzung wurden nicht gespeichert.");
+        }
+    } else {
+        // Wiedererkennungen (last_seen, match_count) sichern
+        shared.store.save();
+    }
+    if let Some(histogram) = &shared.scores {
+        histogram.report(MATCH_THRESHOLD, args.score_histogram_csv.as_deref());
+    }
+    if let Some(door) = &shared.door {
+        door.shutdown();
+    }
+    protocol::send(&Message::End);
+}
+
+/// So oft wird auch ohne Meldung des Dateisystems geprüft, ob die Erkennung beendet wurde
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+/// So lange darf keine weitere Änderung gemeldet werden, bevor neu geladen wird: andere Prozesse schreiben
+/// die Datei nicht in einem Zug, und mehrere Änderungen kurz nacheinander lösen nur ein Neuladen aus
+const WATCH_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Lädt die Datenbank neu, wenn ein anderer Prozess sie geändert hat; ein unlesbarer Stand wird übergangen
+fn watch_database(shared: &Shared) {
+    let path = Path::new(database_path());
+    // Beobachtet wird das Verzeichnis: wer die Datei durch eine neue ersetzt, ließe eine Beobachtung der
+    // Datei selbst ins Leere laufen
+    let directory = path.parent().filter(|directory| !directory.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let (tx, rx) = mpsc::channel();
+    let watcher = notify::recommended_watcher(tx)
+        .and_then(|mut watcher| watcher.watch(directory, RecursiveMode::NonRecursive).map(|()| watcher));
+    let _watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("Warnung: --watch nicht möglich: {e}; die Datenbank wird nicht neu geladen");
+            return;
+        }
+    };
+    // Zeitpunkt der letzten gemeldeten Änderung der Datenbank, die noch nicht geladen ist
+    let mut changed: Option<Instant> = None;
+    while !shared.stop.load(Ordering::Relaxed) {
+        match rx.recv_timeout(WATCH_INTERVAL) {
+            Ok(Ok(event)) => {
+                if !event.kind.is_access() && event.paths.iter().any(|changed| changed.file_name() == path.file_name())
+                {
+                    changed = Some(Instant::now());
+                }
+            }
+            Ok(Err(e)) => eprintln!("Warnung: Beobachtung der Datenbank gestört: {e}"),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+        if changed.is_none_or(|at| at.elapsed() < WATCH_DEBOUNCE) {
+            continue;
+        }
+        changed = None;
+        // Die eigenen Schreibvorgänge meldet das Dateisystem ebenso
+        if database_modified().is_some_and(|modified| Some(modified) == *LAST_WRITE.lock().unwrap()) {
+            continue;
+        }
+        let reloaded = try_read_database(database_path()).map_err(|e| e.to_string());
+        match reloaded.and_then(|database| shared.store.reload(database)) {
+            Ok(count) => {
+                say!("Datenbank geändert, neu geladen ({count} Einträge).");
+                if let Some(lbph) = &shared.lbph {
+                    let faces = shared.store.faces.lock().unwrap();
+                    match LbphBackend::train(&faces, shared.args.lbph_threshold) {
+                        Ok(trained) => *lbph.lock().unwrap() = trained,
+                        Err(e) => eprintln!("Warnung: LBPH-Modell nicht neu trainiert: {e}; das bisherige gilt weiter"),
+                    }
+                }
+            }
+            Err(e) => eprintln!("Warnung: Datenbank nicht neu geladen: {e}; die bisherige Galerie bleibt gültig"),
+        }
+    }
+}
+
+/// Leseversuche nach dem Öffnen einer Kamera, bevor sie als belegt gilt
+const CAMERA_PROBE_READS: usize = 10;
+
+/// Fehlgeschlagene Lesevorgänge einer laufenden Kamera in Folge, bevor sie neu geöffnet wird, und die
+/// Pause dazwischen; überbrückt kurze Aussetzer (USB-Reset, Treiber) von zusammen etwa fünf Sekunden
+const CAMERA_READ_RETRIES: u32 = 50;
+const CAMERA_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Danach gilt die Kamera als ausgefallen (z. B. ausgesteckt) und wird neu geöffnet: zuerst nach dieser Pause,
+/// die sich mit jedem erfolglosen Versuch bis zur Obergrenze verdoppelt
+const CAMERA_REOPEN_DELAY: Duration = Duration::from_secs(1);
+const CAMERA_REOPEN_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// Frames, die vor einer Aufnahme im Intervallbetrieb verworfen werden, um den Kamerapuffer zu leeren
+const STALE_BUFFERED_FRAMES: usize = 5;
 
-#[derive(Serialize, Deserialize, Clone)]
-struct FaceEntry {
-    id: String,
-    features: Vec<f32>,
-    allowed: bool, // true: Zugang erlaubt, false: Zugang verweigert
+/// Geöffnete Quelle im Aufnahme-Thread
+enum Input {
+    Capture(videoio::VideoCapture),
+    Raw(RawReader),
 }
 
-impl FaceEntry {
-    fn new(features: Vec<f32>, allowed: bool) -> Self {
-        Self {
-            id: Uuid::new_v4().to_string(),
-            features,
-            allowed,
+impl Input {
+    /// Bildrate, die die Quelle meldet
+    fn fps(&self) -> Option<f64> {
+        match self {
+            Input::Capture(cam) => cam.get(videoio::CAP_PROP_FPS).ok().filter(|fps| *fps > 0.0),
+            Input::Raw(_) => None,
         }
     }
 }
 
-/// Lädt bekannte Gesichter aus der JSON-Datei
-fn load_face_data() -> Vec<FaceEntry> {
-    let mut file = OpenOptions::new()
-        .read(true)
-        //.create(true)
-        .open(DATABASE)
-        .expect("Konnte face_data.json nicht öffnen!");
-    let mut content = String::new();
-    file.read_to_string(&mut content).unwrap();
-    serde_json::from_str(&content).unwrap_or_else(|_| vec![])
-}
-
-/// Speichert neue Gesichtsdaten in die JSON-Datei
-fn save_face_data(entry: &FaceEntry) {
-    let mut data = load_face_data();
-    data.push(entry.clone());
-    let json_data = serde_json::to_string_pretty(&data).expect("Fehler beim Serialisieren");
-    let mut file = File::create(DATABASE).expect("Fehler beim Erstellen von face_data.json");
-    file.write_all(json_data.as_bytes())
-        .expect("Fehler beim Schreiben in die Datei");
+/// Liest die Frames einer Quelle in einem eigenen Thread, damit die Aufnahme nicht auf die Verarbeitung wartet.
+/// Ist die Verarbeitung noch beschäftigt, werden ältere Frames von Live-Kameras verworfen;
+/// Videodateien werden dagegen vollständig verarbeitet.
+/// Mit `interval` wird nur in diesem Takt ein einzelner Frame aufgenommen und dazwischen geschlafen.
+/// Eine ausgefallene Kamera wird mit `properties` neu geöffnet, bis sie wieder liefert oder beendet wird.
+fn capture_frames(
+    source: &Source,
+    mut input: Input,
+    properties: &[CameraProperty],
+    stop: &AtomicBool,
+    slot: &FrameSlot,
+    interval: Option<Duration>,
+) {
+    let mut dropped: u64 = 0;
+    let mut failures: u32 = 0;
+    let mut next_capture = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        if let Some(interval) = interval {
+            // In kurzen Schritten schlafen, damit ESC nicht bis zum nächsten Takt warten muss
+            let now = Instant::now();
+            if now < next_capture {
+                thread::sleep((next_capture - now).min(Duration::from_millis(100)));
+                continue;
+            }
+            next_capture = now + interval;
+            // Der Treiber puffert einige Frames aus der Schlafphase; diese sind veraltet
+            if let Input::Capture(cam) = &mut input {
+                for _ in 0..STALE_BUFFERED_FRAMES {
+                    cam.grab().ok();
+                }
+            }
+        }
+        let frame = match &mut input {
+            Input::Capture(cam) => {
+                let mut frame = Mat::default();
+                cam.read(&mut frame).unwrap_or(false).then_some(frame)
+            }
+            Input::Raw(reader) => reader.read(),
+        };
+        let Some(frame) = frame.filter(|frame| !frame.empty()) else {
+            // Eine laufende Kamera liefert kurzzeitig nichts: erneut versuchen statt die Quelle zu beenden
+            if let Input::Capture(cam) = &mut input
+                && source.is_live()
+            {
+                if failures < CAMERA_READ_RETRIES {
+                    if failures == 0 {
+                        eprintln!("Warnung: [{}] Kamera liefert keine Bilder, neuer Versuch …", source.label());
+                    }
+                    failures += 1;
+                    thread::sleep(CAMERA_RETRY_DELAY);
+                    continue;
+                }
+                let label = source.label();
+                eprintln!("Warnung: [{label}] Kamera liefert weiterhin keine Bilder; sie wird neu geöffnet");
+                cam.release().ok();
+                let Some(cam) = reopen_camera(source, properties, stop) else {
+                    break;
+                };
+                input = Input::Capture(cam);
+                // Ab dem nächsten Bild wieder als liefernd melden; bleibt es aus, folgen erneut die Leseversuche
+                failures = 1;
+                continue;
+            }
+            // Ende der Videodatei bzw. des Datenstroms erreicht – oder die Datei ist vorher nicht weiter lesbar
+            if let Input::Capture(cam) = &input {
+                let position = cam.get(videoio::CAP_PROP_POS_FRAMES).unwrap_or(0.0);
+                let count = cam.get(videoio::CAP_PROP_FRAME_COUNT).unwrap_or(0.0);
+                if position < count {
+                    eprintln!(
+                        "Warnung: [{}] nach Frame {position} von {count} nicht weiter lesbar; Verarbeitung beendet",
+                        source.label()
+                    );
+                }
+            }
+            break;
+        };
+        if failures > 0 {
+            say!("[{}] Kamera liefert wieder Bilder.", source.label());
+            failures = 0;
+        }
+        // Aufnahmezeitpunkt: Systemuhr bei Live-Kameras, zusätzlich die Position bei Videodateien
+        let captured = Captured {
+            frame,
+            captured: Instant::now(),
+            captured_at: Local::now(),
+            media_ms: match &input {
+                Input::Capture(cam) if !source.is_live() => cam.get(videoio::CAP_PROP_POS_MSEC).ok(),
+                _ => None,
+            },
+        };
+        match slot.put(captured, source.is_live()) {
+            Put::Stored => {}
+            Put::ReplacedStale => {
+                dropped += 1;
+                METRICS.frames_skipped.inc();
+            }
+            // Verarbeitung beendet
+            Put::Closed => break,
+        }
+    }
+    slot.close();
+    if dropped > 0 {
+        say!("[{}] {dropped} Frames verworfen, während die Verarbeitung beschäftigt war.", source.label());
+    }
 }
 
-/// Berechnet die Kosinus-Ähnlichkeit zwischen zwei Feature-Vektoren
-fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
-    let dot: f32 = v1.iter().zip(v2).map(|(a, b)| a * b).sum();
-    let mag1: f32 = v1.iter().map(|a| a * a).sum::<f32>().sqrt();
-    let mag2: f32 = v2.iter().map(|b| b * b).sum::<f32>().sqrt();
-    dot / (mag1 * mag2)
+/// Öffnet eine ausgefallene Kamera in wachsenden Abständen neu; None, sobald `stop` gesetzt ist
+fn reopen_camera(source: &Source, properties: &[CameraProperty], stop: &AtomicBool) -> Option<videoio::VideoCapture> {
+    let mut delay = CAMERA_REOPEN_DELAY;
+    loop {
+        // In kurzen Schritten warten, damit ESC nicht bis zum nächsten Versuch warten muss
+        let attempt = Instant::now() + delay;
+        while Instant::now() < attempt {
+            if stop.load(Ordering::Relaxed) {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        match source.try_open(properties) {
+            Ok(cam) => {
+                say!("[{}] Kamera neu geöffnet.", source.label());
+                return Some(cam);
+            }
+            Err(e) => {
+                delay = (delay * 2).min(CAMERA_REOPEN_MAX_DELAY);
+                eprintln!("Warnung: [{}] {e}; nächster Versuch in {} s", source.label(), delay.as_secs());
+            }
+        }
+    }
 }
 
-/// Sucht in der Datenbank nach einem bekannten Gesicht (auf Basis des Feature-Vektors)
-fn find_existing_face(features: &[f32]) -> Option<FaceEntry> {
-    let known_faces = load_face_data();
-    known_faces
-        .iter()
-        .find(|face| cosine_similarity(&face.features, features) > 0.9)
-        .cloned()
-}
+/// Ein dauerhaft fehlerhafter Detektor meldet ungültige Rahmen in jedem Frame; höchstens eine Warnung je Intervall
+const INVALID_ROI_WARNING_INTERVAL: Duration = Duration::from_secs(10);
 
-/// Gesichtserkennung mithilfe der Kamera und OpenCV
-fn recognize_face_from_camera() {
-    let mut cam = videoio::VideoCapture::new(0, videoio::CAP_ANY)
-        .expect("Kamera konnte nicht geöffnet werden");
-    let mut face_cascade =
-        objdetect::CascadeClassifier::new("./haarcascade_frontalface_default.xml")
-            .expect("Fehler beim Laden des Haarcascades");
+/// Erkennungsschleife für eine einzelne Quelle; fertige Frames gehen zur Anzeige an den Hauptthread
+fn process_source(
+    source: &Source,
+    slot: &FrameSlot,
+    mut embedder: Embedder,
+    shared: &Shared,
+    frame_tx: SyncSender<(String, Mat)>,
+    smpte: Option<Timecode>,
+) {
+    let Shared {
+        args,
+        policy,
+        store,
+        audit_log,
+        alerts,
+        crop_dump,
+        snapshots,
+        scores,
+        heartbeat,
+        stop,
+        enroll_tx,
+        detector,
+        lbph,
+        overrides,
+        door,
+        event_db,
+    } = shared;
+    let label = source.label();
+    let window = source.window();
+    let mut face_detector = or_exit(FaceDetector::new(CASCADE, detector.clone()));
 
-    if !cam.is_opened().unwrap() {
-        panic!("Kamera nicht gefunden");
+    let mut landmark_detector = args.landmark_model.as_deref().map(|path| or_exit(LandmarkDetector::new(path)));
+    if args.landmark_model.is_none() {
+        warn_unaligned(&embedder);
     }
+    let mut preprocessor = or_exit(Pipeline::new(args.normalize.steps(args.clahe_clip_limit, args.gamma)));
+    let mut tracker =
+        Tracker::with_persistence(args.track_persistence).with_trail(if args.draw_trails { args.trail_length } else { 0 });
+    // Letzte Entscheidung je Spur samt Hinweis, um sie bei kurzer Verdeckung weiter anzuzeigen
+    let mut held: HashMap<u64, (FaceDecision, Option<String>)> = HashMap::new();
+    // Spuren, deren Erfassung mit --no-store-denied abgelehnt wurde; nicht erneut nachfragen
+    let mut denied_tracks: HashSet<u64> = HashSet::new();
+    // PIN-Abfrage je Spur, damit nicht in jedem Frame erneut gefragt wird
+    let mut pin_checks = PinChecks::new();
+    // Rahmen und Zeitpunkt, seit dem ein unbekanntes Gesicht ruhig liegt (--enroll-hold)
+    let mut steady: HashMap<u64, (Rect, Instant)> = HashMap::new();
+    let mut profiler = Profiler::new(args.profile);
 
-    let mut frame = Mat::default();
-    loop {
-        cam.read(&mut frame).unwrap();
-        let mut gray = Mat::default();
-        // Wir verwenden hier unsafe { std::mem::zeroed() } als Workaround für den AlgorithmHint-Parameter.
-        imgproc::cvt_color(
-            &frame,
-            &mut gray,
-            imgproc::COLOR_BGR2GRAY,
-            0,
-            unsafe { std::mem::zeroed() },
-        )
-            .unwrap();
+    let mut results = args
+        .results
+        .as_ref()
+        .map(|path| or_exit(File::create(path).map_err(|source| FacerecError::Write { path: path.clone(), source })));
 
-        let mut faces = Vector::<opencv::core::Rect>::new();
-        face_cascade
-            .detect_multi_scale(
-                &gray,
-                &mut faces,
-                1.1,
-                3,
-                objdetect::CASCADE_SCALE_IMAGE,
-                Size::new(30, 30),
-                Size::new(200, 200),
-            )
-            .unwrap();
+    let mut idle = args
+        .idle_after
+        .map(|seconds| IdleMonitor::new(TimeDelta::milliseconds((seconds * 1000.0) as i64)));
+    // Nur Live-Kameras müssen mit der Aufnahme Schritt halten
+    let mut drift = source.is_live().then(DriftMonitor::new);
+    let mut invalid_rois = Throttle::new(INVALID_ROI_WARNING_INTERVAL);
+    let mut schedule = Schedule::default();
+    let max_lag = args.max_lag.map(Duration::from_secs_f64);
+    let mut cadence = DetectionCadence::new(args.detect_every, args.max_detect_every, args.target_fps);
+    // Entscheidungen des letzten erkannten Frames für die Frames dazwischen
+    let mut shown: Vec<(FaceDecision, Option<String>)> = Vec::new();
+
+    let mut best_frames = BestFrames::default();
+    let mut frame_index: u64 = 0;
+    while !stop.load(Ordering::Relaxed) {
+        let timer = profiler.start();
+        let Some(Captured {
+            mut frame,
+            captured,
+            captured_at,
+            media_ms,
+        }) = slot.take()
+        else {
+            // Ende der Videodatei erreicht
+            break;
+        };
+        profiler.record(Stage::Capture, timer);
+        let timecode = smpte.zip(media_ms).map(|(smpte, media_ms)| smpte.at(media_ms));
+        // Lieber Frames auslassen als verspätete Ereignisse melden; der nächste Frame ist wieder aktuell
+        if max_lag.is_some_and(|max| schedule.lag(captured, media_ms) > max) {
+            METRICS.frames_skipped.inc();
+            continue;
+        }
+        if let Some(lag) = drift.as_mut().and_then(|drift| drift.check(captured)) {
+            eprintln!(
+                "[{label}] Warnung: Verarbeitung liegt {:.1} s hinter der Kamera zurück; Ereignisse werden verzögert gemeldet",
+                lag.as_secs_f64()
+            );
+        }
+        let started = Instant::now();
+        if !cadence.due() {
+            if frame.channels() == 1 {
+                frame = to_bgr(&frame);
+            }
+            for (decision, notes) in &shown {
+                let [x, y, width, height] = decision.bbox;
+                let face = Rect::new(x, y, width, height);
+                if args.privacy.masks(decision) {
+                    blur_region(&mut frame, face);
+                }
+                draw_decision(&mut frame, face, decision, notes.as_deref());
+            }
+            frame_index += 1;
+            if let Some(every) = cadence.record(started.elapsed()) {
+                say!("[{label}] Erkennung nun in jedem {every}. Frame.");
+            }
+            if !show_frame(frame, args, &window, &frame_tx) {
+                break;
+            }
+            continue;
+        }
+        shown.clear();
+
+        let timer = profiler.start();
+        let gray = or_exit(preprocessor.apply(&to_gray(&frame)));
+        if frame.channels() == 1 {
+            // IR-Kamera: für die farbige Anzeige in BGR umwandeln
+            frame = to_bgr(&frame);
+        }
+        profiler.record(Stage::Gray, timer);
+
+        // Nur innerhalb der Erkennungszone suchen, damit Passanten im Hintergrund ignoriert werden
+        let timer = profiler.start();
+        let full_frame = Rect::new(0, 0, gray.cols(), gray.rows());
+        let zone = args.region.map_or(full_frame, |region| region & full_frame);
+        let zone_faces = if zone.empty() {
+            Vector::new()
+        } else {
+            let detected = Mat::roi(&gray, zone).map_err(FacerecError::from).and_then(|zone_gray| {
+                if args.detect_scale < 1.0 {
+                    face_detector.detect(&downscale(&zone_gray, args.detect_scale))
+                } else {
+                    face_detector.detect(&zone_gray)
+                }
+            });
+            detected.unwrap_or_else(|e| {
+                eprintln!("Warnung: [{label}] Gesichtssuche fehlgeschlagen: {e}");
+                Vector::new()
+            })
+        };
+        // Koordinaten zurück auf den gesamten Frame in voller Auflösung abbilden
+        let unscale = |value: i32| (value as f64 / args.detect_scale).round() as i32;
+        let mut faces: Vector<Rect> = zone_faces
+            .iter()
+            .map(|face| {
+                let face = Rect::new(
+                    unscale(face.x) + zone.x,
+                    unscale(face.y) + zone.y,
+                    unscale(face.width),
+                    unscale(face.height),
+                );
+                // Rundung darf nicht über den Rand hinausführen
+                face & full_frame
+            })
+            .collect();
+        // Unbeschriftete Kopie für die Beweisbilder, geteilt von allen Gesichtern dieses Frames
+        let raw_frame = (snapshots.is_some() && !faces.is_empty())
+            .then(|| frame.try_clone().ok())
+            .flatten()
+            .map(Rc::new);
+        if args.region.is_some() {
+            imgproc::rectangle(&mut frame, zone, Scalar::new(0.0, 255.0, 255.0, 0.0), 1, imgproc::LINE_8, 0)
+                .unwrap();
+        }
+        // Nur die Person direkt vor der Kamera entscheiden, nicht die im Hintergrund
+        if args.select_largest
+            && let Some(largest) = faces.iter().max_by_key(|face| face.area())
+        {
+            if args.mark_skipped {
+                for face in faces.iter().filter(|&face| face != largest) {
+                    draw_neutral_face(&mut frame, face, "Hintergrund");
+                }
+            }
+            faces = Vector::from_iter([largest]);
+        }
+
+        METRICS.frames.inc();
+        if let Some(heartbeat) = heartbeat {
+            heartbeat.frame(&label);
+        }
+        METRICS.faces.inc_by(faces.len() as u64);
+        if let Some(monitor) = idle.as_mut()
+            && let Some((presence, since)) = monitor.update(captured_at, !faces.is_empty())
+        {
+            let idle_s = since.as_seconds_f64();
+            match presence {
+                Presence::Idle => say!("[{label}] Leerlauf: seit {idle_s:.0} s kein Gesicht."),
+                Presence::Active => say!("[{label}] Wieder aktiv nach {idle_s:.0} s Leerlauf."),
+            }
+            let event = PresenceEvent {
+                timestamp: captured_at.to_rfc3339(),
+                camera: &label,
+                frame: frame_index,
+                media_ms,
+                timecode: timecode.as_deref(),
+                presence,
+                idle_s,
+            };
+            if let Some(log) = audit_log {
+                log.write(&event);
+            }
+            protocol::send(&Message::Presence(&event));
+            if args.verbose_events {
+                say!("{}", serde_json::to_string(&event).expect("Fehler beim Serialisieren"));
+            }
+        }
+
+        let landmarks = match landmark_detector.as_mut() {
+            Some(detector) => detector.detect(&gray, &faces),
+            None => vec![Vec::new(); faces.len()],
+        };
+        profiler.record(Stage::Detection, timer);
+
+        let tracks = tracker.update(&faces.to_vec());
+
+        let mut decisions = Vec::new();
+        let mut actions = FrameActions::default();
+        for ((face, points), track) in faces.iter().zip(&landmarks).zip(tracks.iter_mut()) {
+            // Entartete Rahmen (leer oder außerhalb des Frames) würden Mat::roi und das Embedding abbrechen lassen
+            if !is_valid_roi(face, &gray) {
+                if let Some(count) = invalid_rois.hit() {
+                    let since = if count > 1 { format!(" ({count} seit der letzten Meldung)") } else { String::new() };
+                    eprintln!("Warnung: [{label}] ungültiger Gesichtsrahmen {face:?} übersprungen{since}");
+                }
+                continue;
+            }
+            if args.draw_landmarks {
+                landmarks::draw(&mut frame, points);
+            }
+            // Zu weit entfernte Gesichter liefern unbrauchbare Ausschnitte
+            if let Some(min_distance) = args.min_eye_distance
+                && let Some(distance) = inter_eye_distance(points)
+                && distance < min_distance
+            {
+                if args.mark_skipped {
+                    draw_neutral_face(&mut frame, face, "zu weit entfernt");
+                }
+                continue;
+            }
+            // Abgewandte Gesichter liefern unzuverlässige Embeddings und damit sichere Fehlentscheidungen
+            if let Some(pose) = estimate_pose(points)
+                && (args.max_yaw.is_some_and(|max| pose.yaw.abs() > max)
+                    || args.max_pitch.is_some_and(|max| pose.pitch.abs() > max))
+            {
+                draw_neutral_face(&mut frame, face, "bitte zur Kamera drehen");
+                continue;
+            }
 
-        for face in faces.iter() {
             // Extrahiere den Bereich des Gesichts und klone ihn
-            let roi_box = Mat::roi(&gray, face).unwrap();
-            let face_region = roi_box.try_clone().unwrap();
-            let features = extract_features(&face_region);
+            let timer = profiler.start();
+            let zoomed = args
+                .zoom
+                .filter(|&target| face.height < target)
+                .and_then(|target| zoom_face(&gray, face, target, &mut face_detector));
+            // Die Landmarken gehören zum ursprünglichen Rahmen, nicht zu dem im Zoom neu gefundenen
+            let eyes = if zoomed.is_some() { None } else { landmarks::eyes(points, face) };
+            let face_region = match zoomed.map_or_else(|| crop(&gray, face), Ok) {
+                Ok(face_region) => face_region,
+                Err(e) => {
+                    eprintln!("Warnung: [{label}] Gesichtsausschnitt fehlgeschlagen: {e}");
+                    continue;
+                }
+            };
+            profiler.record(Stage::Crop, timer);
 
-            let mut access_allowed = true;
+            // Bei zu kleinen oder unscharfen Ausschnitten lieber nicht entscheiden als sicher falsch;
+            // die Spur behält ihre bisherigen Werte, bis ein besserer Frame kommt
+            let too_small = args.defer_min_size.is_some_and(|min| face.height < min);
+            let too_blurry = args.defer_min_sharpness.is_some_and(|min| guided::sharpness(&face_region) < min);
+            if too_small || too_blurry {
+                let decision = FaceDecision {
+                    track: track.id,
+                    bbox: [face.x, face.y, face.width, face.height],
+                    id: None,
+                    score: None,
+                    best_score: None,
+                    allowed: false,
+                    review: false,
+                    pending: false,
+                    deferred: true,
+                    overridden: None,
+                };
+                if args.privacy.masks(&decision) {
+                    blur_region(&mut frame, face);
+                }
+                draw_decision(&mut frame, face, &decision, Some(if too_small { "zu klein" } else { "unscharf" }));
+                decisions.push(decision);
+                continue;
+            }
+            if let Some(raw_frame) = &raw_frame {
+                best_frames.push(track.id, guided::sharpness(&face_region), raw_frame);
+            }
 
-            let mut draw_color = Scalar::new(0.0, 255.0, 0.0, 0.0); // grün: Zugang erlaubt
+            // LBPH vergleicht die Ausschnitte selbst; das Embedding wird dann erst für eine Neuerfassung berechnet
+            let timer = profiler.start();
+            let features = match lbph {
+                Some(_) => None,
+                None => match embedder.extract_aligned(&face_region, eyes) {
+                    Ok(features) => Some(features),
+                    Err(e) => {
+                        eprintln!("Warnung: [{label}] Embedding fehlgeschlagen, Gesicht übersprungen: {e}");
+                        continue;
+                    }
+                },
+            };
+            profiler.record(Stage::Features, timer);
 
-            // Prüfe, ob das Gesicht bereits in der Datenbank vorhanden ist
-            if let Some(existing_face) = find_existing_face(&features) {
-                access_allowed = existing_face.allowed;
-                if access_allowed {
-                    println!("Willkommen zurück!");
-                    draw_color = Scalar::new(0.0, 255.0, 0.0, 0.0); // grün
-                } else {
-                    println!("ALERT: Zugang verweigert! Unbefugtes Betreten!");
-                    draw_color = Scalar::new(0.0, 0.0, 255.0, 0.0); // rot
+            // Prüfe, ob das Gesicht bereits in der Datenbank vorhanden ist.
+            // Entschieden wird anhand der über die Spur geglätteten Ähnlichkeit, damit die Anzeige nicht flackert.
+            let ctx = FrameContext {
+                camera: &label,
+                frame_index,
+                timestamp: captured_at,
+                media_ms,
+                timecode: timecode.as_deref(),
+            };
+            let timer = profiler.start();
+            let match_start = Instant::now();
+            let best_match = match (lbph, &features) {
+                (Some(lbph), _) => lbph
+                    .lock()
+                    .unwrap()
+                    .predict(&face_region)
+                    .unwrap_or_else(|e| {
+                        eprintln!("Warnung: [{label}] LBPH-Vorhersage fehlgeschlagen: {e}");
+                        None
+                    })
+                    .and_then(|(id, score)| store.get(&id).map(|face| Candidate { face, score, runner_up: None })),
+                (None, Some(features)) => store.find_best_match(features),
+                (None, None) => None,
+            };
+            METRICS.match_latency.observe(match_start.elapsed().as_secs_f64());
+            let raw_score = best_match.as_ref().map(|candidate| candidate.score);
+            let best_score = raw_score.filter(|&raw| raw >= args.score_floor);
+            let (matched, verdict, overridden) = evaluate_face(store, *policy, overrides.as_ref(), best_match, &ctx, |id, score| {
+                if let Some(histogram) = scores {
+                    histogram.record(score);
                 }
-            } else {
-                // Erstmalige Erkennung: Prompt zur Zugangskontrolle
-                println!("Neue Person erkannt. Zugang gewähren? (j/n): ");
-                let mut response = String::new();
-                io::stdin()
-                    .read_line(&mut response)
-                    .expect("Fehler beim Lesen der Eingabe");
-                access_allowed = response.trim().to_lowercase() == "j";
-                if access_allowed {
-                    println!("Zugang erlaubt. Willkommen!");
-                    draw_color = Scalar::new(0.0, 255.0, 0.0, 0.0); // grün
+                track.smooth_score(id, score, args.smoothing_window)
+            });
+            profiler.record(Stage::Matching, timer);
+            let matched_name = matched.as_ref().and_then(|(face, _)| face.name.clone());
+            let matched_notes = matched.as_ref().and_then(|(face, _)| face.notes.clone());
+            let matched_pin = matched.as_ref().and_then(|(face, _)| face.pin_hash.clone());
+            let (id, score) = matched.map_or((None, None), |(face, score)| (Some(face.id), Some(score)));
+            let verdict = match (verdict, id.as_deref(), matched_pin.as_deref()) {
+                (Decision::Enroll, _, _) if denied_tracks.contains(&track.id) => Decision::Deny,
+                (Decision::Allow | Decision::Probation, Some(_), Some(stored)) if args.require_pin => {
+                    let status = pin_checks.check(track.id, stored, &label, matched_name.as_deref());
+                    match second_factor(verdict, status) {
+                        Some(verdict) => verdict,
+                        // Bis die PIN eingegeben ist, bleibt die Tür zu und die Kamera läuft weiter
+                        None => {
+                            let decision = FaceDecision {
+                                track: track.id,
+                                bbox: [face.x, face.y, face.width, face.height],
+                                id,
+                                score,
+                                best_score: None,
+                                allowed: false,
+                                review: false,
+                                pending: false,
+                                deferred: true,
+                                overridden: None,
+                            };
+                            if args.privacy.masks(&decision) {
+                                blur_region(&mut frame, face);
+                            }
+                            draw_decision(&mut frame, face, &decision, Some("PIN eingeben"));
+                            decisions.push(decision);
+                            continue;
+                        }
+                    }
+                }
+                (verdict, _, _) => verdict,
+            };
+            let decision = match verdict {
+                Decision::Allow | Decision::Probation | Decision::Deny => {
+                    let allowed = verdict != Decision::Deny;
+                    let review = verdict == Decision::Probation;
+                    // Dieselbe ID mit derselben Entscheidung in einem weiteren Rahmen dieses Frames löst ihre
+                    // Aktionen nicht erneut aus
+                    let earlier = if args.no_dedupe_ids { None } else { actions.earlier(id.as_deref(), allowed) };
+                    if let Some(id) = &id
+                        && earlier.is_none()
+                    {
+                        store.record_match(id, ctx.timestamp);
+                        // Nur sehr sichere Treffer anpassen, damit ein Fremder das Embedding nicht zu sich zieht
+                        if args.adaptive
+                            && let Some(features) = &features
+                            && raw_score.is_some_and(|raw| raw >= args.adaptive_min_score)
+                        {
+                            store.adapt(id, features, args.adaptive_rate);
+                        }
+                    }
+                    if let (Some(list), Some(id), None) = (overridden, &id, earlier) {
+                        let list = match list {
+                            Override::Denylist => "Sperrliste",
+                            Override::Allowlist => "Freigabeliste",
+                        };
+                        say!("[{label}] {id} steht auf der {list}; gespeichertes Zugangsrecht überstimmt.");
+                    }
+                    let mut pending = false;
+                    let announce = match (earlier, allowed, id.is_some()) {
+                        (Some(earlier_pending), _, _) => {
+                            pending = earlier_pending;
+                            false
+                        }
+                        (None, true, true) => {
+                            match &matched_name {
+                                Some(name) => say!("[{label}] Willkommen zurück, {name}!"),
+                                None => say!("[{label}] Willkommen zurück!"),
+                            }
+                            true
+                        }
+                        (None, true, false) => {
+                            say!("[{label}] Zugang erlaubt.");
+                            true
+                        }
+                        (None, false, _) => {
+                            // Unbekannte Gesichter ohne ID werden über ihre Spur entprellt
+                            let key = id.clone().unwrap_or_else(|| format!("{label}/spur-{}", track.id));
+                            match alerts.assess(&key) {
+                                Alert::Warn => {
+                                    say!("[{label}] Zugang verweigert – bitte auf Unterstützung warten.");
+                                    pending = true;
+                                    true
+                                }
+                                Alert::Grace => {
+                                    pending = true;
+                                    false
+                                }
+                                Alert::Raise => {
+                                    say!("[{label}] ALERT: Zugang verweigert! Unbefugtes Betreten!");
+                                    if let Some(snapshots) = snapshots {
+                                        snapshots.save(&label, best_frames.best(track.id).unwrap_or(&frame));
+                                    }
+                                    true
+                                }
+                                Alert::Suppressed => false,
+                            }
+                        }
+                    };
+                    // Jeder erlaubte Zugang verlängert die Haltezeit der Tür
+                    if allowed
+                        && earlier.is_none()
+                        && let Some(door) = door
+                    {
+                        door.open(&label, id.as_deref());
+                    }
+                    if review && earlier.is_none() {
+                        say!("[{label}] Zugang auf Probe – Ereignis zur Prüfung vorgemerkt.");
+                    }
+                    if announce && let Some(notes) = &matched_notes {
+                        say!("[{label}] Hinweis: {notes}");
+                    }
+                    FaceDecision {
+                        track: track.id,
+                        bbox: [face.x, face.y, face.width, face.height],
+                        best_score: best_score.filter(|_| id.is_none()),
+                        id,
+                        score,
+                        allowed,
+                        review,
+                        pending,
+                        deferred: false,
+                        overridden,
+                    }
+                }
+                // Im Protokollbetrieb gehört stdin dem Elternprozess: ohne Fenster keine Rückfrage,
+                // das Gesicht bleibt unbekannt
+                Decision::Enroll if enroll_tx.is_none() && protocol::active() => FaceDecision {
+                    track: track.id,
+                    bbox: [face.x, face.y, face.width, face.height],
+                    id: None,
+                    score: None,
+                    best_score,
+                    allowed: false,
+                    review: false,
+                    pending: false,
+                    deferred: false,
+                    overridden: None,
+                },
+                Decision::Enroll => {
+                    // Erst erfassen, wenn das Gesicht eine Weile ruhig und scharf im Bild war
+                    if let Some(hold) = args.enroll_hold {
+                        let sharp = args.enroll_min_sharpness.is_none_or(|min| guided::sharpness(&face_region) >= min);
+                        let (anchor, since) = steady.entry(track.id).or_insert((face, Instant::now()));
+                        if !sharp || tracking::motion(*anchor, face) > args.enroll_max_motion {
+                            (*anchor, *since) = (face, Instant::now());
+                        }
+                        let remaining = hold - since.elapsed().as_secs_f64();
+                        if remaining > 0.0 {
+                            let decision = FaceDecision {
+                                track: track.id,
+                                bbox: [face.x, face.y, face.width, face.height],
+                                id: None,
+                                score: None,
+                                best_score,
+                                allowed: false,
+                                review: false,
+                                pending: false,
+                                deferred: true,
+                                overridden: None,
+                            };
+                            if args.privacy.masks(&decision) {
+                                blur_region(&mut frame, face);
+                            }
+                            let hint = if sharp {
+                                format!("Bitte stillhalten ({remaining:.1} s)")
+                            } else {
+                                "unscharf".to_string()
+                            };
+                            draw_neutral_face(&mut frame, face, &hint);
+                            decisions.push(decision);
+                            continue;
+                        }
+                        steady.remove(&track.id);
+                    }
+                    let features = match features {
+                        Some(features) => features,
+                        None => match embedder.extract_aligned(&face_region, eyes) {
+                            Ok(features) => features,
+                            Err(e) => {
+                                eprintln!("Warnung: [{label}] Embedding fehlgeschlagen, Gesicht nicht erfasst: {e}");
+                                continue;
+                            }
+                        },
+                    };
+                    // Erstmalige Erkennung: Prompt zur Zugangskontrolle
+                    let answer = match enroll_tx {
+                        Some(requests) => enrollment::prompt_gui(&window, requests),
+                        None => Some(enrollment::prompt_console(&label)),
+                    };
+                    // Abgebrochene Rückfrage: Gesicht nicht erfassen
+                    let Some(answer) = answer else {
+                        continue;
+                    };
+                    let access = match answer.access {
+                        AccessType::Allowed | AccessType::Visitor => AccessLevel::Allowed,
+                        AccessType::Probation => AccessLevel::Probation,
+                        AccessType::Denied => AccessLevel::Denied,
+                    };
+                    let access_allowed = access != AccessLevel::Denied;
+                    if access_allowed {
+                        let name = answer.name.as_deref().map_or(String::new(), |name| format!(", {name}"));
+                        say!("[{label}] Zugang erlaubt. Willkommen{name}!");
+                    } else {
+                        say!("[{label}] ALERT: Zugang verweigert! Unbefugtes Betreten!");
+                    }
+                    // Abgewiesene nur melden, nicht speichern: die Spur bleibt verweigert, solange sie besteht
+                    if !access_allowed && args.no_store_denied {
+                        denied_tracks.insert(track.id);
+                        // Als Alarm vermerken, damit die Folgeframes entprellt werden
+                        alerts.assess(&format!("{label}/spur-{}", track.id));
+                        if let Some(snapshots) = snapshots {
+                            snapshots.save(&label, best_frames.best(track.id).unwrap_or(&frame));
+                        }
+                        track.reset_scores();
+                        FaceDecision {
+                            track: track.id,
+                            bbox: [face.x, face.y, face.width, face.height],
+                            id: None,
+                            score: None,
+                            best_score,
+                            allowed: false,
+                            review: false,
+                            pending: false,
+                            deferred: false,
+                            overridden: None,
+                        }
+                    } else {
+                        let mut new_entry = if args.deterministic_ids {
+                            FaceEntry::with_id(deterministic_id(&features), features, access)
+                        } else {
+                            FaceEntry::new(features, access)
+                        };
+                        new_entry.name = answer.name;
+                        if answer.access == AccessType::Visitor {
+                            new_entry.valid_until = Some(ctx.timestamp + TimeDelta::hours(args.visitor_hours));
+                        }
+                        new_entry.crop = save_face_crop(&new_entry.id, &face_region);
+                        let id = new_entry.id.clone();
+                        if args.on_enroll.is_some() || args.on_enroll_url.is_some() {
+                            let event = hooks::Enrolled {
+                                timestamp: ctx.timestamp.to_rfc3339(),
+                                camera: label.clone(),
+                                id: id.clone(),
+                                name: new_entry.name.clone(),
+                                access,
+                                allowed: access_allowed,
+                            };
+                            hooks::fire(args.on_enroll.as_deref(), args.on_enroll_url.as_deref(), event);
+                        }
+                        if access_allowed && let Some(door) = door {
+                            door.open(&label, Some(&id));
+                        }
+                        store.add(new_entry);
+                        if let Some(lbph) = lbph
+                            && let Err(e) = lbph.lock().unwrap().update(&id, &face_region)
+                        {
+                            eprintln!("Warnung: [{label}] LBPH-Modell nicht nachtrainiert: {e}");
+                        }
+                        track.reset_scores();
+                        FaceDecision {
+                            track: track.id,
+                            bbox: [face.x, face.y, face.width, face.height],
+                            id: Some(id),
+                            score: None,
+                            best_score,
+                            allowed: access_allowed,
+                            review: false,
+                            pending: false,
+                            deferred: false,
+                            overridden: None,
+                        }
+                    }
+                }
+            };
+            if let Some(log) = audit_log {
+                log.record(&ctx, &decision);
+            }
+            if let Some(db) = event_db {
+                let name = decision.id.as_deref().and_then(|id| store.name_of(id));
+                db.record(&event_db::Row {
+                    timestamp: ctx.timestamp.format("%Y-%m-%d %H:%M:%S%.3f").to_string(),
+                    camera: ctx.camera,
+                    frame: ctx.frame_index,
+                    track: decision.track,
+                    id: decision.id.as_deref(),
+                    name: name.as_deref(),
+                    score: decision.score,
+                    decision: decision.outcome(),
+                });
+            }
+            protocol::send(&Message::Decision(&Event::new(&ctx, &decision)));
+            if args.verbose_events {
+                let line = decision.id.as_deref().and_then(|id| {
+                    store.with_entry(id, |face| serde_json::to_string(&VerboseEvent::new(&ctx, &decision, Some(face))))
+                });
+                let line = line.unwrap_or_else(|| serde_json::to_string(&VerboseEvent::new(&ctx, &decision, None)));
+                say!("{}", line.expect("Fehler beim Serialisieren"));
+            }
+            let masked = args.privacy.masks(&decision);
+            if let Some(dump) = crop_dump {
+                if masked {
+                    match face_region.try_clone() {
+                        Ok(mut blurred) => {
+                            let whole = Rect::new(0, 0, blurred.cols(), blurred.rows());
+                            blur_region(&mut blurred, whole);
+                            dump.save(&ctx, &decision, &blurred);
+                        }
+                        Err(e) => eprintln!("Warnung: [{label}] Ausschnitt nicht abgelegt: {e}"),
+                    }
                 } else {
-                    println!("ALERT: Zugang verweigert! Unbefugtes Betreten!");
-                    draw_color = Scalar::new(0.0, 0.0, 255.0, 0.0); // rot
+                    dump.save(&ctx, &decision, &face_region);
                 }
-                let new_entry = FaceEntry::new(features, access_allowed);
-                save_face_data(&new_entry);
             }
-            // Zeichne den Rahmen um das erkannte Gesicht
-            imgproc::rectangle(&mut frame, face, draw_color, 2, imgproc::LINE_8, 0)
-                .unwrap();
 
-            // Falls der Zugang verweigert ist, füge oberhalb des Rahmens den Text hinzu
-            if !access_allowed {
-                let text = "Zugang verweigert";
-                // Positioniere den Text etwas oberhalb des Rechtecks
-                let org = Point::new(face.x, if face.y - 10 > 0 { face.y - 10 } else { face.y });
-                imgproc::put_text(
-                    &mut frame,
-                    text,
-                    org,
-                    imgproc::FONT_HERSHEY_SIMPLEX,
-                    0.8,
-                    Scalar::new(0.0, 0.0, 255.0, 0.0),
-                    2,
-                    imgproc::LINE_AA,
-                    false,
-                )
-                    .unwrap();
-            }
-        }
-
-        highgui::imshow("Gesichtserkennung", &frame).unwrap();
-        if highgui::wait_key(10).unwrap() == 27 {
-            // ESC-Taste zum Beenden
+            let timer = profiler.start();
+            if masked {
+                blur_region(&mut frame, face);
+            }
+            draw_decision(&mut frame, face, &decision, matched_notes.as_deref());
+            if args.confidence_bar
+                && let Some(score) = raw_score
+            {
+                draw_confidence_bar(&mut frame, face, score);
+            }
+            profiler.record(Stage::Draw, timer);
+            shown.push((decision.clone(), matched_notes.clone()));
+            if args.track_persistence > 0 {
+                held.insert(track.id, (decision.clone(), matched_notes));
+            }
+            actions.record(&decision);
+            decisions.push(decision);
+        }
+        for track in tracker.coasting() {
+            if let Some((decision, notes)) = held.get(&track.id) {
+                if args.privacy.masks(decision) {
+                    blur_region(&mut frame, track.bbox);
+                }
+                draw_decision(&mut frame, track.bbox, decision, notes.as_deref());
+            }
+        }
+        if args.draw_trails {
+            for track in tracker.tracks() {
+                draw_trail(&mut frame, track.trail());
+            }
+        }
+        held.retain(|id, _| tracker.contains(*id));
+        denied_tracks.retain(|id| tracker.contains(*id));
+        pin_checks.retain(|id| tracker.contains(id));
+        steady.retain(|id, _| tracker.contains(*id));
+        best_frames.retain(|id| tracker.contains(id));
+
+        if let Some(file) = results.as_mut() {
+            let record = FrameResult {
+                frame: frame_index,
+                timestamp_ms: media_ms.unwrap_or_default(),
+                timecode: timecode.as_deref(),
+                faces: &decisions,
+            };
+            let line = serde_json::to_string(&record).expect("Fehler beim Serialisieren");
+            let path = args.results.clone().unwrap_or_default();
+            or_exit(writeln!(file, "{line}").map_err(|source| FacerecError::Write { path, source }));
+        }
+        frame_index += 1;
+        profiler.finish_frame();
+        if let Some(every) = cadence.record(started.elapsed()) {
+            say!("[{label}] Erkennung nun in jedem {every}. Frame.");
+        }
+        if !show_frame(frame, args, &window, &frame_tx) {
             break;
         }
     }
+    profiler.report(&label);
+}
+
+/// Übergibt den Frame an die Anzeige im Hauptthread; `false`, wenn diese beendet ist
+fn show_frame(frame: Mat, args: &RunArgs, window: &str, frame_tx: &SyncSender<(String, Mat)>) -> bool {
+    // Erkannt wird in voller Auflösung; verkleinert wird nur, was angezeigt wird
+    let display = if args.display_scale < 1.0 { downscale(&frame, args.display_scale) } else { frame };
+    frame_tx.send((window.to_string(), display)).is_ok()
+}
+
+/// Bewertet den besten Treffer eines Gesichts und holt die Entscheidung der Richtlinie ein.
+/// `adjust` erhält ID und rohe Ähnlichkeit des besten Treffers und liefert den Wert, der gegen den Schwellwert geprüft wird
+/// (z. B. über die Spur geglättet).
+fn evaluate_face(
+    store: &FaceStore,
+    policy: &dyn AccessPolicy,
+    overrides: Option<&OverrideLists>,
+    best_match: Option<Candidate>,
+    ctx: &FrameContext,
+    adjust: impl FnOnce(&str, f32) -> f32,
+) -> (Option<(FaceEntry, f32)>, Decision, Option<Override>) {
+    let mut ambiguous = false;
+    let matched = best_match.and_then(|candidate| {
+        let score = adjust(&candidate.face.id, candidate.score);
+        // Mehrdeutig, wenn ein zweiter Eintrag kaum weniger ähnlich ist
+        ambiguous = score > MATCH_THRESHOLD
+            && store.margin > 0.0
+            && candidate
+                .runner_up
+                .is_some_and(|runner_up| candidate.score - runner_up <= store.margin);
+        (score > MATCH_THRESHOLD && !ambiguous).then_some((candidate.face, score))
+    });
+    // Ein mehrdeutiges Gesicht ist nicht unbekannt: abweisen statt nachzufragen, sonst würde ein
+    // Beinahe-Duplikat einer gespeicherten Person erfasst
+    let mut verdict = if ambiguous {
+        Decision::Deny
+    } else {
+        policy.decide(matched.as_ref().map(|(face, score)| (face, *score)), ctx)
+    };
+    // Sperr- und Freigabeliste überstimmen das gespeicherte Zugangsrecht und die Richtlinie
+    let overridden = overrides.zip(matched.as_ref()).and_then(|(lists, (face, _))| lists.check(&face.id));
+    match overridden {
+        Some(Override::Denylist) => verdict = Decision::Deny,
+        Some(Override::Allowlist) => verdict = Decision::Allow,
+        None => {}
+    }
+    match verdict {
+        Decision::Allow => METRICS.allows.inc(),
+        Decision::Probation => {
+            METRICS.allows.inc();
+            METRICS.reviews.inc();
+        }
+        Decision::Deny => METRICS.denies.inc(),
+        Decision::Enroll => METRICS.unknowns.inc(),
+    }
+    (matched, verdict, overridden)
+}
+
+/// Zeichnet den Rahmen um ein entschiedenes Gesicht; `caption` erscheint unterhalb des Rahmens
+fn draw_decision(frame: &mut Mat, face: Rect, decision: &FaceDecision, caption: Option<&str>) {
+    if decision.deferred {
+        let color = Scalar::new(200.0, 200.0, 200.0, 0.0); // grau: noch keine Entscheidung
+        imgproc::rectangle(frame, face, color, 1, imgproc::LINE_8, 0).unwrap();
+        draw_label(frame, "Analyse laeuft ...", face, true, color, 0.75);
+        if let Some(caption) = caption {
+            draw_label(frame, caption, face, false, color, 0.6);
+        }
+        return;
+    }
+    let draw_color = if decision.allowed {
+        Scalar::new(0.0, 255.0, 0.0, 0.0) // grün: Zugang erlaubt
+    } else if decision.pending {
+        Scalar::new(0.0, 165.0, 255.0, 0.0) // orange: verweigert, Alarm steht noch aus
+    } else {
+        Scalar::new(0.0, 0.0, 255.0, 0.0) // rot: Zugang verweigert
+    };
+    // Zeichne den Rahmen um das erkannte Gesicht
+    imgproc::rectangle(frame, face, draw_color, 2, imgproc::LINE_8, 0)
+        .unwrap();
+
+    // Falls der Zugang verweigert ist, füge oberhalb des Rahmens den Text hinzu
+    if decision.pending {
+        draw_label(frame, "Bitte auf Unterstuetzung warten", face, true, draw_color, 1.0);
+    } else if !decision.allowed {
+        draw_label(frame, "Zugang verweigert", face, true, Scalar::new(0.0, 0.0, 255.0, 0.0), 1.0);
+    }
+
+    if let Some(caption) = caption {
+        draw_label(frame, caption, face, false, draw_color, 0.75);
+    }
+}
+
+/// Balken am unteren Rand des Gesichtsrahmens: gefüllt nach der Ähnlichkeit des besten Treffers,
+/// grün ab dem Schwellwert, sonst rot; ein weißer Strich markiert den Schwellwert
+fn draw_confidence_bar(frame: &mut Mat, face: Rect, score: f32) {
+    let height = (face.height / 16).clamp(4, 12);
+    let bar = Rect::new(face.x + 4, face.y + face.height - height - 4, (face.width - 8).max(1), height);
+    let fill = (bar.width as f32 * score.clamp(0.0, 1.0)) as i32;
+    let color = if score > MATCH_THRESHOLD {
+        Scalar::new(0.0, 200.0, 0.0, 0.0)
+    } else {
+        Scalar::new(0.0, 0.0, 220.0, 0.0)
+    };
+    imgproc::rectangle(frame, bar, Scalar::new(30.0, 30.0, 30.0, 0.0), imgproc::FILLED, imgproc::LINE_8, 0).unwrap();
+    if fill > 0 {
+        imgproc::rectangle(frame, Rect::new(bar.x, bar.y, fill, bar.height), color, imgproc::FILLED, imgproc::LINE_8, 0)
+            .unwrap();
+    }
+    let mark = bar.x + (bar.width as f32 * MATCH_THRESHOLD) as i32;
+    imgproc::line(
+        frame,
+        Point::new(mark, bar.y - 2),
+        Point::new(mark, bar.y + bar.height + 1),
+        Scalar::new(255.0, 255.0, 255.0, 0.0),
+        1,
+        imgproc::LINE_8,
+        0,
+    )
+        .unwrap();
 }
 
-/// Extrahiere Merkmale aus einem Gesicht (Dummy-Implementierung)
-fn extract_features(face: &Mat) -> Vec<f32> {
-    let mut resized = Mat::default();
-    imgproc::resize(
-        face,
-        &mut resized,
-        Size::new(100, 100),
+/// Zeichnet einen Bildbereich so stark weich, dass das Gesicht darin nicht mehr erkennbar ist
+fn blur_region(frame: &mut Mat, region: Rect) {
+    let region = region & Rect::new(0, 0, frame.cols(), frame.rows());
+    if region.area() == 0 {
+        return;
+    }
+    // Kernel proportional zur Gesichtsgröße, damit auch große Gesichter unkenntlich werden (muss ungerade sein)
+    let kernel = (region.width.max(region.height) / 2) | 1;
+    let mut roi = Mat::roi_mut(frame, region).unwrap();
+    let mut blurred = Mat::default();
+    imgproc::gaussian_blur(
+        &*roi,
+        &mut blurred,
+        Size::new(kernel, kernel),
         0.0,
         0.0,
-        imgproc::INTER_LINEAR,
+        core::BORDER_DEFAULT,
+        core::AlgorithmHint::ALGO_HINT_DEFAULT,
+    )
+        .unwrap();
+    blurred.copy_to(&mut *roi).unwrap();
+}
+
+/// Schreibt einen Text mit dunklem Hintergrund ober- oder unterhalb eines Gesichtsrahmens.
+/// Die Schriftgröße richtet sich nach der Gesichtsgröße (`relative` skaliert zusätzlich), und der Text
+/// wird so verschoben, dass er vollständig im Bild bleibt.
+fn draw_label(frame: &mut Mat, text: &str, face: Rect, above: bool, color: Scalar, relative: f64) {
+    const FONT: i32 = imgproc::FONT_HERSHEY_SIMPLEX;
+    const PADDING: i32 = 4;
+    let scale = (face.height as f64 / 200.0).clamp(0.4, 1.2) * relative;
+    let thickness = if scale >= 0.7 { 2 } else { 1 };
+    let mut baseline = 0;
+    let size = imgproc::get_text_size(text, FONT, scale, thickness, &mut baseline).unwrap();
+    let (box_width, box_height) = (size.width + 2 * PADDING, size.height + baseline + 2 * PADDING);
+
+    // Bevorzugte Seite, bei Platzmangel die andere; zuletzt an den Bildrand geklemmt
+    let top_above = face.y - box_height - 2;
+    let top_below = face.y + face.height + 2;
+    let top = match (above, top_above >= 0, top_below + box_height <= frame.rows()) {
+        (true, true, _) | (false, true, false) => top_above,
+        _ => top_below,
+    };
+    let top = top.clamp(0, (frame.rows() - box_height).max(0));
+    let left = face.x.clamp(0, (frame.cols() - box_width).max(0));
+
+    let background = Rect::new(left, top, box_width, box_height);
+    imgproc::rectangle(frame, background, Scalar::new(30.0, 30.0, 30.0, 0.0), imgproc::FILLED, imgproc::LINE_8, 0)
+        .unwrap();
+    imgproc::put_text(
+        frame,
+        text,
+        Point::new(left + PADDING, top + PADDING + size.height),
+        FONT,
+        scale,
+        color,
+        thickness,
+        imgproc::LINE_AA,
+        false,
+    )
+        .unwrap();
+}
+
+/// Erkennt alle Gesichter eines Standbilds und schreibt das Bild mit Rahmen, Namen und Ähnlichkeiten.
+/// Unbekannte Gesichter werden nur markiert, nicht erfasst.
+fn annotate_image(input: &str, output: &str, privacy: &PrivacyArgs, cli: &Cli) {
+    let mut embedder = cli.model.embedder();
+    let mut image = or_exit(read_image(input));
+    let gray = to_gray(&image);
+    let faces = or_exit(cli.detector.detector().detect(&gray));
+    let store = FaceStore::load();
+    store.ensure_dimension(or_exit(embedder.dimension()));
+    store.ensure_model(&cli.model);
+    let ctx = FrameContext {
+        camera: input,
+        frame_index: 0,
+        timestamp: Local::now(),
+        media_ms: None,
+        timecode: None,
+    };
+
+    println!("{} Gesicht(er) in {input} gefunden.", faces.len());
+    for (index, face) in faces.iter().enumerate() {
+        let face_region = or_exit(crop(&gray, face));
+        let features = or_exit(embedder.extract(&face_region));
+        let best_match = store.find_best_match(&features);
+        let best_score = best_match
+            .as_ref()
+            .map(|candidate| candidate.score)
+            .filter(|&score| score >= cli.run.score_floor);
+        let (matched, verdict, overridden) = evaluate_face(&store, &DefaultPolicy, None, best_match, &ctx, |_, score| score);
+        let caption = match &matched {
+            Some((entry, score)) => {
+                let notes = entry.notes.as_deref().map_or(String::new(), |notes| format!(" – {notes}"));
+                format!("{} ({score:.2}){notes}", entry.name.as_deref().unwrap_or(&entry.id))
+            }
+            None => "unbekannt".to_string(),
+        };
+        let decision = FaceDecision {
+            track: index as u64,
+            bbox: [face.x, face.y, face.width, face.height],
+            score: matched.as_ref().map(|(_, score)| *score),
+            best_score: best_score.filter(|_| matched.is_none()),
+            id: matched.map(|(entry, _)| entry.id),
+            allowed: matches!(verdict, Decision::Allow | Decision::Probation),
+            review: verdict == Decision::Probation,
+            pending: false,
+            deferred: false,
+            overridden,
+        };
+        println!(
+            "  Gesicht {index} bei {:?}: {caption} – {}",
+            decision.bbox,
+            match verdict {
+                Decision::Allow => "Zugang erlaubt",
+                Decision::Probation => "Zugang auf Probe (zur Prüfung)",
+                Decision::Deny => "Zugang verweigert",
+                Decision::Enroll => "unbekannt",
+            }
+        );
+        if privacy.masks(&decision) {
+            blur_region(&mut image, face);
+        }
+        draw_decision(&mut image, face, &decision, Some(&caption));
+    }
+
+    if !or_exit(imgcodecs::imwrite(output, &image, &Vector::new())) {
+        eprintln!("Fehler: Bild {output} konnte nicht geschrieben werden");
+        std::process::exit(1);
+    }
+}
+
+/// Wandelt ein BGR- oder BGRA-Bild in Graustufen um; einkanalige Bilder (z. B. von IR-Kameras) bleiben unverändert
+fn to_gray(frame: &Mat) -> Mat {
+    let code = match frame.channels() {
+        1 => return frame.try_clone().unwrap(),
+        4 => imgproc::COLOR_BGRA2GRAY,
+        _ => imgproc::COLOR_BGR2GRAY,
+    };
+    let mut gray = Mat::default();
+    // Wir verwenden hier unsafe { std::mem::zeroed() } als Workaround für den AlgorithmHint-Parameter.
+    imgproc::cvt_color(
+        frame,
+        &mut gray,
+        code,
+        0,
+        unsafe { std::mem::zeroed() },
+    )
+        .unwrap();
+    gray
+}
+
+/// Prüft, ob der Rahmen eine Fläche hat und vollständig im Bild liegt
+fn is_valid_roi(face: Rect, image: &Mat) -> bool {
+    let bounds = Rect::new(0, 0, image.cols(), image.rows());
+    face.width > 0 && face.height > 0 && (face & bounds) == face
+}
+
+/// Kopiert den Ausschnitt, damit er unabhängig vom Bild weiterverwendet werden kann
+fn crop(image: &Mat, region: Rect) -> opencv::Result<Mat> {
+    Mat::roi(image, region)?.try_clone()
+}
+
+/// Höchste Vergrößerung des digitalen Zooms; darüber erfindet die Interpolation nur noch Unschärfe
+const MAX_ZOOM: f64 = 4.0;
+
+/// Vergrößert das Gesicht samt halber Rahmenbreite Umgebung auf etwa `target` Pixel Höhe und sucht darin
+/// erneut; liefert den Ausschnitt des dort gefundenen Gesichts, das der Mitte am nächsten liegt.
+/// None, wenn der zweite Durchlauf kein Gesicht findet.
+fn zoom_face(gray: &Mat, face: Rect, target: i32, detector: &mut FaceDetector) -> Option<Mat> {
+    let margin_x = face.width / 2;
+    let margin_y = face.height / 2;
+    let bounds = Rect::new(0, 0, gray.cols(), gray.rows());
+    let context = Rect::new(face.x - margin_x, face.y - margin_y, face.width + 2 * margin_x, face.height + 2 * margin_y)
+        & bounds;
+    let scale = (target as f64 / face.height as f64).min(MAX_ZOOM);
+    let mut zoomed = Mat::default();
+    imgproc::resize(&Mat::roi(gray, context).ok()?, &mut zoomed, Size::default(), scale, scale, imgproc::INTER_CUBIC)
+        .ok()?;
+    let center = Point::new(
+        ((face.x + face.width / 2 - context.x) as f64 * scale) as i32,
+        ((face.y + face.height / 2 - context.y) as f64 * scale) as i32,
+    );
+    let distance = |candidate: &Rect| {
+        let dx = candidate.x + candidate.width / 2 - center.x;
+        let dy = candidate.y + candidate.height / 2 - center.y;
+        dx * dx + dy * dy
+    };
+    let refined = detector.detect(&zoomed).ok()?.iter().min_by_key(distance)?;
+    is_valid_roi(refined, &zoomed).then(|| crop(&zoomed, refined).ok()).flatten()
+}
+
+/// Platzhalter im Videofenster, solange das Modell lädt
+fn show_loading(window: &str) {
+    let mut placeholder =
+        Mat::new_rows_cols_with_default(480, 640, opencv::core::CV_8UC3, Scalar::new(40.0, 40.0, 40.0, 0.0)).unwrap();
+    imgproc::put_text(
+        &mut placeholder,
+        "Modell wird geladen ...",
+        Point::new(170, 240),
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        0.8,
+        Scalar::new(255.0, 255.0, 255.0, 0.0),
+        1,
+        imgproc::LINE_AA,
+        false,
+    )
+        .unwrap();
+    or_exit(highgui::imshow(window, &placeholder));
+    or_exit(highgui::wait_key(1));
+}
+
+/// Zeichnet den Bewegungspfad einer Spur als Linienzug
+fn draw_trail(frame: &mut Mat, trail: &VecDeque<Point>) {
+    if trail.len() < 2 {
+        return;
+    }
+    let points: Vector<Point> = trail.iter().copied().collect();
+    let lines: Vector<Vector<Point>> = Vector::from_iter([points]);
+    imgproc::polylines(frame, &lines, false, Scalar::new(255.0, 200.0, 0.0, 0.0), 2, imgproc::LINE_AA, 0).unwrap();
+}
+
+/// Wandelt ein Graustufenbild in BGR um, damit farbige Rahmen gezeichnet werden können
+fn to_bgr(gray: &Mat) -> Mat {
+    let mut bgr = Mat::default();
+    imgproc::cvt_color(
+        gray,
+        &mut bgr,
+        imgproc::COLOR_GRAY2BGR,
+        0,
+        unsafe { std::mem::zeroed() },
     )
         .unwrap();
+    bgr
+}
+
+/// Verkleinert ein Bild um den angegebenen Faktor
+fn downscale(image: &impl ToInputArray, scale: f64) -> Mat {
+    let mut small = Mat::default();
+    imgproc::resize(image, &mut small, Size::default(), scale, scale, imgproc::INTER_AREA).unwrap();
+    small
+}
+
+/// Fehlende oder beschädigte Dateien liefert imread als leere Matrix statt als Fehler
+fn read_image(path: &str) -> Result<Mat, FacerecError> {
+    let image = imgcodecs::imread(path, imgcodecs::IMREAD_COLOR)?;
+    if image.empty() {
+        return Err(FacerecError::Invalid { path: path.to_string(), reason: "kein lesbares Bild".to_string() });
+    }
+    Ok(image)
+}
+
+/// Liest ein Bild und liefert den Graustufen-Ausschnitt seines größten Gesichts
+fn largest_face_in_image(path: &str, detector: &mut FaceDetector) -> Result<Mat, FacerecError> {
+    let gray = to_gray(&read_image(path)?);
+    let Some(face) = detector.detect(&gray)?.iter().max_by_key(|face| face.area()) else {
+        return Err(FacerecError::Invalid { path: path.to_string(), reason: "kein Gesicht gefunden".to_string() });
+    };
+    Ok(crop(&gray, face)?)
+}
+
+/// Gruppiert die Bilder eines Ordners nach der Ähnlichkeit ihres größten Gesichts und gibt die Zuordnung aus.
+/// Findet der Cascade kein Gesicht (etwa bei bereits ausgeschnittenen Gesichtern), zählt das ganze Bild.
+fn cluster_images(dir: &str, threshold: f32, cli: &Cli) {
+    let mut embedder = cli.model.embedder();
+    let mut detector = cli.detector.detector();
+    let entries = fs::read_dir(dir).map_err(|source| FacerecError::Read { path: dir.to_string(), source });
+    let mut paths: Vec<_> = or_exit(entries)
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .collect();
+    paths.sort();
+
+    let mut files = Vec::new();
+    let mut embeddings = Vec::new();
+    for path in &paths {
+        let path = path.to_string_lossy();
+        let image = imgcodecs::imread(&path, imgcodecs::IMREAD_COLOR).unwrap_or_default();
+        if image.empty() {
+            // Kein Bild (z. B. crops.jsonl)
+            continue;
+        }
+        let gray = to_gray(&image);
+        let face_region = match or_exit(detector.detect(&gray)).iter().max_by_key(|face| face.area()) {
+            Some(face) => or_exit(crop(&gray, face)),
+            None => gray,
+        };
+        embeddings.push(or_exit(embedder.extract(&face_region)));
+        files.push(path.into_owned());
+    }
+
+    let clusters = clustering::agglomerative(&embeddings, threshold);
+    println!("{} Bilder in {} Gruppen (Schwellwert {threshold}):", files.len(), clusters.len());
+    for (index, cluster) in clusters.iter().enumerate() {
+        println!("Gruppe {index} ({} Bilder):", cluster.len());
+        for &member in cluster {
+            println!("  {}", files[member]);
+        }
+    }
+}
+
+/// Berechnet die Ähnlichkeiten der beschrifteten Paare und empfiehlt einen Schwellwert.
+/// Zeilen, die mit `#` beginnen, und eine Kopfzeile ohne gültige Beschriftung werden übersprungen.
+fn calibrate_threshold(path: &str, target_far: Option<f64>, cli: &Cli) {
+    let content = fs::read_to_string(path);
+    let content = or_exit(content.map_err(|source| FacerecError::Read { path: path.to_string(), source }));
+    let mut embedder = cli.model.embedder();
+    let mut detector = cli.detector.detector();
+    let mut cache = cli.model.embedding_cache(&embedder);
+    // Das Embedding eines ganzen Bildes hängt auch davon ab, welches Gesicht die Suche darin findet
+    let config = serde_json::to_vec(&cli.detector.config()).expect("Fehler beim Serialisieren");
+    let detection = Uuid::new_v5(&Uuid::NAMESPACE_OID, &config).simple().to_string();
+    let mut cached = 0;
+    // Bilder kommen meist in mehreren Paaren vor
+    let mut embeddings: HashMap<String, Vec<f32>> = HashMap::new();
+    let mut pairs = Vec::new();
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        let same = match fields.get(2).map(|field| field.to_lowercase()) {
+            Some(label) if ["true", "1", "ja"].contains(&label.as_str()) => true,
+            Some(label) if ["false", "0", "nein"].contains(&label.as_str()) => false,
+            _ if number == 0 => continue,
+            _ => {
+                eprintln!("Fehler: Zeile {} in {path} hat nicht die Form bild_a,bild_b,true|false", number + 1);
+                std::process::exit(1);
+            }
+        };
+        let mut embedding_of = |image: &str| -> Vec<f32> {
+            embeddings
+                .entry(image.to_string())
+                .or_insert_with(|| {
+                    let key = cache.as_ref().and_then(|_| EmbeddingCache::key(image));
+                    let key = key.map(|key| format!("{key}-{detection}"));
+                    if let Some(embedding) = key.as_deref().and_then(|key| cache.as_ref()?.get(key)) {
+                        cached += 1;
+                        return embedding.clone();
+                    }
+                    let features = or_exit(embedder.extract(&or_exit(largest_face_in_image(image, &mut detector))));
+                    if let (Some(cache), Some(key)) = (cache.as_mut(), key) {
+                        cache.insert(key, features.clone());
+                    }
+                    features
+                })
+                .clone()
+        };
+        let first = embedding_of(fields[0]);
+        let second = embedding_of(fields[1]);
+        pairs.push((cosine_similarity(&first, &second), same));
+    }
+    if let Some(cache) = &cache {
+        cache.save();
+        println!("{cached} Embeddings aus dem Zwischenspeicher übernommen.");
+    }
+
+    let same_count = pairs.iter().filter(|(_, same)| *same).count();
+    println!("{} Paare ({same_count} gleiche, {} verschiedene Personen).", pairs.len(), pairs.len() - same_count);
+    let Some(result) = calibration::calibrate(&pairs, target_far) else {
+        eprintln!("Fehler: kein Schwellwert gefunden, der die Vorgaben erfüllt");
+        std::process::exit(1);
+    };
+    println!("Empfohlener Schwellwert: {:.4} (aktuell {MATCH_THRESHOLD})", result.threshold);
+    println!("  Präzision:              {:.3}", result.precision);
+    println!("  Trefferquote:           {:.3}", result.recall);
+    println!("  F1:                     {:.3}", result.f1);
+    println!("  Falschakzeptanzrate:    {:.4}", result.false_accept_rate);
+    println!("  Falschrückweisungsrate: {:.4}", result.false_reject_rate);
+}
+
+/// Globale Optionen, mit denen ein Kindprozess dieselbe Datenbank, dasselbe Modell und dieselbe Gesichtssuche nutzt
+fn global_args(cli: &Cli) -> Vec<String> {
+    let mut args = vec![
+        format!("--database={}", cli.database),
+        format!("--backups={}", cli.backups),
+        format!("--model={}", cli.model.model),
+    ];
+    if cli.model.allow_dummy_features {
+        args.push("--allow-dummy-features".to_string());
+    }
+    if let Some(preset) = cli.model.model_preset.and_then(|preset| preset.to_possible_value()) {
+        args.push(format!("--model-preset={}", preset.get_name()));
+    }
+    let paths = [
+        ("--model-config", &cli.model.model_config),
+        ("--detector-config", &cli.detector.detector_config),
+        ("--confirm-cascade", &cli.detector.confirm_cascade),
+    ];
+    args.extend(paths.into_iter().filter_map(|(name, path)| path.as_ref().map(|path| format!("{name}={path}"))));
+    args
+}
+
+/// Startet je Video einen eigenen Prozess, damit ein Fehler in einer Datei die übrigen nicht abbricht
+fn run_batch(manifest: &str, summary_path: &str, cli: &Cli) {
+    let manifest = batch::Manifest::load(manifest).unwrap_or_else(|e| {
+        eprintln!("Fehler: {e}");
+        std::process::exit(1);
+    });
+    let executable = or_exit(std::env::current_exe().map_err(|source| FacerecError::Read {
+        path: "Programmpfad".to_string(),
+        source,
+    }));
+    let mut files = Vec::new();
+    for (index, video) in manifest.videos.iter().enumerate() {
+        let results = video.results_path();
+        println!("[{}/{}] {} …", index + 1, manifest.videos.len(), video.path);
+        let status = std::process::Command::new(&executable)
+            .args(global_args(cli))
+            .args(["--strict", "--video", &video.path, "--results", &results])
+            .args(&manifest.args)
+            .args(&video.args)
+            .status();
+        let error = match status {
+            Ok(status) if status.success() => None,
+            Ok(status) => Some(format!("beendet mit {status}")),
+            Err(e) => Some(format!("konnte nicht gestartet werden: {e}")),
+        };
+        if let Some(error) = &error {
+            eprintln!("Warnung: {} {error}; weiter mit der nächsten Datei", video.path);
+        }
+        files.push(batch::FileSummary::read(&video.path, &results, error));
+    }
+
+    let summary = batch::BatchSummary::new(files);
+    for file in &summary.files {
+        println!(
+            "{}: {} Frames, {} Gesichter ({} erlaubt, {} verweigert), {} erkannte Personen{}",
+            file.path,
+            file.frames,
+            file.faces,
+            file.allowed,
+            file.denied,
+            file.matches.len(),
+            if file.error.is_some() { " – FEHLER" } else { "" }
+        );
+    }
+    println!("Erkannte Personen über alle Dateien:");
+    for (id, sightings) in &summary.matches {
+        let files: Vec<String> = sightings
+            .iter()
+            .map(|file| {
+                let (first, last) = (file.sighting.first_ms / 1000.0, file.sighting.last_ms / 1000.0);
+                format!("{} ({first:.1}–{last:.1} s)", file.path)
+            })
+            .collect();
+        println!("  {id}: {}", files.join(", "));
+    }
+    let json = serde_json::to_string_pretty(&summary).expect("Fehler beim Serialisieren");
+    let written = fs::write(summary_path, json);
+    or_exit(written.map_err(|source| FacerecError::Write { path: summary_path.to_string(), source }));
+    println!("Zusammenfassung in {summary_path} geschrieben.");
+    if summary.files.iter().any(|file| file.error.is_some()) {
+        std::process::exit(1);
+    }
+}
+
+/// Lässt die Gesichtssuche über alle beschrifteten Bilder laufen und vergleicht mit den erwarteten Rahmen
+fn evaluate_detector(dir: &str, annotations: &str, min_iou: f64, cli: &Cli) {
+    let content = fs::read_to_string(annotations);
+    let content = or_exit(content.map_err(|source| FacerecError::Read { path: annotations.to_string(), source }));
+    let expected: HashMap<String, Vec<[i32; 4]>> = serde_json::from_str(&content).unwrap_or_else(|e| {
+        eprintln!("Fehler: {annotations} ist ungültig ({e}); erwartet wird {{\"bild.jpg\": [[x, y, breite, höhe], ...]}}");
+        std::process::exit(1);
+    });
+    let mut images: Vec<_> = expected.into_iter().collect();
+    images.sort();
+    let mut detector = cli.detector.detector();
+    let mut score = DetectionScore::default();
+    let started = Instant::now();
+    for (name, boxes) in &images {
+        let path = Path::new(dir).join(name);
+        let image = imgcodecs::imread(&path.to_string_lossy(), imgcodecs::IMREAD_COLOR).unwrap_or_default();
+        if image.empty() {
+            eprintln!("Fehler: {} konnte nicht gelesen werden", path.display());
+            std::process::exit(1);
+        }
+        let detected: Vec<Rect> = or_exit(detector.detect(&to_gray(&image))).to_vec();
+        let truth: Vec<Rect> = boxes.iter().map(|&[x, y, width, height]| Rect::new(x, y, width, height)).collect();
+        let before = (score.false_positives, score.false_negatives);
+        score.add(&detected, &truth, min_iou);
+        let (false_positives, false_negatives) = (score.false_positives - before.0, score.false_negatives - before.1);
+        if false_positives + false_negatives > 0 {
+            println!("{name}: {false_negatives} verfehlt, {false_positives} Fehlalarme");
+        }
+    }
+    let truth_count = score.true_positives + score.false_negatives;
+    println!("{} Bilder mit {truth_count} Gesichtern (IoU ≥ {min_iou}):", images.len());
+    println!("  Gefunden:        {}", score.true_positives);
+    println!("  Fehlalarme:      {}", score.false_positives);
+    println!("  Verfehlt:        {}", score.false_negatives);
+    println!("  Präzision:       {:.3}", score.precision());
+    println!("  Trefferquote:    {:.3}", score.recall());
+    println!("  F1:              {:.3}", score.f1());
+    println!("  Zeit je Bild:    {:.1} ms", started.elapsed().as_secs_f64() * 1000.0 / images.len().max(1) as f64);
+}
+
+/// Erfasst das größte Gesicht eines Bildes ohne Kamera und Rückfrage. Eine explizite ID (mit dem Flag, ob ein
+/// bestehender Eintrag ersetzt werden darf) darf nur mit `--replace` auf eine vorhandene Person treffen.
+fn enroll_from_image(
+    path: &str,
+    access: AccessLevel,
+    id: Option<(String, bool)>,
+    deterministic: bool,
+    notes: Option<String>,
+    cli: &Cli,
+) {
+    let store = FaceStore::load();
+    if let Some((id, false)) = &id
+        && store.contains(id)
+    {
+        eprintln!("Fehler: Eintrag {id} existiert bereits; mit --replace ersetzen");
+        std::process::exit(1);
+    }
+    let mut embedder = cli.model.unaligned_embedder();
+    store.ensure_dimension(or_exit(embedder.dimension()));
+    store.ensure_model(&cli.model);
+    let face_region = or_exit(largest_face_in_image(path, &mut cli.detector.detector()));
+    let features = or_exit(embedder.extract(&face_region));
+    let id = id.map(|(id, _)| id).unwrap_or_else(|| {
+        if deterministic {
+            deterministic_id(&features)
+        } else {
+            Uuid::new_v4().to_string()
+        }
+    });
+    let mut entry = FaceEntry::with_id(id, features, access);
+    entry.crop = save_face_crop(&entry.id, &face_region);
+    entry.notes = notes;
+    println!(
+        "Gesicht {} erfasst (Zugang {}).",
+        entry.id,
+        match access {
+            AccessLevel::Allowed => "erlaubt",
+            AccessLevel::Denied => "verweigert",
+            AccessLevel::Probation => "auf Probe",
+        }
+    );
+    store.add(entry);
+}
+
+/// Geführte Erfassung vor der Kamera. Je Anweisung wird eine Aufnahme gespeichert, sobald das einzige
+/// Gesicht im Bild groß und scharf genug ist und die verlangte Kopfhaltung einige Frames lang hält.
+/// `quality` ist die Mindesthöhe des Gesichts und die Mindestschärfe. ESC bricht ohne Speichern ab.
+fn enroll_guided(camera: i32, landmark_model: &str, name: Option<String>, access: AccessLevel, quality: (i32, f64), cli: &Cli) {
+    let (min_face_size, min_sharpness) = quality;
+    let mut embedder = cli.model.embedder();
+    let store = FaceStore::load();
+    store.ensure_dimension(or_exit(embedder.dimension()));
+    store.ensure_model(&cli.model);
+    let mut detector = cli.detector.detector();
+    let mut landmark_detector = or_exit(LandmarkDetector::new(landmark_model));
+    let source = Source::Camera(camera);
+    let _lock = source.lock();
+    let mut cam = source.open(&[]);
+    let window = "Geführte Erfassung";
+
+    let mut guide = GuidedEnrollment::new();
+    let mut embeddings = Vec::new();
+    let mut shots = Vec::new();
+    while !guide.is_done() {
+        let mut frame = Mat::default();
+        if !or_exit(cam.read(&mut frame)) || frame.empty() {
+            eprintln!("Fehler: die Kamera liefert keine Bilder");
+            std::process::exit(1);
+        }
+        let gray = to_gray(&frame);
+        let faces = or_exit(detector.detect(&gray));
+        let mut region = None;
+        let observation = if faces.len() != 1 {
+            Observation::NoFace
+        } else {
+            let face = or_exit(faces.get(0));
+            let color = Scalar::new(255.0, 255.0, 0.0, 0.0);
+            or_exit(imgproc::rectangle(&mut frame, face, color, 2, imgproc::LINE_8, 0));
+            let face_region = or_exit(crop(&gray, face));
+            let points = landmark_detector.detect(&gray, &faces).into_iter().next().unwrap_or_default();
+            let pose = estimate_pose(&points);
+            let observation = if face.height < min_face_size {
+                Observation::PoorQuality("naeher herankommen")
+            } else if guided::sharpness(&face_region) < min_sharpness {
+                Observation::PoorQuality("Bild unscharf, bitte stillhalten")
+            } else {
+                pose.map_or(Observation::NoFace, Observation::Pose)
+            };
+            region = Some((face_region, landmarks::eyes(&points, face)));
+            observation
+        };
+        if let Progress::Capture = guide.observe(observation)
+            && let Some((face_region, eyes)) = region
+        {
+            embeddings.push(or_exit(embedder.extract_aligned(&face_region, eyes)));
+            shots.push(face_region);
+        }
+        guide.draw(&mut frame);
+        or_exit(highgui::imshow(window, &frame));
+        if or_exit(highgui::wait_key(1)) == 27 {
+            println!("Erfassung abgebrochen.");
+            return;
+        }
+    }
+    or_exit(highgui::destroy_window(window));
 
-    // Erstelle einen Dummy-Feature-Vektor (normiere Pixelwerte)
-    resized
-        .data_bytes()
-        .unwrap()
+    let mut embeddings = embeddings.into_iter();
+    let Some(first) = embeddings.next() else {
+        eprintln!("Fehler: keine Aufnahme erfasst");
+        std::process::exit(1);
+    };
+    let mut entry = FaceEntry::new(first, access);
+    entry.embeddings.extend(embeddings);
+    entry.name = name;
+    // Der erste Schritt ist die frontale Aufnahme; sie dient als Ausschnitt des Eintrags, etwa für LBPH
+    entry.crops = shots
         .iter()
-        .map(|&x| x as f32 / 255.0)
-        .collect()
+        .enumerate()
+        .map(|(index, shot)| match index {
+            0 => save_face_crop(&entry.id, shot),
+            _ => save_face_crop(&format!("{}-{index}", entry.id), shot),
+        })
+        .collect();
+    entry.crop = entry.crops.first().cloned().flatten();
+    println!("Gesicht {} mit {} Aufnahmen erfasst.", entry.id, entry.embeddings.len());
+    store.add(entry);
+}
+
+/// Fügt das größte Gesicht eines Bildes als weitere Aufnahme zu einem bestehenden Eintrag hinzu
+fn add_embedding_from_image(path: &str, id: &str, notes: Option<String>, cli: &Cli) {
+    let mut embedder = cli.model.embedder();
+    let store = FaceStore::load();
+    store.ensure_dimension(or_exit(embedder.dimension()));
+    store.ensure_model(&cli.model);
+    let Some(mut entry) = store.get(id) else {
+        eprintln!("Fehler: kein Eintrag mit der ID {id}");
+        std::process::exit(1);
+    };
+    let face_region = or_exit(largest_face_in_image(path, &mut cli.detector.detector()));
+    let features = or_exit(embedder.extract(&face_region));
+    // Laufende Nummer, unter der noch kein Ausschnitt liegt; frühere können weiter verwendet werden
+    let name = (entry.embeddings.len()..)
+        .map(|index| format!("{id}-{index}"))
+        .find(|name| !Path::new(&format!("{CROP_DIR}/{name}.png")).exists())
+        .unwrap_or_default();
+    let crop = save_face_crop(&name, &face_region);
+    if entry.crop.is_none() {
+        entry.crop = crop.clone();
+    }
+    entry.add_embedding(features, crop);
+    if notes.is_some() {
+        entry.notes = notes;
+    }
+    println!("Aufnahme zu {id} hinzugefügt ({} insgesamt).", entry.embeddings.len());
+    store.add(entry);
+}
+
+/// Schreibt das L2-normierte Embedding des größten Gesichts auf stdout; ohne Gesicht Exit-Code 1
+fn print_embedding(path: &str, format: EmbeddingFormat, cli: &Cli) {
+    let gray = to_gray(&or_exit(read_image(path)));
+    let Some(face) = or_exit(cli.detector.detector().detect(&gray)).iter().max_by_key(|face| face.area()) else {
+        eprintln!("Fehler: kein Gesicht in {path} gefunden");
+        std::process::exit(1);
+    };
+    let mut features = or_exit(cli.model.embedder().extract(&or_exit(crop(&gray, face))));
+    let norm = features.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        features.iter_mut().for_each(|v| *v /= norm);
+    }
+    match format {
+        EmbeddingFormat::Json => println!("{}", serde_json::to_string(&features).expect("Fehler beim Serialisieren")),
+        EmbeddingFormat::Base64 => {
+            let bytes: Vec<u8> = features.iter().flat_map(|v| v.to_le_bytes()).collect();
+            println!("{}", BASE64_STANDARD.encode(bytes));
+        }
+    }
+}
+
+/// Vergleicht die größten Gesichter zweier Bilder; liefert `true`, wenn sie als dieselbe Person gelten
+fn verify_images(first: &str, second: &str, cli: &Cli) -> bool {
+    let mut embedder = cli.model.embedder();
+    let mut detector = cli.detector.detector();
+    let features_first = or_exit(embedder.extract(&or_exit(largest_face_in_image(first, &mut detector))));
+    let features_second = or_exit(embedder.extract(&or_exit(largest_face_in_image(second, &mut detector))));
+    let similarity = cosine_similarity(&features_first, &features_second);
+    let same = similarity > MATCH_THRESHOLD;
+    println!(
+        "Ähnlichkeit: {similarity:.4} (Schwellwert {MATCH_THRESHOLD}) – {}",
+        if same { "dieselbe Person" } else { "verschiedene Personen" }
+    );
+    same
+}
+
+/// Umrahmt ein Gesicht, über das nicht entschieden wird, neutral mit einem Hinweis
+fn draw_neutral_face(frame: &mut Mat, face: Rect, note: &str) {
+    let color = Scalar::new(200.0, 200.0, 200.0, 0.0); // grau
+    imgproc::rectangle(frame, face, color, 1, imgproc::LINE_8, 0).unwrap();
+    draw_label(frame, note, face, true, color, 0.6);
 }
 
 fn main() {
-    recognize_face_from_camera();
+    let cli = Cli::parse();
+    BACKUPS.store(cli.backups, Ordering::Relaxed);
+    DATABASE_PATH.set(cli.database.clone()).unwrap();
+    match &cli.command {
+        Some(Command::Reindex) => reindex_faces(&cli.model),
+        Some(Command::Enroll {
+            image,
+            deny,
+            probation,
+            id,
+            replace,
+            deterministic_id,
+            notes,
+            add_to,
+        }) => {
+            let access = match (deny, probation) {
+                (true, _) => AccessLevel::Denied,
+                (_, true) => AccessLevel::Probation,
+                _ => AccessLevel::Allowed,
+            };
+            match add_to {
+                Some(add_to) => add_embedding_from_image(image, add_to, notes.clone(), &cli),
+                None => {
+                    let id = id.clone().map(|id| (id, *replace));
+                    enroll_from_image(image, access, id, *deterministic_id, notes.clone(), &cli)
+                }
+            }
+        }
+        Some(Command::EnrollGuided {
+            camera_index,
+            landmark_model,
+            name,
+            deny,
+            probation,
+            min_face_size,
+            min_sharpness,
+        }) => {
+            let access = match (deny, probation) {
+                (true, _) => AccessLevel::Denied,
+                (_, true) => AccessLevel::Probation,
+                _ => AccessLevel::Allowed,
+            };
+            let quality = (*min_face_size, *min_sharpness);
+            enroll_guided(*camera_index, landmark_model, name.clone(), access, quality, &cli)
+        }
+        Some(Command::Update {
+            id,
+            notes,
+            set_pin,
+            remove_pin,
+        }) => update_face(id, notes.as_deref(), *set_pin, *remove_pin),
+        Some(Command::Verify { first, second }) => {
+            std::process::exit(if verify_images(first, second, &cli) { 0 } else { 1 })
+        }
+        Some(Command::Embed { image, format }) => print_embedding(image, *format, &cli),
+        Some(Command::Annotate { input, output, privacy }) => annotate_image(input, output, privacy, &cli),
+        Some(Command::Calibrate { pairs, target_far }) => calibrate_threshold(pairs, *target_far, &cli),
+        Some(Command::DetectEval { dir, annotations, min_iou }) => evaluate_detector(dir, annotations, *min_iou, &cli),
+        Some(Command::Batch { manifest, summary }) => run_batch(manifest, summary, &cli),
+        Some(Command::Log {
+            path,
+            since,
+            until,
+            id,
+            camera,
+            denied_only,
+            audit_salt_file,
+        }) => {
+            let mut ids: Vec<String> = id.iter().cloned().collect();
+            if let (Some(id), Some(salt_file)) = (id, audit_salt_file) {
+                let salt = fs::read_to_string(salt_file)
+                    .ok()
+                    .and_then(|content| Uuid::parse_str(content.trim()).ok())
+                    .unwrap_or_else(|| {
+                        eprintln!("Fehler: {salt_file} enthält kein gültiges Salz");
+                        std::process::exit(1);
+                    });
+                ids.push(audit_query::hashed_id(&salt, id));
+            }
+            let filter = audit_query::Filter {
+                since: *since,
+                until: *until,
+                ids,
+                camera: camera.clone(),
+                denied_only: *denied_only,
+            };
+            match audit_query::print_matching(path, &filter) {
+                Ok(count) => eprintln!("{count} Ereignisse gefunden."),
+                Err(e) => {
+                    eprintln!("Fehler: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(Command::FitPca { dimensions }) => fit_pca(*dimensions),
+        Some(Command::ExportEval { output, quantize }) => export_eval(output, *quantize),
+        Some(Command::Cluster { dir, threshold }) => cluster_images(dir, *threshold, &cli),
+        Some(Command::Dedupe { threshold, yes }) => dedupe_faces(*threshold, *yes),
+        Some(Command::Fsck { fix }) => std::process::exit(if fsck::check_database(*fix) { 0 } else { 1 }),
+        Some(Command::CheckDb { dimension, tolerance }) => {
+            std::process::exit(if fsck::check_embeddings(*dimension, *tolerance) { 0 } else { 1 })
+        }
+        Some(Command::Convert { input, output }) => convert_database(input, output),
+        Some(Command::Clear { yes, backup }) => clear_face_data(*yes, *backup),
+        None if cli.run.strict => {
+            recognize_face_from_camera(&cli.run, &cli.model, cli.detector.config(), &StrictPolicy(DefaultPolicy))
+        }
+        None => recognize_face_from_camera(&cli.run, &cli.model, cli.detector.config(), &DefaultPolicy),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(track: u64, bbox: [i32; 4], id: Option<&str>, allowed: bool) -> FaceDecision {
+        FaceDecision {
+            track,
+            bbox,
+            id: id.map(str::to_string),
+            score: Some(0.95),
+            best_score: None,
+            allowed,
+            review: false,
+            pending: false,
+            deferred: false,
+            overridden: None,
+        }
+    }
+
+    #[test]
+    fn same_id_twice_in_a_frame_acts_once() {
+        // Person und ihre Spiegelung: zwei Rahmen, derselbe Eintrag
+        let person = decision(1, [100, 80, 120, 120], Some("a"), true);
+        let reflection = decision(2, [420, 90, 110, 110], Some("a"), true);
+        let mut actions = FrameActions::default();
+
+        assert_eq!(actions.earlier(person.id.as_deref(), true), None);
+        actions.record(&person);
+        assert_eq!(actions.earlier(reflection.id.as_deref(), true), Some(false));
+        actions.record(&reflection);
+        assert_eq!(actions.decided.len(), 1);
+
+        // Andere IDs und unbekannte Gesichter werden nicht zusammengefasst
+        assert_eq!(actions.earlier(Some("b"), true), None);
+        actions.record(&decision(3, [0, 0, 50, 50], None, false));
+        assert_eq!(actions.earlier(None, false), None);
+    }
+
+    #[test]
+    fn every_box_needs_its_own_pin() {
+        let mut actions = FrameActions::default();
+        // Die Person hat ihre PIN bestätigt
+        assert_eq!(second_factor(Decision::Allow, PinStatus::Confirmed), Some(Decision::Allow));
+        actions.record(&decision(1, [100, 80, 120, 120], Some("a"), true));
+
+        // Ein Foto derselben Person im selben Frame übernimmt die Bestätigung nicht, sondern wartet auf eine
+        // eigene PIN und wird bei falscher PIN abgewiesen
+        assert_eq!(second_factor(Decision::Allow, PinStatus::Waiting), None);
+        let photo = second_factor(Decision::Probation, PinStatus::Denied);
+        assert_eq!(photo, Some(Decision::Deny));
+        // Die Abweisung wird nicht mit dem Zugang der Person zusammengefasst und löst ihre Warnung aus
+        assert_eq!(actions.earlier(Some("a"), false), None);
+        actions.record(&decision(2, [420, 90, 110, 110], Some("a"), false));
+        assert_eq!(actions.earlier(Some("a"), false), Some(false));
+        assert_eq!(actions.earlier(Some("a"), true), Some(false));
+    }
+
+    #[test]
+    fn durations_must_be_finite_and_not_negative() {
+        assert_eq!(parse_seconds("0"), Ok(0.0));
+        assert_eq!(parse_seconds("2.5"), Ok(2.5));
+        for value in ["-1", "inf", "NaN", "zehn"] {
+            assert!(parse_seconds(value).is_err(), "{value} akzeptiert");
+        }
+    }
+
+    #[test]
+    fn legacy_model_header_matches_any_preprocessing() {
+        assert!(same_model("modell-vorverarbeitung", "modell-vorverarbeitung"));
+        assert!(same_model("modell-vorverarbeitung", "modell"));
+        assert!(same_model("modell", "modell-andere"));
+        assert!(!same_model("modell-vorverarbeitung", "modell-andere"));
+        assert!(!same_model("modell-vorverarbeitung", "anderes"));
+    }
+
+    #[test]
+    fn reload_refuses_a_changed_projection() {
+        let face = FaceEntry::with_id("a".to_string(), vec![1.0, 0.0], AccessLevel::Allowed);
+        let projection = Projection { components: vec![vec![1.0, 0.0]] };
+        let store = FaceStore {
+            dimension: Some(2),
+            index: Mutex::new(matching::build(IndexKind::BruteForce, &vec![vec![vec![1.0]]])),
+            index_kind: IndexKind::BruteForce,
+            projected: Mutex::new(vec![FaceStore::project_entry(&projection, &face)]),
+            projection: Some(projection),
+            faces: Mutex::new(vec![face.clone()]),
+            tie_break: TieBreak::default(),
+            margin: 0.0,
+            auto_save: true,
+            unsaved: AtomicUsize::new(0),
+            adapted: AtomicUsize::new(0),
+        };
+        let database = Database {
+            dimension: Some(2),
+            model: None,
+            projection: Some(Projection { components: vec![vec![0.0, 1.0]] }),
+            faces: vec![face],
+        };
+        assert!(store.reload(database).unwrap_err().contains("PCA-Projektion"));
+    }
+
+    #[test]
+    fn frame_rates_must_be_positive() {
+        assert_eq!(parse_fps("12.5"), Ok(12.5));
+        for value in ["0", "-5", "inf"] {
+            assert!(parse_fps(value).is_err(), "{value} akzeptiert");
+        }
+    }
 }
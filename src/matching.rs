@@ -0,0 +1,295 @@
+//! Suche nach dem ähnlichsten Eintrag der Galerie. Ein austauschbarer Index liefert eine Vorauswahl,
+//! die anschließend exakt bewertet wird: vollständiger Durchlauf für kleine Galerien, HNSW
+//! (approximativ, via `hnsw_rs`) für sehr große.
+
+use crate::FaceEntry;
+use clap::ValueEnum;
+use hnsw_rs::prelude::{DistCosine, Hnsw};
+
+/// Ab dieser Ähnlichkeit (ausschließlich) gilt ein Eintrag als erkannt
+pub const MATCH_THRESHOLD: f32 = 0.9;
+
+/// Einträge der Vorauswahl, die exakt bewertet werden
+pub const SHORTLIST: usize = 10;
+
+/// Berechnet die Kosinus-Ähnlichkeit zwischen zwei Feature-Vektoren
+pub fn cosine_similarity(v1: &[f32], v2: &[f32]) -> f32 {
+    let dot: f32 = v1.iter().zip(v2).map(|(a, b)| a * b).sum();
+    let mag1: f32 = v1.iter().map(|a| a * a).sum::<f32>().sqrt();
+    let mag2: f32 = v2.iter().map(|b| b * b).sum::<f32>().sqrt();
+    dot / (mag1 * mag2)
+}
+
+/// Beste Ähnlichkeit über alle Aufnahmen eines Eintrags
+pub fn similarity(embeddings: &[Vec<f32>], features: &[f32]) -> f32 {
+    embeddings
+        .iter()
+        .map(|embedding| cosine_similarity(embedding, features))
+        .max_by(f32::total_cmp)
+        .unwrap_or(f32::NEG_INFINITY)
+}
+
+/// Bevorzugter Eintrag, wenn mehrere nahezu gleich ähnlich sind
+#[derive(Clone, Copy, ValueEnum)]
+pub enum TiePreference {
+    /// Zuletzt erkannter Eintrag
+    Recency,
+    /// Am häufigsten erkannter Eintrag
+    Frequency,
+}
+
+/// Auflösung von Gleichständen beim Abgleich
+#[derive(Clone, Copy)]
+pub struct TieBreak {
+    /// Einträge, deren Ähnlichkeit höchstens so weit unter der besten liegt, gelten als gleichauf
+    pub epsilon: f32,
+    pub prefer: TiePreference,
+}
+
+impl Default for TieBreak {
+    fn default() -> Self {
+        Self {
+            epsilon: 0.001,
+            prefer: TiePreference::Recency,
+        }
+    }
+}
+
+/// Sucht das ähnlichste bekannte Gesicht und liefert es mit seiner Ähnlichkeit (der besten über seine Aufnahmen).
+/// Liegen mehrere Einträge innerhalb von `tie.epsilon` zur besten Ähnlichkeit, entscheidet `tie.prefer`,
+/// danach die Ähnlichkeit und zuletzt die ID, damit das Ergebnis nicht von der Reihenfolge abhängt.
+pub fn find_best_match<'a>(
+    features: &[f32],
+    known_faces: impl IntoIterator<Item = &'a FaceEntry>,
+    tie: TieBreak,
+) -> Option<(&'a FaceEntry, f32)> {
+    break_ties(known_faces.into_iter().map(|face| (face, face.similarity(features))), tie)
+}
+
+/// Wie `find_best_match` für bereits bewertete Einträge, z. B. wenn die Ähnlichkeit in der PCA-Projektion
+/// berechnet wurde, Gleichstände aber nach den Metadaten des gespeicherten Eintrags aufgelöst werden
+pub fn break_ties<'a>(
+    scored: impl IntoIterator<Item = (&'a FaceEntry, f32)>,
+    tie: TieBreak,
+) -> Option<(&'a FaceEntry, f32)> {
+    let scored: Vec<(&FaceEntry, f32)> = scored.into_iter().collect();
+    let best = scored.iter().map(|(_, score)| *score).max_by(f32::total_cmp)?;
+    scored
+        .into_iter()
+        .filter(|(_, score)| best - score <= tie.epsilon)
+        .max_by(|(a, score_a), (b, score_b)| {
+            let preferred = match tie.prefer {
+                TiePreference::Recency => a.last_seen.cmp(&b.last_seen),
+                TiePreference::Frequency => a.match_count.cmp(&b.match_count),
+            };
+            preferred.then(score_a.total_cmp(score_b)).then_with(|| b.id.cmp(&a.id))
+        })
+}
+
+/// Abgleich gegen eine feste Galerie, z. B. für die Einbindung in andere Programme: Vorauswahl über den
+/// Index, exakte Bewertung mit Auflösung von Gleichständen. Ein Treffer zählt über `threshold`.
+pub struct Matcher {
+    faces: Vec<FaceEntry>,
+    index: Box<dyn NeighborIndex>,
+    threshold: f32,
+    tie: TieBreak,
+}
+
+impl Matcher {
+    pub fn new(faces: Vec<FaceEntry>, kind: IndexKind) -> Self {
+        let index = build(kind, &faces);
+        Self {
+            faces,
+            index,
+            threshold: MATCH_THRESHOLD,
+            tie: TieBreak::default(),
+        }
+    }
+
+    pub fn with_threshold(mut self, threshold: f32) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn with_tie_break(mut self, tie: TieBreak) -> Self {
+        self.tie = tie;
+        self
+    }
+
+    pub fn faces(&self) -> &[FaceEntry] {
+        &self.faces
+    }
+
+    /// Ähnlichster Eintrag samt Ähnlichkeit, auch unter dem Schwellwert
+    pub fn best(&self, features: &[f32]) -> Option<(&FaceEntry, f32)> {
+        let shortlist = self.index.search(&self.faces, features, SHORTLIST);
+        find_best_match(features, shortlist.iter().map(|&entry| &self.faces[entry]), self.tie)
+    }
+
+    /// Erkannter Eintrag, None unter dem Schwellwert
+    pub fn identify(&self, features: &[f32]) -> Option<(&FaceEntry, f32)> {
+        self.best(features).filter(|&(_, score)| score > self.threshold)
+    }
+}
+
+/// Bester Treffer einer Suche samt Ähnlichkeit des nächstbesten Eintrags
+pub struct Candidate {
+    pub face: FaceEntry,
+    pub score: f32,
+    pub runner_up: Option<f32>,
+}
+
+/// Verfahren für die Vorauswahl
+#[derive(Clone, Copy, ValueEnum)]
+pub enum IndexKind {
+    /// Alle Einträge vergleichen (exakt)
+    BruteForce,
+    /// Hierarchischer Nachbarschaftsgraph (approximativ, für sehr große Galerien)
+    Hnsw,
+}
+
+/// Aufnahmen je Position, über die ein Index sucht: die gespeicherten Einträge selbst oder nur ihre
+/// projizierten Embeddings (--pca), deren Metadaten weiter aus den Einträgen kommen
+pub trait Gallery {
+    fn entries(&self) -> usize;
+
+    fn embeddings(&self, entry: usize) -> &[Vec<f32>];
+}
+
+impl Gallery for Vec<FaceEntry> {
+    fn entries(&self) -> usize {
+        self.len()
+    }
+
+    fn embeddings(&self, entry: usize) -> &[Vec<f32>] {
+        &self[entry].embeddings
+    }
+}
+
+impl Gallery for Vec<Vec<Vec<f32>>> {
+    fn entries(&self) -> usize {
+        self.len()
+    }
+
+    fn embeddings(&self, entry: usize) -> &[Vec<f32>] {
+        &self[entry]
+    }
+}
+
+/// Index über die Embeddings der Galerie. Einträge werden über ihre Position in der Galerie angesprochen.
+pub trait NeighborIndex: Send + Sync {
+    /// Nimmt die Aufnahmen eines neuen Eintrags an Position `entry` auf. Punkte lassen sich nicht entfernen:
+    /// wird ein Eintrag ersetzt oder entfernt, verschieben sich Positionen, und der Index ist neu aufzubauen.
+    fn insert(&self, entry: usize, embeddings: &[Vec<f32>]);
+
+    /// Positionen von bis zu `k` verschiedenen Einträgen, die `query` am ähnlichsten sind, beste zuerst
+    fn search(&self, gallery: &dyn Gallery, query: &[f32], k: usize) -> Vec<usize>;
+}
+
+/// Baut den Index des gewählten Verfahrens über die ganze Galerie
+pub fn build(kind: IndexKind, gallery: &dyn Gallery) -> Box<dyn NeighborIndex> {
+    match kind {
+        IndexKind::BruteForce => Box::new(BruteForce),
+        IndexKind::Hnsw => Box::new(HnswIndex::build(gallery)),
+    }
+}
+
+/// Vergleicht bei jeder Suche alle Einträge; braucht keinen eigenen Speicher
+pub struct BruteForce;
+
+impl NeighborIndex for BruteForce {
+    fn insert(&self, _entry: usize, _embeddings: &[Vec<f32>]) {}
+
+    fn search(&self, gallery: &dyn Gallery, query: &[f32], k: usize) -> Vec<usize> {
+        let mut scored: Vec<(usize, f32)> = (0..gallery.entries())
+            .map(|entry| (entry, similarity(gallery.embeddings(entry), query)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(entry, _)| entry).collect()
+    }
+}
+
+/// Nachbarn je Knoten und Ebene
+const MAX_CONNECTIONS: usize = 16;
+const MAX_LAYERS: usize = 16;
+/// Breite der Kandidatenliste beim Aufbau bzw. bei der Suche; größer = genauer, aber langsamer
+const EF_CONSTRUCTION: usize = 200;
+const EF_SEARCH: usize = 64;
+
+/// Approximative Suche über einen HNSW-Graphen. Der Graph kopiert die Embeddings beim Einfügen; spätere
+/// Änderungen durch `--adaptive` sieht er nicht, die exakte Bewertung der Vorauswahl aber schon.
+pub struct HnswIndex {
+    graph: Hnsw<'static, f32, DistCosine>,
+}
+
+impl HnswIndex {
+    pub fn build(gallery: &dyn Gallery) -> Self {
+        let points: usize = (0..gallery.entries()).map(|entry| gallery.embeddings(entry).len()).sum();
+        let graph = Hnsw::new(MAX_CONNECTIONS, points.max(1), MAX_LAYERS, EF_CONSTRUCTION, DistCosine);
+        let data: Vec<(&[f32], usize)> = (0..gallery.entries())
+            .flat_map(|entry| gallery.embeddings(entry).iter().map(move |embedding| (embedding.as_slice(), entry)))
+            .collect();
+        graph.parallel_insert_slice(&data);
+        Self { graph }
+    }
+}
+
+impl NeighborIndex for HnswIndex {
+    fn insert(&self, entry: usize, embeddings: &[Vec<f32>]) {
+        for embedding in embeddings {
+            self.graph.insert_slice((embedding, entry));
+        }
+    }
+
+    fn search(&self, _gallery: &dyn Gallery, query: &[f32], k: usize) -> Vec<usize> {
+        if self.graph.get_nb_point() == 0 {
+            return Vec::new();
+        }
+        // Ein Eintrag kann mit mehreren Aufnahmen im Graphen stehen, daher mehr Nachbarn holen als verlangt
+        let wanted = EF_SEARCH.max(k);
+        let mut entries: Vec<usize> = Vec::with_capacity(k);
+        for neighbour in self.graph.search(query, wanted, wanted) {
+            if !entries.contains(&neighbour.d_id) {
+                entries.push(neighbour.d_id);
+            }
+            if entries.len() == k {
+                break;
+            }
+        }
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AccessLevel;
+    use crate::test_rng::Rng;
+
+    #[test]
+    fn hnsw_recall_against_brute_force() {
+        const DIMENSION: usize = 64;
+        const K: usize = 10;
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let faces: Vec<FaceEntry> = (0..2000)
+            .map(|_| FaceEntry::new(rng.unit_vector(DIMENSION), AccessLevel::Allowed))
+            .collect();
+        let hnsw = build(IndexKind::Hnsw, &faces);
+        let exact = build(IndexKind::BruteForce, &faces);
+
+        let (mut found, mut top_hits) = (0, 0);
+        let queries = 100;
+        for query in 0..queries {
+            // Anfragen liegen nahe an einem Eintrag, wie eine erneute Aufnahme derselben Person
+            let noise = rng.unit_vector(DIMENSION);
+            let probe: Vec<f32> = faces[query * 17].embeddings[0].iter().zip(&noise).map(|(v, n)| v + 0.3 * n).collect();
+            let truth = exact.search(&faces, &probe, K);
+            let approximate = hnsw.search(&faces, &probe, K);
+            found += truth.iter().filter(|entry| approximate.contains(entry)).count();
+            top_hits += usize::from(approximate.first() == truth.first());
+        }
+        let recall = found as f64 / (queries * K) as f64;
+        assert!(recall >= 0.9, "Recall@{K} zu niedrig: {recall:.3}");
+        assert!(top_hits >= queries * 98 / 100, "bester Treffer nur {top_hits} von {queries} mal gefunden");
+    }
+}
@@ -0,0 +1,118 @@
+//! Prometheus-Metriken der Erkennungsschleife und ein minimaler HTTP-Endpunkt unter /metrics
+
+use prometheus::{Encoder, Histogram, HistogramOpts, IntCounter, IntGauge, Registry, TextEncoder};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::LazyLock;
+use std::thread;
+use std::time::Duration;
+
+/// Zähler, die an den jeweiligen Stellen der Erkennungsschleife erhöht werden
+pub struct Metrics {
+    registry: Registry,
+    pub frames: IntCounter,
+    /// Frames, die ausgelassen wurden, um mit der Quelle Schritt zu halten
+    pub frames_skipped: IntCounter,
+    pub faces: IntCounter,
+    pub allows: IntCounter,
+    pub denies: IntCounter,
+    pub unknowns: IntCounter,
+    pub reviews: IntCounter,
+    pub match_latency: Histogram,
+    pub gallery_size: IntGauge,
+}
+
+pub static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let counter = |name: &str, help: &str| IntCounter::new(name, help).unwrap();
+        let metrics = Self {
+            registry: Registry::new(),
+            frames: counter("facerec_frames_processed_total", "Verarbeitete Frames"),
+            frames_skipped: counter(
+                "facerec_frames_skipped_total",
+                "Wegen Rückstand nicht verarbeitete Frames",
+            ),
+            faces: counter("facerec_faces_detected_total", "Erkannte Gesichter"),
+            allows: counter("facerec_allows_total", "Erlaubte Zugänge"),
+            denies: counter("facerec_denies_total", "Verweigerte Zugänge"),
+            unknowns: counter("facerec_unknowns_total", "Unbekannte Gesichter"),
+            reviews: counter("facerec_reviews_total", "Zugänge auf Probe, zur Prüfung vorgemerkt"),
+            match_latency: Histogram::with_opts(HistogramOpts::new(
+                "facerec_match_latency_seconds",
+                "Dauer der Suche in der Gesichtsdatenbank",
+            ))
+                .unwrap(),
+            gallery_size: IntGauge::new("facerec_gallery_size", "Anzahl gespeicherter Gesichter")
+                .unwrap(),
+        };
+        for counter in [
+            &metrics.frames,
+            &metrics.frames_skipped,
+            &metrics.faces,
+            &metrics.allows,
+            &metrics.denies,
+            &metrics.unknowns,
+            &metrics.reviews,
+        ] {
+            metrics.registry.register(Box::new(counter.clone())).unwrap();
+        }
+        metrics
+            .registry
+            .register(Box::new(metrics.match_latency.clone()))
+            .unwrap();
+        metrics
+            .registry
+            .register(Box::new(metrics.gallery_size.clone()))
+            .unwrap();
+        metrics
+    }
+}
+
+/// Längste Wartezeit auf die Anfrage bzw. beim Senden; Anfragen werden nacheinander bedient, ein stummer
+/// Client hält spätere Abfragen also höchstens so lange auf
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Startet einen Hintergrund-Thread, der die Metriken unter http://<addr>/metrics ausliefert.
+/// Scheitert das Binden (z. B. Port belegt), wird der Fehler zurückgegeben.
+pub fn serve(addr: &str) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    crate::say!("Metriken unter http://{addr}/metrics");
+    // Der Endpunkt liefert nur Zähler, aber unverschlüsselt und ohne Anmeldung
+    if listener.local_addr().is_ok_and(|local| !local.ip().is_loopback()) {
+        eprintln!("Warnung: Metrik-Endpunkt {addr} ist ohne TLS und Anmeldung aus dem Netz erreichbar");
+    }
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            if let Err(e) = handle_request(stream) {
+                eprintln!("Warnung: Fehler beim Ausliefern der Metriken: {e}");
+            }
+        }
+    });
+    Ok(())
+}
+
+fn handle_request(mut stream: TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+    let path = request_line.split_whitespace().nth(1).unwrap_or("");
+    if path != "/metrics" {
+        return stream.write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n");
+    }
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    encoder
+        .encode(&METRICS.registry.gather(), &mut body)
+        .map_err(io::Error::other)?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n",
+        encoder.format_type(),
+        body.len()
+    )?;
+    stream.write_all(&body)
+}
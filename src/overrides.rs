@@ -0,0 +1,102 @@
+//! Sperr- und Freigabelisten: IDs, deren Zugang unabhängig vom gespeicherten Zugangsrecht verweigert
+//! bzw. erlaubt wird, z. B. um einen Zugang sofort zu entziehen, ohne die Datenbank zu ändern.
+//! Die Dateien enthalten eine ID je Zeile (`#` leitet Kommentare ein) und werden bei Änderung neu gelesen.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Höchstens so oft wird das Änderungsdatum der Listen geprüft
+const RELOAD_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Liste, die eine Entscheidung überstimmt hat
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Override {
+    Denylist,
+    Allowlist,
+}
+
+struct ListFile {
+    path: String,
+    ids: HashSet<String>,
+    modified: Option<SystemTime>,
+}
+
+impl ListFile {
+    fn open(path: &str) -> Result<Self, String> {
+        let mut list = Self {
+            path: path.to_string(),
+            ids: HashSet::new(),
+            modified: None,
+        };
+        list.read()?;
+        Ok(list)
+    }
+
+    fn read(&mut self) -> Result<(), String> {
+        let content = fs::read_to_string(&self.path).map_err(|e| format!("{} konnte nicht gelesen werden: {e}", self.path))?;
+        self.ids = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or("").trim())
+            .filter(|id| !id.is_empty())
+            .map(str::to_string)
+            .collect();
+        self.modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        Ok(())
+    }
+
+    /// Liest die Datei neu, wenn sich ihr Änderungsdatum geändert hat; bei Fehlern bleibt der alte Stand
+    fn refresh(&mut self) {
+        let modified = fs::metadata(&self.path).and_then(|metadata| metadata.modified()).ok();
+        if modified.is_none() || modified == self.modified {
+            return;
+        }
+        match self.read() {
+            Ok(()) => crate::say!("{} neu geladen ({} IDs).", self.path, self.ids.len()),
+            Err(e) => eprintln!("Warnung: {e}; die bisherige Liste bleibt gültig"),
+        }
+    }
+}
+
+struct Lists {
+    deny: Option<ListFile>,
+    allow: Option<ListFile>,
+    checked: Instant,
+}
+
+/// Sperr- und Freigabeliste; wird von allen Kamera-Threads geteilt
+pub struct OverrideLists {
+    lists: Mutex<Lists>,
+}
+
+impl OverrideLists {
+    pub fn open(deny: Option<&str>, allow: Option<&str>) -> Result<Self, String> {
+        Ok(Self {
+            lists: Mutex::new(Lists {
+                deny: deny.map(ListFile::open).transpose()?,
+                allow: allow.map(ListFile::open).transpose()?,
+                checked: Instant::now(),
+            }),
+        })
+    }
+
+    /// Liste, in der die ID steht; die Sperrliste hat Vorrang
+    pub fn check(&self, id: &str) -> Option<Override> {
+        let mut guard = self.lists.lock().unwrap();
+        let lists = &mut *guard;
+        if lists.checked.elapsed() >= RELOAD_CHECK_INTERVAL {
+            lists.checked = Instant::now();
+            lists.deny.iter_mut().chain(lists.allow.iter_mut()).for_each(ListFile::refresh);
+        }
+        if lists.deny.as_ref().is_some_and(|list| list.ids.contains(id)) {
+            Some(Override::Denylist)
+        } else if lists.allow.as_ref().is_some_and(|list| list.ids.contains(id)) {
+            Some(Override::Allowlist)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Verkleinerung der Embeddings per PCA, um den Abgleich sehr großer Galerien zu beschleunigen.
+//! `fit-pca` bestimmt die Hauptachsen aus der Galerie und legt sie im Kopf der Datenbank ab;
+//! mit `--pca` werden Galerie und Anfrage vor dem Abgleich darauf projiziert. Gespeichert bleiben
+//! die vollständigen Embeddings, damit die Projektion jederzeit neu bestimmt werden kann.
+//!
+//! Die Daten werden nicht zentriert: die Projektion auf die Hauptachsen der zweiten Momente erhält
+//! Skalarprodukte und damit Kosinus-Ähnlichkeiten näherungsweise, der Schwellwert bleibt gültig.
+
+use serde::{Deserialize, Serialize};
+
+/// Höchstzahl der Iterationen je Achse
+const MAX_ITERATIONS: usize = 200;
+/// Die Iteration endet, sobald sich die Achse kaum noch ändert
+const TOLERANCE: f32 = 1e-6;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Projection {
+    /// Hauptachsen (je eine Zeile, Länge 1), nach absteigender Varianz
+    pub components: Vec<Vec<f32>>,
+}
+
+impl Projection {
+    /// Bestimmt die `dimensions` stärksten Achsen per Potenzmethode. Liefert die Projektion und den Anteil
+    /// der erklärten Varianz.
+    pub fn fit(samples: &[&[f32]], dimensions: usize) -> Option<(Self, f32)> {
+        let dimension = samples.first()?.len();
+        // Matrix der zweiten Momente (dimension × dimension)
+        let mut moments = vec![0.0f32; dimension * dimension];
+        for sample in samples {
+            for (i, a) in sample.iter().enumerate() {
+                let row = &mut moments[i * dimension..(i + 1) * dimension];
+                for (value, b) in row.iter_mut().zip(sample.iter()) {
+                    *value += a * b;
+                }
+            }
+        }
+        moments.iter_mut().for_each(|value| *value /= samples.len() as f32);
+        let total: f32 = (0..dimension).map(|i| moments[i * dimension + i]).sum();
+
+        let mut components: Vec<Vec<f32>> = Vec::new();
+        let mut explained = 0.0;
+        for axis in 0..dimensions.min(dimension) {
+            // Feste, von den bisherigen Achsen unabhängige Startrichtung, damit das Ergebnis reproduzierbar ist
+            let mut vector: Vec<f32> = (0..dimension)
+                .map(|j| ((j * 7919 + axis * 104_729) % 1000) as f32 / 1000.0 - 0.5)
+                .collect();
+            orthonormalize(&mut vector, &components);
+            for _ in 0..MAX_ITERATIONS {
+                let mut next = multiply(&moments, &vector);
+                orthonormalize(&mut next, &components);
+                let change: f32 = next.iter().zip(&vector).map(|(a, b)| (a - b).abs()).sum();
+                vector = next;
+                if change < TOLERANCE {
+                    break;
+                }
+            }
+            let variance: f32 = multiply(&moments, &vector).iter().zip(&vector).map(|(a, b)| a * b).sum();
+            explained += variance;
+            components.push(vector);
+        }
+        let ratio = if total > 0.0 { explained / total } else { 0.0 };
+        Some((Self { components }, ratio))
+    }
+
+    /// Dimension der Embeddings, für die die Projektion bestimmt wurde
+    pub fn input_dimension(&self) -> Option<usize> {
+        self.components.first().map(Vec::len)
+    }
+
+    pub fn project(&self, embedding: &[f32]) -> Vec<f32> {
+        self.components
+            .iter()
+            .map(|component| component.iter().zip(embedding).map(|(a, b)| a * b).sum())
+            .collect()
+    }
+}
+
+fn multiply(matrix: &[f32], vector: &[f32]) -> Vec<f32> {
+    matrix
+        .chunks(vector.len())
+        .map(|row| row.iter().zip(vector).map(|(a, b)| a * b).sum())
+        .collect()
+}
+
+/// Entfernt die Anteile der bisherigen Achsen (Gram-Schmidt) und normiert auf Länge 1
+fn orthonormalize(vector: &mut [f32], components: &[Vec<f32>]) {
+    for component in components {
+        let dot: f32 = vector.iter().zip(component).map(|(a, b)| a * b).sum();
+        vector.iter_mut().zip(component).for_each(|(value, c)| *value -= dot * c);
+    }
+    let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matching::cosine_similarity;
+    use crate::test_rng::Rng;
+
+    fn best(gallery: &[Vec<f32>], probe: &[f32]) -> usize {
+        (0..gallery.len())
+            .max_by(|&a, &b| cosine_similarity(&gallery[a], probe).total_cmp(&cosine_similarity(&gallery[b], probe)))
+            .unwrap()
+    }
+
+    #[test]
+    fn projection_retains_recall() {
+        const DIMENSION: usize = 128;
+        const LATENT: usize = 24;
+        const REDUCED: usize = 32;
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        // Echte Embeddings liegen nahe einem niedrigdimensionalen Unterraum; hier ein zufälliger mit Rauschen
+        let basis: Vec<Vec<f32>> = (0..LATENT).map(|_| rng.vector(DIMENSION)).collect();
+        let embed = |latent: &[f32], rng: &mut Rng| -> Vec<f32> {
+            let noise = rng.vector(DIMENSION);
+            (0..DIMENSION)
+                .map(|j| latent.iter().zip(&basis).map(|(l, axis)| l * axis[j]).sum::<f32>() + 0.05 * noise[j])
+                .collect()
+        };
+        let people: Vec<Vec<f32>> = (0..300).map(|_| rng.vector(LATENT)).collect();
+        let gallery: Vec<Vec<f32>> = people.iter().map(|person| embed(person, &mut rng)).collect();
+
+        let samples: Vec<&[f32]> = gallery.iter().map(Vec::as_slice).collect();
+        let (projection, explained) = Projection::fit(&samples, REDUCED).unwrap();
+        assert_eq!(projection.components.len(), REDUCED);
+        assert!(explained > 0.95, "erklärte Varianz {explained}");
+        let reduced: Vec<Vec<f32>> = gallery.iter().map(|embedding| projection.project(embedding)).collect();
+
+        // Erneute Aufnahme derselben Person: leicht veränderte Merkmale
+        let (mut full_hits, mut reduced_hits) = (0, 0);
+        for (person, latent) in people.iter().enumerate() {
+            let varied: Vec<f32> = latent.iter().map(|value| value + 0.15 * rng.next()).collect();
+            let probe = embed(&varied, &mut rng);
+            full_hits += usize::from(best(&gallery, &probe) == person);
+            reduced_hits += usize::from(best(&reduced, &projection.project(&probe)) == person);
+        }
+        let retention = reduced_hits as f32 / full_hits as f32;
+        assert!(full_hits > 250, "Trefferquote ohne Projektion {full_hits}/300");
+        assert!(retention >= 0.98, "Trefferquote {reduced_hits} statt {full_hits} ({retention:.3})");
+    }
+}
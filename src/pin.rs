@@ -0,0 +1,195 @@
+//! Zweiter Faktor für Türen mit höherem Schutzbedarf: eine erkannte Person mit hinterlegter PIN
+//! erhält erst Zutritt, wenn sie die PIN auf der Tastatur eingibt.
+//! Gespeichert wird nur ein langsamer Hash (PBKDF2-HMAC-SHA256) mit zufälligem Salz je Eintrag, nie die PIN
+//! selbst. Format: `pbkdf2-sha256$<Iterationen>$<Salz>$<Hash>`, Salz und Hash base64-kodiert.
+
+use base64::prelude::*;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use subtle::ConstantTimeEq;
+
+const SCHEME: &str = "pbkdf2-sha256";
+
+/// Bei nur 10^4 bis 10^8 möglichen PINs muss jeder einzelne Versuch teuer sein
+const ITERATIONS: u32 = 600_000;
+
+fn derive(pin: &str, salt: &[u8], iterations: u32) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(pin.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+pub fn hash(pin: &str) -> String {
+    let salt: [u8; 16] = rand::random();
+    let key = derive(pin, &salt, ITERATIONS);
+    format!("{SCHEME}${ITERATIONS}${}${}", BASE64_STANDARD.encode(salt), BASE64_STANDARD.encode(key))
+}
+
+/// Vergleicht in konstanter Zeit; ein nicht lesbarer Hash passt zu keiner PIN
+pub fn verify(stored: &str, pin: &str) -> bool {
+    let mut parts = stored.split('$');
+    let (Some(SCHEME), Some(iterations), Some(salt), Some(key), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(iterations), Ok(salt), Ok(key)) =
+        (iterations.parse(), BASE64_STANDARD.decode(salt), BASE64_STANDARD.decode(key))
+    else {
+        return false;
+    };
+    derive(pin, &salt, iterations).ct_eq(&key[..]).into()
+}
+
+/// Eine PIN besteht aus 4 bis 8 Ziffern, damit sie auch auf einem Ziffernblock eingegeben werden kann
+pub fn is_valid(pin: &str) -> bool {
+    (4..=8).contains(&pin.len()) && pin.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Fragt auf der Konsole nach der PIN der erkannten Person
+pub fn prompt(camera: &str, name: Option<&str>) -> String {
+    let _guard = crate::enrollment::PROMPT_LOCK.lock().unwrap();
+    match name {
+        Some(name) => crate::say!("[{camera}] {name} erkannt. Bitte PIN eingeben: "),
+        None => crate::say!("[{camera}] Person erkannt. Bitte PIN eingeben: "),
+    }
+    read_pin()
+}
+
+/// Versuche je Spur, bevor eine falsche PIN den Zugang endgültig verweigert
+pub const MAX_ATTEMPTS: u32 = 3;
+
+/// Stand der PIN-Abfrage einer Spur
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PinStatus {
+    /// Die Abfrage läuft noch; bis dahin wird nicht entschieden
+    Waiting,
+    Confirmed,
+    Denied,
+}
+
+enum PinState {
+    Waiting { answer: Receiver<bool>, attempts: u32 },
+    Done(bool),
+}
+
+type Ask = Arc<dyn Fn(&str, Option<&str>) -> String + Send + Sync>;
+
+/// PIN-Abfragen je Spur; Eingabe und Prüfung laufen in einem eigenen Thread,
+/// damit die Kamera weiterläuft, solange die Person tippt
+pub struct PinChecks {
+    tracks: HashMap<u64, PinState>,
+    ask: Ask,
+}
+
+impl PinChecks {
+    pub fn new() -> Self {
+        Self::with_prompt(prompt)
+    }
+
+    fn with_prompt(ask: impl Fn(&str, Option<&str>) -> String + Send + Sync + 'static) -> Self {
+        Self { tracks: HashMap::new(), ask: Arc::new(ask) }
+    }
+
+    /// Startet bei Bedarf die Abfrage für die Spur und liefert ihren aktuellen Stand
+    pub fn check(&mut self, track: u64, stored: &str, camera: &str, name: Option<&str>) -> PinStatus {
+        let state = self
+            .tracks
+            .entry(track)
+            .or_insert_with(|| PinState::Waiting { answer: ask(&self.ask, stored, camera, name), attempts: 1 });
+        if let PinState::Waiting { answer, attempts } = state {
+            match answer.try_recv() {
+                Err(TryRecvError::Empty) => return PinStatus::Waiting,
+                Ok(true) => *state = PinState::Done(true),
+                Ok(false) if *attempts < MAX_ATTEMPTS => {
+                    let left = MAX_ATTEMPTS - *attempts;
+                    crate::say!("[{camera}] PIN falsch – noch {left} Versuch(e).");
+                    *answer = ask(&self.ask, stored, camera, name);
+                    *attempts += 1;
+                    return PinStatus::Waiting;
+                }
+                Ok(false) | Err(TryRecvError::Disconnected) => {
+                    crate::say!("[{camera}] PIN falsch – Zugang verweigert.");
+                    *state = PinState::Done(false);
+                }
+            }
+        }
+        match state {
+            PinState::Done(true) => PinStatus::Confirmed,
+            _ => PinStatus::Denied,
+        }
+    }
+
+    /// Vergisst Spuren, die nicht mehr verfolgt werden; eine noch offene Eingabe läuft ins Leere
+    pub fn retain(&mut self, keep: impl Fn(u64) -> bool) {
+        self.tracks.retain(|&track, _| keep(track));
+    }
+}
+
+fn ask(ask: &Ask, stored: &str, camera: &str, name: Option<&str>) -> Receiver<bool> {
+    let (tx, rx) = mpsc::channel();
+    let (ask, stored) = (Arc::clone(ask), stored.to_string());
+    let (camera, name) = (camera.to_string(), name.map(str::to_string));
+    thread::spawn(move || {
+        let confirmed = verify(&stored, &ask(&camera, name.as_deref()));
+        let _ = tx.send(confirmed);
+    });
+    rx
+}
+
+/// Liest die PIN ohne Echo; bei einem Lesefehler gilt die Eingabe als leer und damit als falsch
+pub fn read_pin() -> String {
+    match rpassword::read_password() {
+        Ok(response) => response.trim().to_string(),
+        Err(e) => {
+            eprintln!("Warnung: PIN konnte nicht gelesen werden: {e}");
+            String::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash_verifies_only_the_same_pin() {
+        let stored = hash("1234");
+        assert!(stored.starts_with("pbkdf2-sha256$"));
+        assert!(verify(&stored, "1234"));
+        assert!(!verify(&stored, "1235"));
+        assert_ne!(stored, hash("1234"), "jedes Hashen verwendet ein neues Salz");
+        assert!(!verify("pbkdf2-sha256$kaputt", "1234"));
+    }
+
+    fn settle(checks: &mut PinChecks, stored: &str) -> PinStatus {
+        loop {
+            match checks.check(7, stored, "test", None) {
+                PinStatus::Waiting => thread::sleep(std::time::Duration::from_millis(10)),
+                status => return status,
+            }
+        }
+    }
+
+    #[test]
+    fn a_wrong_pin_can_be_retried() {
+        let stored = hash("1234");
+        let answers = std::sync::Mutex::new(vec!["1234", "0000"]);
+        let mut checks = PinChecks::with_prompt(move |_, _| answers.lock().unwrap().pop().unwrap().to_string());
+        assert_eq!(settle(&mut checks, &stored), PinStatus::Confirmed);
+        assert_eq!(checks.check(7, &stored, "test", None), PinStatus::Confirmed, "das Ergebnis bleibt bei der Spur");
+    }
+
+    #[test]
+    fn too_many_wrong_pins_deny_the_track() {
+        let stored = hash("1234");
+        let mut checks = PinChecks::with_prompt(|_, _| "0000".to_string());
+        assert_eq!(checks.check(7, &stored, "test", None), PinStatus::Waiting);
+        assert_eq!(settle(&mut checks, &stored), PinStatus::Denied);
+        checks.retain(|_| false);
+        assert_eq!(checks.check(7, &stored, "test", None), PinStatus::Waiting, "eine neue Spur wird neu gefragt");
+    }
+}
@@ -0,0 +1,66 @@
+//! Zugangsrichtlinien: die letzte Instanz der Entscheidung über Erlauben oder Verweigern
+
+use crate::{AccessLevel, FaceEntry};
+use chrono::{DateTime, Local};
+
+/// Kontext des Frames, in dem ein Gesicht erkannt wurde
+pub struct FrameContext<'a> {
+    pub camera: &'a str,
+    pub frame_index: u64,
+    /// Aufnahmezeitpunkt des Frames
+    pub timestamp: DateTime<Local>,
+    /// Position in der Videodatei in Millisekunden; None bei Live-Kameras
+    pub media_ms: Option<f64>,
+    /// SMPTE-Timecode dieser Position mit --timecode
+    pub timecode: Option<&'a str>,
+}
+
+/// Ergebnis einer Zugangsrichtlinie
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Decision {
+    Allow,
+    /// Zugang erlaubt, das Ereignis wird aber zur Prüfung vorgemerkt
+    Probation,
+    Deny,
+    /// Unbekanntes Gesicht: beim Bediener nachfragen und die Person erfassen
+    Enroll,
+}
+
+/// Entscheidet über den Zugang. `matched` ist der Treffer oberhalb des Schwellwerts samt Ähnlichkeit.
+/// Richtlinien werden von allen Kamera-Threads gemeinsam genutzt und müssen daher `Sync` sein.
+pub trait AccessPolicy: Sync {
+    fn decide(&self, matched: Option<(&FaceEntry, f32)>, ctx: &FrameContext) -> Decision;
+}
+
+/// Standardverhalten: gespeichertes Zugangsrecht übernehmen, unbekannte Gesichter erfassen.
+/// Abgelaufene Besucherzugänge werden verweigert.
+pub struct DefaultPolicy;
+
+impl AccessPolicy for DefaultPolicy {
+    fn decide(&self, matched: Option<(&FaceEntry, f32)>, ctx: &FrameContext) -> Decision {
+        let Some((face, _)) = matched else {
+            return Decision::Enroll;
+        };
+        if face.valid_until.is_some_and(|until| ctx.timestamp >= until) {
+            return Decision::Deny;
+        }
+        match face.access {
+            AccessLevel::Allowed => Decision::Allow,
+            AccessLevel::Probation => Decision::Probation,
+            AccessLevel::Denied => Decision::Deny,
+        }
+    }
+}
+
+/// Strenger Betrieb: Zugang nur für vorab erfasste Personen. Unbekannte Gesichter werden verweigert statt
+/// erfasst; neue Personen lassen sich nur über `facerec enroll` aufnehmen.
+pub struct StrictPolicy<P>(pub P);
+
+impl<P: AccessPolicy> AccessPolicy for StrictPolicy<P> {
+    fn decide(&self, matched: Option<(&FaceEntry, f32)>, ctx: &FrameContext) -> Decision {
+        match self.0.decide(matched, ctx) {
+            Decision::Enroll => Decision::Deny,
+            decision => decision,
+        }
+    }
+}
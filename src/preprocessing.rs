@@ -0,0 +1,325 @@
+//! Vorverarbeitung der Gesichtsausschnitte vor der Merkmalsextraktion als geordnete Liste von Schritten.
+//! Die Liste steht unter `preprocessing` in der Modellkonfiguration (--model-config) und geht in die
+//! Kennung des Extraktors ein, sodass Embeddings nur bei gleicher Vorverarbeitung als gleich gelten.
+//! Dieselben Schritte normalisieren auch ganze Frames vor der Gesichtssuche (--normalize). Die Ausrichtung
+//! (`align`) braucht die Lage der Augen aus den Landmarken (--landmark-model). Beispiel:
+//!
+//! ```json
+//! "preprocessing": [
+//!     {"step": "pad", "fraction": 0.1},
+//!     {"step": "align", "width": 112, "height": 112},
+//!     {"step": "clahe", "clip_limit": 2.0},
+//!     {"step": "normalize"}
+//! ]
+//! ```
+
+use opencv::{
+    core::{self, Mat, Ptr, Scalar, Size},
+    imgproc,
+    prelude::*,
+};
+use crate::error::FacerecError;
+use serde::{Deserialize, Serialize};
+
+/// Ein Schritt der Vorverarbeitung; fehlende Parameter erhalten die Standardwerte
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "step", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Rand um den Ausschnitt durch Wiederholen der Randpixel, als Anteil von Breite bzw. Höhe je Seite
+    Pad {
+        #[serde(default = "default_pad")]
+        fraction: f64,
+    },
+    /// Histogrammausgleich
+    Equalize,
+    /// Kontrastbegrenzter adaptiver Histogrammausgleich auf `tiles`×`tiles` Kacheln
+    Clahe {
+        #[serde(default = "default_clip_limit")]
+        clip_limit: f64,
+        #[serde(default = "default_tiles")]
+        tiles: i32,
+    },
+    /// Gammakorrektur (> 1 hellt dunkle Bereiche auf)
+    Gamma { gamma: f64 },
+    /// Skalierung auf eine feste Größe
+    Resize { width: i32, height: i32 },
+    /// Dreht und skaliert den Ausschnitt auf `width`×`height`, sodass die Augen waagrecht an den Stellen
+    /// aus `EYE_TEMPLATE` liegen. Ohne bekannte Augen (kein Landmarken-Modell, Anpassung fehlgeschlagen)
+    /// wird nur skaliert.
+    Align { width: i32, height: i32 },
+    /// Streckt die Grauwerte auf den vollen Bereich 0–255
+    Normalize,
+}
+
+/// Ziel der Augenmittelpunkte bei `align` als Anteil von Breite und Höhe, wie in der verbreiteten
+/// 112×112-Vorlage von ArcFace: links (38,3; 51,7), rechts (73,5; 51,5) Pixel
+pub const EYE_TEMPLATE: Eyes = Eyes {
+    left: (0.342, 0.46),
+    right: (0.658, 0.46),
+};
+
+/// Augenmittelpunkte im Ausschnitt als Anteil von Breite und Höhe; `left` ist das im Bild linke Auge
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Eyes {
+    pub left: (f64, f64),
+    pub right: (f64, f64),
+}
+
+/// Affine Abbildung (2×3, zeilenweise) eines `from`-großen Bilds auf ein `to`-großes, die die Augen auf
+/// `EYE_TEMPLATE` legt; None, wenn beide Augen zusammenfallen
+pub fn alignment(eyes: Eyes, from: (f64, f64), to: (f64, f64)) -> Option<[[f64; 3]; 2]> {
+    let pixel = |(x, y): (f64, f64), (width, height): (f64, f64)| (x * width, y * height);
+    let (left, right) = (pixel(eyes.left, from), pixel(eyes.right, from));
+    let (target_left, target_right) = (pixel(EYE_TEMPLATE.left, to), pixel(EYE_TEMPLATE.right, to));
+    let distance = (right.0 - left.0).hypot(right.1 - left.1);
+    if distance < f64::EPSILON {
+        return None;
+    }
+    let scale = (target_right.0 - target_left.0).hypot(target_right.1 - target_left.1) / distance;
+    let direction = |from: (f64, f64), to: (f64, f64)| (to.1 - from.1).atan2(to.0 - from.0);
+    let angle = direction(left, right) - direction(target_left, target_right);
+    let (cos, sin) = (scale * angle.cos(), scale * angle.sin());
+    // Die Augenmitte geht in die Mitte der Vorlage über, gedreht wird um sie
+    let center = ((left.0 + right.0) / 2.0, (left.1 + right.1) / 2.0);
+    let target = ((target_left.0 + target_right.0) / 2.0, (target_left.1 + target_right.1) / 2.0);
+    Some([
+        [cos, sin, target.0 - cos * center.0 - sin * center.1],
+        [-sin, cos, target.1 + sin * center.0 - cos * center.1],
+    ])
+}
+
+fn default_pad() -> f64 {
+    0.1
+}
+
+fn default_clip_limit() -> f64 {
+    2.0
+}
+
+fn default_tiles() -> i32 {
+    8
+}
+
+/// Prüft die Parameter aller Schritte
+pub fn validate(steps: &[Step]) -> Result<(), String> {
+    for (position, step) in steps.iter().enumerate() {
+        let valid = match *step {
+            Step::Pad { fraction } => (0.0..=1.0).contains(&fraction),
+            Step::Clahe { clip_limit, tiles } => clip_limit > 0.0 && tiles > 0,
+            Step::Gamma { gamma } => gamma > 0.0,
+            Step::Resize { width, height } | Step::Align { width, height } => width > 0 && height > 0,
+            Step::Equalize | Step::Normalize => true,
+        };
+        if !valid {
+            return Err(format!("preprocessing: Schritt {} ({step:?}) hat ungültige Parameter", position + 1));
+        }
+    }
+    Ok(())
+}
+
+/// Für die wiederholte Anwendung vorbereitete Schritte: das CLAHE-Objekt und die Gamma-Tabelle werden
+/// einmal angelegt statt für jeden Ausschnitt
+pub struct Pipeline {
+    steps: Vec<Step>,
+    stages: Vec<Stage>,
+}
+
+enum Stage {
+    Pad(f64),
+    Equalize,
+    Clahe(Ptr<imgproc::CLAHE>),
+    Gamma(Mat),
+    Resize(Size),
+    Align(Size),
+    Normalize,
+}
+
+impl Pipeline {
+    pub fn new(steps: Vec<Step>) -> Result<Self, FacerecError> {
+        let stages = steps
+            .iter()
+            .map(|step| {
+                Ok(match *step {
+                    Step::Pad { fraction } => Stage::Pad(fraction),
+                    Step::Equalize => Stage::Equalize,
+                    Step::Clahe { clip_limit, tiles } => {
+                        Stage::Clahe(imgproc::create_clahe(clip_limit, Size::new(tiles, tiles))?)
+                    }
+                    Step::Gamma { gamma } => {
+                        let table: Vec<u8> = (0..=255)
+                            .map(|i| ((i as f64 / 255.0).powf(1.0 / gamma) * 255.0).round() as u8)
+                            .collect();
+                        Stage::Gamma(Mat::from_slice(&table)?.try_clone()?)
+                    }
+                    Step::Resize { width, height } => Stage::Resize(Size::new(width, height)),
+                    Step::Align { width, height } => Stage::Align(Size::new(width, height)),
+                    Step::Normalize => Stage::Normalize,
+                })
+            })
+            .collect::<Result<_, FacerecError>>()?;
+        Ok(Self { steps, stages })
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Ob ein Schritt die Lage der Augen braucht (`align`)
+    pub fn aligns(&self) -> bool {
+        self.stages.iter().any(|stage| matches!(stage, Stage::Align(_)))
+    }
+
+    /// Wendet die Schritte der Reihe nach auf einen Ausschnitt an
+    pub fn apply(&mut self, face: &Mat) -> Result<Mat, FacerecError> {
+        self.apply_with(face, None)
+    }
+
+    /// Wie `apply`, mit der Lage der Augen im Ausschnitt für `align`
+    pub fn apply_with(&mut self, face: &Mat, eyes: Option<Eyes>) -> Result<Mat, FacerecError> {
+        let mut current = face.try_clone()?;
+        let mut eyes = eyes;
+        for stage in &mut self.stages {
+            let mut next = Mat::default();
+            match stage {
+                Stage::Pad(fraction) => {
+                    let vertical = (current.rows() as f64 * *fraction).round() as i32;
+                    let horizontal = (current.cols() as f64 * *fraction).round() as i32;
+                    // Der Rand verschiebt die Augen relativ zum größeren Bild
+                    let (width, height) = (current.cols() as f64, current.rows() as f64);
+                    let shift = |(x, y): (f64, f64)| {
+                        let x = (x * width + horizontal as f64) / (width + 2.0 * horizontal as f64);
+                        (x, (y * height + vertical as f64) / (height + 2.0 * vertical as f64))
+                    };
+                    eyes = eyes.map(|eyes| Eyes { left: shift(eyes.left), right: shift(eyes.right) });
+                    core::copy_make_border(
+                        &current,
+                        &mut next,
+                        vertical,
+                        vertical,
+                        horizontal,
+                        horizontal,
+                        core::BORDER_REPLICATE,
+                        Scalar::default(),
+                    )?;
+                }
+                Stage::Equalize => imgproc::equalize_hist(&gray(&current)?, &mut next)?,
+                Stage::Clahe(clahe) => clahe.apply(&gray(&current)?, &mut next)?,
+                Stage::Gamma(table) => core::lut(&current, table, &mut next)?,
+                Stage::Resize(size) => imgproc::resize(&current, &mut next, *size, 0.0, 0.0, imgproc::INTER_LINEAR)?,
+                Stage::Align(size) => {
+                    let from = (current.cols() as f64, current.rows() as f64);
+                    let to = (size.width as f64, size.height as f64);
+                    match eyes.and_then(|eyes| alignment(eyes, from, to)) {
+                        Some(matrix) => imgproc::warp_affine(
+                            &current,
+                            &mut next,
+                            &Mat::from_slice_2d(&matrix)?,
+                            *size,
+                            imgproc::INTER_LINEAR,
+                            core::BORDER_REPLICATE,
+                            Scalar::default(),
+                        )?,
+                        None => imgproc::resize(&current, &mut next, *size, 0.0, 0.0, imgproc::INTER_LINEAR)?,
+                    }
+                    eyes = eyes.map(|_| EYE_TEMPLATE);
+                }
+                Stage::Normalize => core::normalize(
+                    &current,
+                    &mut next,
+                    0.0,
+                    255.0,
+                    core::NORM_MINMAX,
+                    core::CV_8U,
+                    &core::no_array(),
+                )?,
+            }
+            current = next;
+        }
+        Ok(current)
+    }
+}
+
+/// Histogrammausgleich und CLAHE arbeiten nur auf einem Kanal
+fn gray(face: &Mat) -> opencv::Result<Mat> {
+    if face.channels() == 1 {
+        return face.try_clone();
+    }
+    let mut gray = Mat::default();
+    imgproc::cvt_color(face, &mut gray, imgproc::COLOR_BGR2GRAY, 0, unsafe { std::mem::zeroed() })?;
+    Ok(gray)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: &str) -> serde_json::Result<Vec<Step>> {
+        serde_json::from_str(json)
+    }
+
+    #[test]
+    fn parses_steps_with_defaults() {
+        let steps = parse(r#"[{"step": "pad"}, {"step": "clahe", "clip_limit": 3.0}, {"step": "normalize"}]"#).unwrap();
+        assert!(matches!(steps[0], Step::Pad { fraction } if fraction == 0.1));
+        assert!(matches!(steps[1], Step::Clahe { clip_limit, tiles: 8 } if clip_limit == 3.0));
+        assert!(matches!(steps[2], Step::Normalize));
+    }
+
+    #[test]
+    fn rejects_unknown_steps_and_fields() {
+        assert!(parse(r#"[{"step": "align"}]"#).is_err(), "align braucht die Zielgröße");
+        assert!(parse(r#"[{"step": "rotate"}]"#).is_err());
+        assert!(parse(r#"[{"step": "pad", "fraktion": 0.2}]"#).is_err());
+        assert!(parse(r#"[{"step": "resize", "width": 112}]"#).is_err());
+        assert!(parse(r#"[{"fraction": 0.2}]"#).is_err());
+    }
+
+    #[test]
+    fn validate_rejects_bad_parameters() {
+        assert!(validate(&[Step::Pad { fraction: 0.2 }, Step::Gamma { gamma: 1.5 }]).is_ok());
+        let invalid = [
+            Step::Pad { fraction: -0.1 },
+            Step::Pad { fraction: 1.5 },
+            Step::Clahe { clip_limit: 0.0, tiles: 8 },
+            Step::Clahe { clip_limit: 2.0, tiles: 0 },
+            Step::Gamma { gamma: 0.0 },
+            Step::Resize { width: 112, height: -1 },
+        ];
+        for step in invalid {
+            let error = validate(&[Step::Equalize, step]).unwrap_err();
+            assert!(error.contains("Schritt 2"), "{error}");
+        }
+    }
+
+    #[test]
+    fn alignment_moves_the_eyes_onto_the_template() {
+        let map = |matrix: [[f64; 3]; 2], (x, y): (f64, f64)| {
+            (matrix[0][0] * x + matrix[0][1] * y + matrix[0][2], matrix[1][0] * x + matrix[1][1] * y + matrix[1][2])
+        };
+        // Schräg stehende Augen in einem 200×160-Ausschnitt auf eine 112×112-Vorlage
+        let eyes = Eyes { left: (0.3, 0.5), right: (0.7, 0.35) };
+        let matrix = alignment(eyes, (200.0, 160.0), (112.0, 112.0)).unwrap();
+        for (eye, target) in [(eyes.left, EYE_TEMPLATE.left), (eyes.right, EYE_TEMPLATE.right)] {
+            let (x, y) = map(matrix, (eye.0 * 200.0, eye.1 * 160.0));
+            assert!((x - target.0 * 112.0).abs() < 1e-9 && (y - target.1 * 112.0).abs() < 1e-9, "{x}, {y}");
+        }
+        assert_eq!(alignment(Eyes { left: (0.5, 0.5), right: (0.5, 0.5) }, (100.0, 100.0), (112.0, 112.0)), None);
+        assert!(validate(&[Step::Align { width: 0, height: 112 }]).is_err());
+    }
+
+    #[test]
+    fn apply_runs_steps_in_list_order() {
+        let face = Mat::new_rows_cols_with_default(20, 20, core::CV_8UC1, Scalar::all(128.0)).unwrap();
+        let pad = Step::Pad { fraction: 0.1 };
+        let resize = Step::Resize { width: 10, height: 10 };
+        // Erst skalieren, dann 1 Pixel Rand je Seite; umgekehrt bestimmt die Skalierung die Endgröße
+        let padded = Pipeline::new(vec![resize.clone(), pad.clone()]).unwrap().apply(&face).unwrap();
+        assert_eq!((padded.cols(), padded.rows()), (12, 12));
+        let resized = Pipeline::new(vec![pad, resize]).unwrap().apply(&face).unwrap();
+        assert_eq!((resized.cols(), resized.rows()), (10, 10));
+    }
+}
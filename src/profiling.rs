@@ -0,0 +1,273 @@
+//! Laufzeitmessung der einzelnen Pipeline-Stufen (--profile) und Taktung der Erkennung nach Laufzeit
+
+use std::time::{Duration, Instant};
+
+/// Stufen der Verarbeitungspipeline
+#[derive(Clone, Copy)]
+pub enum Stage {
+    Capture,
+    Gray,
+    Detection,
+    Crop,
+    Features,
+    Matching,
+    Draw,
+}
+
+const STAGE_NAMES: [&str; 7] = [
+    "Erfassung",
+    "Graustufen",
+    "Erkennung",
+    "Ausschnitt",
+    "Merkmale",
+    "Abgleich",
+    "Zeichnen",
+];
+
+/// Summiert die Laufzeit je Stufe; ist die Messung deaktiviert, wird keine Zeit genommen
+pub struct Profiler {
+    enabled: bool,
+    totals: [Duration; 7],
+    frames: u64,
+}
+
+impl Profiler {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            totals: [Duration::ZERO; 7],
+            frames: 0,
+        }
+    }
+
+    /// Startet die Messung einer Stufe
+    pub fn start(&self) -> Option<Instant> {
+        self.enabled.then(Instant::now)
+    }
+
+    /// Schließt die Messung einer Stufe ab
+    pub fn record(&mut self, stage: Stage, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.totals[stage as usize] += start.elapsed();
+        }
+    }
+
+    pub fn finish_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Gibt die Aufschlüsselung pro Frame aus
+    pub fn report(&self, label: &str) {
+        if !self.enabled || self.frames == 0 {
+            return;
+        }
+        let total: Duration = self.totals.iter().sum();
+        crate::say!("[{label}] Profil über {} Frames:", self.frames);
+        for (name, duration) in STAGE_NAMES.iter().zip(self.totals) {
+            let per_frame = duration.as_secs_f64() * 1000.0 / self.frames as f64;
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            crate::say!("  {name:<12}{per_frame:>8.2} ms/Frame ({share:>5.1} %)");
+        }
+    }
+}
+
+/// Erkennt, ob die Verarbeitung einer Live-Quelle hinter der Aufnahme zurückfällt
+pub struct DriftMonitor {
+    last_warning: Option<Instant>,
+}
+
+/// Ab diesem Alter eines Frames bei Verarbeitungsbeginn wird gewarnt
+const DRIFT_TOLERANCE: Duration = Duration::from_secs(1);
+/// Mindestabstand zwischen zwei Warnungen
+const DRIFT_WARNING_INTERVAL: Duration = Duration::from_secs(10);
+
+impl DriftMonitor {
+    pub fn new() -> Self {
+        Self { last_warning: None }
+    }
+
+    /// Prüft das Alter eines Frames; liefert den Rückstand, wenn eine Warnung fällig ist
+    pub fn check(&mut self, captured: Instant) -> Option<Duration> {
+        let lag = captured.elapsed();
+        if lag < DRIFT_TOLERANCE || self.last_warning.is_some_and(|at| at.elapsed() < DRIFT_WARNING_INTERVAL) {
+            return None;
+        }
+        self.last_warning = Some(Instant::now());
+        Some(lag)
+    }
+}
+
+/// Fasst eine Warnung zusammen, die in jedem Frame auftreten kann: höchstens eine Meldung je Intervall
+pub struct Throttle {
+    interval: Duration,
+    last_warning: Option<Instant>,
+    count: u64,
+}
+
+impl Throttle {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval, last_warning: None, count: 0 }
+    }
+
+    /// Zählt einen Fall; liefert die Zahl der Fälle seit der letzten Meldung, wenn wieder gemeldet werden darf
+    pub fn hit(&mut self) -> Option<u64> {
+        self.count += 1;
+        if self.last_warning.is_some_and(|at| at.elapsed() < self.interval) {
+            return None;
+        }
+        self.last_warning = Some(Instant::now());
+        Some(std::mem::take(&mut self.count))
+    }
+}
+
+/// Rückstand eines Frames gegenüber Echtzeit bei Verarbeitungsbeginn: bei Live-Quellen sein Alter,
+/// bei Videodateien der Abstand zwischen verstrichener Zeit und Abspielposition seit dem ersten Frame
+#[derive(Default)]
+pub struct Schedule {
+    start: Option<(Instant, f64)>,
+}
+
+impl Schedule {
+    pub fn lag(&mut self, captured: Instant, media_ms: Option<f64>) -> Duration {
+        let Some(media_ms) = media_ms else {
+            return captured.elapsed();
+        };
+        let (started, start_ms) = *self.start.get_or_insert((Instant::now(), media_ms));
+        let played = Duration::from_secs_f64((media_ms - start_ms).max(0.0) / 1000.0);
+        started.elapsed().saturating_sub(played)
+    }
+}
+
+/// Frames je Messfenster, über die die Verarbeitungszeit für `DetectionCadence` gemittelt wird
+const CADENCE_WINDOW: u32 = 30;
+/// Reserve gegenüber der Ziel-Bildrate, ab der wieder häufiger erkannt wird; verhindert ein Pendeln
+const CADENCE_HEADROOM: f64 = 1.3;
+
+/// Bestimmt, in welchen Frames erkannt wird (--detect-every). Mit Ziel-Bildrate (--target-fps) wird die
+/// Schrittweite nach der gemessenen Verarbeitungszeit je Frame zwischen den Grenzen nachgeführt:
+/// zu langsam — seltener erkennen, reichlich Reserve — wieder häufiger.
+pub struct DetectionCadence {
+    every: u32,
+    min: u32,
+    max: u32,
+    target: Option<Duration>,
+    countdown: u32,
+    elapsed: Duration,
+    frames: u32,
+}
+
+impl DetectionCadence {
+    pub fn new(every: u32, max: u32, target_fps: Option<f64>) -> Self {
+        let every = every.max(1);
+        Self {
+            every,
+            min: every,
+            max: max.max(every),
+            target: target_fps.map(|fps| Duration::from_secs_f64(1.0 / fps)),
+            countdown: 0,
+            elapsed: Duration::ZERO,
+            frames: 0,
+        }
+    }
+
+    /// Ob im nächsten Frame erkannt wird
+    pub fn due(&mut self) -> bool {
+        if self.countdown == 0 {
+            self.countdown = self.every - 1;
+            true
+        } else {
+            self.countdown -= 1;
+            false
+        }
+    }
+
+    /// Vermerkt die Verarbeitungszeit eines Frames (ohne Warten auf die Kamera); liefert die neue
+    /// Schrittweite, wenn sie sich ändert
+    pub fn record(&mut self, elapsed: Duration) -> Option<u32> {
+        let target = self.target?;
+        self.elapsed += elapsed;
+        self.frames += 1;
+        // Das Fenster umfasst stets mehrere Frames mit Erkennung
+        if self.frames < CADENCE_WINDOW.max(3 * self.every) {
+            return None;
+        }
+        let per_frame = self.elapsed / self.frames;
+        (self.elapsed, self.frames) = (Duration::ZERO, 0);
+        let every = if per_frame > target {
+            (self.every + 1).min(self.max)
+        } else if per_frame.as_secs_f64() * CADENCE_HEADROOM < target.as_secs_f64() {
+            (self.every - 1).max(self.min)
+        } else {
+            self.every
+        };
+        (every != self.every).then(|| {
+            self.every = every;
+            self.countdown = self.countdown.min(every - 1);
+            every
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn throttle_reports_once_per_interval() {
+        let mut throttle = Throttle::new(Duration::from_secs(3600));
+        assert_eq!(throttle.hit(), Some(1));
+        assert_eq!(throttle.hit(), None);
+        assert_eq!(throttle.hit(), None);
+        throttle.interval = Duration::ZERO;
+        assert_eq!(throttle.hit(), Some(3), "die unterdrückten Fälle werden mitgezählt");
+    }
+
+    fn pattern(cadence: &mut DetectionCadence, frames: usize) -> Vec<bool> {
+        (0..frames).map(|_| cadence.due()).collect()
+    }
+
+    #[test]
+    fn detects_every_nth_frame() {
+        let mut cadence = DetectionCadence::new(3, 3, None);
+        assert_eq!(pattern(&mut cadence, 7), [true, false, false, true, false, false, true]);
+        // Ohne Ziel-Bildrate bleibt die Schrittweite fest
+        assert_eq!(cadence.record(Duration::from_secs(1)), None);
+        assert_eq!(DetectionCadence::new(0, 0, None).every, 1);
+    }
+
+    #[test]
+    fn slows_down_when_too_slow_and_recovers_within_bounds() {
+        // 10 fps: 100 ms je Frame
+        let mut cadence = DetectionCadence::new(1, 3, Some(10.0));
+        let mut changes = Vec::new();
+        for _ in 0..4 * CADENCE_WINDOW {
+            changes.extend(cadence.record(Duration::from_millis(150)));
+        }
+        assert_eq!(changes, [2, 3], "höchstens bis zur Obergrenze");
+        // Knapp unter dem Ziel, aber ohne Reserve: keine Änderung
+        for _ in 0..2 * CADENCE_WINDOW {
+            assert_eq!(cadence.record(Duration::from_millis(90)), None);
+        }
+        changes.clear();
+        for _ in 0..4 * CADENCE_WINDOW {
+            changes.extend(cadence.record(Duration::from_millis(20)));
+        }
+        assert_eq!(changes, [2, 1], "nicht unter die eingestellte Schrittweite");
+    }
+
+    #[test]
+    fn a_shorter_interval_takes_effect_at_once() {
+        let mut cadence = DetectionCadence::new(1, 3, Some(10.0));
+        cadence.every = 3;
+        cadence.countdown = 2;
+        for _ in 0..CADENCE_WINDOW - 1 {
+            assert_eq!(cadence.record(Duration::from_millis(10)), None);
+        }
+        assert_eq!(cadence.record(Duration::from_millis(10)), Some(2));
+        assert_eq!(pattern(&mut cadence, 3), [false, true, false]);
+    }
+}
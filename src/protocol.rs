@@ -0,0 +1,88 @@
+//! Maschinenlesbare Ausgabe für den Betrieb als Kindprozess. Mit `--protocol` gehört stdout allein den
+//! Nachrichten; alle Meldungen für Menschen (`say!`) gehen dann nach stderr.
+//!
+//! Jede Nachricht ist ein JSON-Objekt mit dem Feld `type`:
+//! - `hello`: einmal zu Beginn, mit Protokollversion, Programmversion, Embedding-Dimension und Quellen
+//! - `decision`: je Zugangsentscheidung, mit denselben Feldern wie eine Zeile im Audit-Log
+//! - `presence`: Wechsel zwischen Leerlauf und Anwesenheit (`--idle-after`)
+//! - `end`: zum Abschluss, danach folgt nichts mehr
+//!
+//! Als Rahmen dient entweder eine Zeile je Nachricht (`ndjson`) oder eine vorangestellte Länge
+//! (`length-prefixed`: 4 Byte Big-Endian, danach so viele Bytes UTF-8-JSON).
+
+use crate::{Event, PresenceEvent};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+/// Wird erhöht, sobald sich Felder bestehender Nachrichten ändern; neue Nachrichtentypen sind kompatibel
+pub const VERSION: u32 = 1;
+
+/// Rahmen der Nachrichten auf stdout
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Framing {
+    /// Eine JSON-Nachricht je Zeile
+    Ndjson,
+    /// Länge als u32 (Big-Endian) vor jeder JSON-Nachricht
+    LengthPrefixed,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Message<'a> {
+    Hello {
+        version: u32,
+        app_version: &'static str,
+        /// Länge der Embeddings; fehlt ohne Embedding-Modell (LBPH)
+        #[serde(skip_serializing_if = "Option::is_none")]
+        dimension: Option<usize>,
+        sources: Vec<String>,
+    },
+    Decision(&'a Event<'a>),
+    Presence(&'a PresenceEvent<'a>),
+    End,
+}
+
+static FRAMING: OnceLock<Framing> = OnceLock::new();
+
+/// Schaltet stdout auf das Protokoll um; muss vor der ersten Ausgabe geschehen
+pub fn start(framing: Framing) {
+    FRAMING.set(framing).ok().expect("Protokoll bereits gestartet");
+}
+
+pub fn active() -> bool {
+    FRAMING.get().is_some()
+}
+
+/// Schreibt eine Nachricht, sofern das Protokoll aktiv ist. Ist der Elternprozess weg, endet das Programm.
+pub fn send(message: &Message) {
+    let Some(framing) = FRAMING.get() else {
+        return;
+    };
+    let payload = serde_json::to_vec(message).expect("Fehler beim Serialisieren");
+    let mut stdout = io::stdout().lock();
+    let written = match framing {
+        Framing::Ndjson => stdout.write_all(&payload).and_then(|_| stdout.write_all(b"\n")),
+        Framing::LengthPrefixed => {
+            let length = u32::try_from(payload.len()).expect("Nachricht zu groß");
+            stdout.write_all(&length.to_be_bytes()).and_then(|_| stdout.write_all(&payload))
+        }
+    };
+    if let Err(e) = written.and_then(|_| stdout.flush()) {
+        eprintln!("Fehler: Protokollausgabe fehlgeschlagen ({e})");
+        std::process::exit(1);
+    }
+}
+
+/// Meldung für Menschen: stdout wie `println!`, im Protokollbetrieb stderr
+#[macro_export]
+macro_rules! say {
+    ($($arg:tt)*) => {
+        if $crate::protocol::active() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
@@ -0,0 +1,31 @@
+//! Kennzahlen aus Trefferzählungen, gemeinsam für `calibrate` und `detect-eval`
+
+/// Anteil von `hits` an `hits + misses`; 0 ohne Zählungen
+pub fn ratio(hits: usize, misses: usize) -> f64 {
+    if hits + misses == 0 { 0.0 } else { hits as f64 / (hits + misses) as f64 }
+}
+
+/// Harmonisches Mittel aus Präzision und Trefferquote; 0, wenn beide 0 sind
+pub fn f1(precision: f64, recall: f64) -> f64 {
+    if precision + recall > 0.0 { 2.0 * precision * recall / (precision + recall) } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratio_of_hits() {
+        assert_eq!(ratio(3, 1), 0.75);
+        assert_eq!(ratio(0, 4), 0.0);
+        assert_eq!(ratio(0, 0), 0.0, "ohne Zählungen keine Division durch 0");
+    }
+
+    #[test]
+    fn f1_is_the_harmonic_mean() {
+        assert_eq!(f1(1.0, 1.0), 1.0);
+        assert_eq!(f1(0.5, 1.0), 2.0 / 3.0);
+        assert_eq!(f1(0.0, 0.0), 0.0);
+        assert_eq!(f1(0.0, 1.0), 0.0);
+    }
+}
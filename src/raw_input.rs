@@ -0,0 +1,127 @@
+//! Unkomprimierte Frames von der Standardeingabe oder einer Named Pipe, z. B. von ffmpeg oder GStreamer:
+//! `ffmpeg -i rtsp://… -f rawvideo -pix_fmt bgr24 -s 1280x720 - | facer run --raw-input format=bgr24,1280x720`.
+//! So lassen sich Quellen verarbeiten, die VideoCapture selbst nicht öffnen kann.
+
+use opencv::{
+    core::{self, Mat, Scalar},
+    imgproc,
+    prelude::*,
+};
+use std::fs::File;
+use std::io::{self, Read};
+
+/// Pixelformat des Datenstroms, benannt wie bei ffmpeg
+#[derive(Clone, Copy, Debug)]
+pub enum PixelFormat {
+    Bgr24,
+    Rgb24,
+    Gray,
+}
+
+impl PixelFormat {
+    fn channels(self) -> usize {
+        match self {
+            PixelFormat::Bgr24 | PixelFormat::Rgb24 => 3,
+            PixelFormat::Gray => 1,
+        }
+    }
+}
+
+/// Beschreibung des Datenstroms: `format=bgr24,1280x720[,path=/tmp/kamera.fifo]`; ohne `path` die Standardeingabe
+#[derive(Clone, Debug)]
+pub struct RawInput {
+    pub path: Option<String>,
+    pub format: PixelFormat,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl RawInput {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (mut path, mut format, mut size) = (None, None, None);
+        for part in spec.split(',').map(str::trim) {
+            if let Some(value) = part.strip_prefix("format=") {
+                format = Some(match value {
+                    "bgr24" => PixelFormat::Bgr24,
+                    "rgb24" => PixelFormat::Rgb24,
+                    "gray" | "gray8" => PixelFormat::Gray,
+                    _ => return Err(format!("unbekanntes Pixelformat {value} (bgr24, rgb24 oder gray)")),
+                });
+            } else if let Some(value) = part.strip_prefix("path=") {
+                path = Some(value.to_string()).filter(|path| path != "-");
+            } else if let Some((width, height)) = part.split_once('x') {
+                let parse = |value: &str| value.parse::<i32>().ok().filter(|&value| value > 0);
+                size = Some(
+                    parse(width)
+                        .zip(parse(height))
+                        .ok_or_else(|| format!("ungültige Auflösung {part}, erwartet z. B. 1280x720"))?,
+                );
+            } else {
+                return Err(format!("unbekannte Angabe {part}"));
+            }
+        }
+        let format = format.ok_or("Pixelformat fehlt, z. B. format=bgr24")?;
+        let (width, height) = size.ok_or("Auflösung fehlt, z. B. 1280x720")?;
+        Ok(Self { path, format, width, height })
+    }
+
+    pub fn label(&self) -> String {
+        format!("Rohdaten ({})", self.path.as_deref().unwrap_or("stdin"))
+    }
+
+    pub fn open(&self) -> io::Result<RawReader> {
+        let input: Box<dyn Read + Send> = match &self.path {
+            Some(path) => Box::new(File::open(path)?),
+            None => Box::new(io::stdin()),
+        };
+        Ok(RawReader {
+            input,
+            format: self.format,
+            width: self.width,
+            height: self.height,
+            buffer: vec![0; self.width as usize * self.height as usize * self.format.channels()],
+        })
+    }
+}
+
+pub struct RawReader {
+    input: Box<dyn Read + Send>,
+    format: PixelFormat,
+    width: i32,
+    height: i32,
+    buffer: Vec<u8>,
+}
+
+impl RawReader {
+    /// Liest den nächsten vollständigen Frame; None am Ende des Datenstroms
+    pub fn read(&mut self) -> Option<Mat> {
+        if let Err(e) = self.input.read_exact(&mut self.buffer) {
+            if e.kind() != io::ErrorKind::UnexpectedEof {
+                eprintln!("Warnung: --raw-input konnte nicht gelesen werden: {e}");
+            }
+            return None;
+        }
+        match self.frame() {
+            Ok(frame) => Some(frame),
+            Err(e) => {
+                eprintln!("Warnung: Frame von --raw-input konnte nicht umgewandelt werden: {e}");
+                None
+            }
+        }
+    }
+
+    fn frame(&self) -> opencv::Result<Mat> {
+        let typ = match self.format.channels() {
+            1 => core::CV_8UC1,
+            _ => core::CV_8UC3,
+        };
+        let mut frame = Mat::new_rows_cols_with_default(self.height, self.width, typ, Scalar::all(0.0))?;
+        frame.data_bytes_mut()?.copy_from_slice(&self.buffer);
+        if let PixelFormat::Rgb24 = self.format {
+            let mut bgr = Mat::default();
+            imgproc::cvt_color(&frame, &mut bgr, imgproc::COLOR_RGB2BGR, 0, unsafe { std::mem::zeroed() })?;
+            frame = bgr;
+        }
+        Ok(frame)
+    }
+}
@@ -0,0 +1,115 @@
+//! Beweisbilder bei Alarmen mit begrenzter Aufbewahrung. Gespeichert wird nicht der Frame des Alarms,
+//! sondern der schärfste der letzten Frames derselben Spur.
+
+use crate::FacerecError;
+use chrono::Local;
+use opencv::{core::{Mat, Vector}, imgcodecs};
+use std::fs;
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Namensschema der eigenen Dateien; beim Aufräumen wird nichts anderes im Ordner angefasst
+const PREFIX: &str = "alarm-";
+const EXTENSION: &str = ".jpg";
+
+/// Speichert bei jedem Alarm den Frame und begrenzt danach Anzahl und Alter der abgelegten Bilder
+pub struct SnapshotStore {
+    dir: String,
+    max_count: Option<usize>,
+    ttl: Option<Duration>,
+    /// Schreiben und Aufräumen mehrerer Kamera-Threads nicht verschränken
+    lock: Mutex<()>,
+}
+
+impl SnapshotStore {
+    pub fn open(dir: &str, max_count: Option<usize>, ttl: Option<Duration>) -> Result<Self, FacerecError> {
+        fs::create_dir_all(dir).map_err(|source| FacerecError::Write { path: dir.to_string(), source })?;
+        Ok(Self {
+            dir: dir.to_string(),
+            max_count,
+            ttl,
+            lock: Mutex::new(()),
+        })
+    }
+
+    pub fn save(&self, camera: &str, frame: &Mat) {
+        let _guard = self.lock.lock().unwrap();
+        let camera: String = camera.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        // Der Zeitstempel im Namen sortiert die Bilder chronologisch
+        let path = format!(
+            "{}/{PREFIX}{}-{camera}{EXTENSION}",
+            self.dir,
+            Local::now().format("%Y%m%d-%H%M%S%.3f")
+        );
+        if !imgcodecs::imwrite(&path, frame, &Vector::new()).unwrap_or(false) {
+            eprintln!("Warnung: Alarmbild {path} konnte nicht gespeichert werden");
+        }
+        self.clean_up();
+    }
+
+    /// Löscht abgelaufene Bilder und danach die ältesten über der Höchstzahl
+    fn clean_up(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut snapshots: Vec<(String, PathBuf, SystemTime)> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let modified = entry.metadata().and_then(|metadata| metadata.modified()).ok()?;
+                (name.starts_with(PREFIX) && name.ends_with(EXTENSION)).then(|| (name, entry.path(), modified))
+            })
+            .collect();
+        snapshots.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let now = SystemTime::now();
+        let expired = |modified: SystemTime| {
+            self.ttl.is_some_and(|ttl| now.duration_since(modified).is_ok_and(|age| age > ttl))
+        };
+        let excess = self.max_count.map_or(0, |max| snapshots.len().saturating_sub(max));
+        for (index, (_, path, modified)) in snapshots.iter().enumerate() {
+            if (index < excess || expired(*modified))
+                && let Err(e) = fs::remove_file(path)
+            {
+                eprintln!("Warnung: Alarmbild {} konnte nicht gelöscht werden: {e}", path.display());
+            }
+        }
+    }
+}
+
+/// Frames je Spur, unter denen der schärfste als Beweisbild gewählt wird
+const BEST_FRAME_WINDOW: usize = 8;
+
+/// Die letzten Frames jeder Spur samt Schärfe des Gesichts; ein Frame mit mehreren Gesichtern wird geteilt
+#[derive(Default)]
+pub struct BestFrames {
+    tracks: HashMap<u64, VecDeque<(f64, Rc<Mat>)>>,
+}
+
+impl BestFrames {
+    pub fn push(&mut self, track: u64, sharpness: f64, frame: &Rc<Mat>) {
+        let frames = self.tracks.entry(track).or_default();
+        if frames.len() == BEST_FRAME_WINDOW {
+            frames.pop_front();
+        }
+        frames.push_back((sharpness, Rc::clone(frame)));
+    }
+
+    /// Schärfster gepufferter Frame der Spur
+    pub fn best(&self, track: u64) -> Option<&Mat> {
+        self.tracks
+            .get(&track)?
+            .iter()
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .map(|(_, frame)| frame.as_ref())
+    }
+
+    /// Vergisst beendete Spuren
+    pub fn retain(&mut self, keep: impl Fn(u64) -> bool) {
+        self.tracks.retain(|&track, _| keep(track));
+    }
+}
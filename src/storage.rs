@@ -0,0 +1,332 @@
+//! Galerie bekannter Gesichter: Einträge, Kopf der Datenbankdatei sowie Lesen und Schreiben im JSON- oder
+//! binären Format (nach Dateiendung)
+
+use crate::binary;
+use crate::error::FacerecError;
+use crate::matching;
+use crate::pca::Projection;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fs::{self, File};
+use std::io::Write;
+use uuid::Uuid;
+
+/// Gespeichertes Zugangsrecht einer Person
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum AccessLevel {
+    Allowed,
+    Denied,
+    /// Zugang erlaubt, jede Wiedererkennung wird zur Prüfung vorgemerkt
+    Probation,
+}
+
+/// Liest das Zugangsrecht; ältere Datenbanken speichern es als `"allowed": true/false`
+fn deserialize_access<'de, D: Deserializer<'de>>(deserializer: D) -> Result<AccessLevel, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Legacy(bool),
+        Level(AccessLevel),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Legacy(true) => AccessLevel::Allowed,
+        Stored::Legacy(false) => AccessLevel::Denied,
+        Stored::Level(level) => level,
+    })
+}
+
+/// Liest die Embeddings eines Eintrags; ältere Datenbanken speichern ein einzelnes unter `features`
+fn deserialize_embeddings<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<f32>>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Single(Vec<f32>),
+        Gallery(Vec<Vec<f32>>),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Single(features) => vec![features],
+        Stored::Gallery(embeddings) => embeddings,
+    })
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct FaceEntry {
+    pub id: String,
+    /// Mehrere Aufnahmen derselben Person (Blickwinkel, Beleuchtung); verglichen wird mit der ähnlichsten
+    #[serde(alias = "features", deserialize_with = "deserialize_embeddings")]
+    pub embeddings: Vec<Vec<f32>>,
+    #[serde(alias = "allowed", deserialize_with = "deserialize_access")]
+    pub access: AccessLevel,
+    #[serde(default)]
+    pub crop: Option<String>, // Pfad zum gespeicherten Gesichtsausschnitt
+    /// Ausschnitt je Aufnahme in der Reihenfolge von `embeddings`, damit `reindex` jede neu berechnen kann;
+    /// leer bei Einträgen mit nur einer Aufnahme und aus älteren Fassungen, dann gilt `crop` für die erste
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub crops: Vec<Option<String>>,
+    #[serde(default)]
+    pub needs_reenrollment: bool, // true: kein Ausschnitt vorhanden, Embedding veraltet
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub valid_until: Option<DateTime<Local>>, // Ende des Besucherzugangs; None: unbefristet
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Local>>, // letzte Wiedererkennung
+    #[serde(default)]
+    pub match_count: u64, // Anzahl der Wiedererkennungen
+    #[serde(default)]
+    pub notes: Option<String>, // Hinweis für den Bediener, z. B. "Lieferant" oder der Grund einer Sperre
+    #[serde(default)]
+    pub pin_hash: Option<String>, // Hash der PIN für den zweiten Faktor, siehe `pin`
+}
+
+impl FaceEntry {
+    pub fn new(features: Vec<f32>, access: AccessLevel) -> Self {
+        Self::with_id(Uuid::new_v4().to_string(), features, access)
+    }
+
+    pub fn with_id(id: String, features: Vec<f32>, access: AccessLevel) -> Self {
+        Self {
+            id,
+            embeddings: vec![features],
+            access,
+            crop: None,
+            crops: Vec::new(),
+            needs_reenrollment: false,
+            name: None,
+            valid_until: None,
+            last_seen: None,
+            match_count: 0,
+            notes: None,
+            pin_hash: None,
+        }
+    }
+}
+
+impl FaceEntry {
+    /// Ähnlichkeit zur ähnlichsten Aufnahme dieser Person
+    pub fn similarity(&self, features: &[f32]) -> f32 {
+        matching::similarity(&self.embeddings, features)
+    }
+
+    pub fn dimension(&self) -> Option<usize> {
+        self.embeddings.first().map(Vec::len)
+    }
+
+    /// Ausschnitt, aus dem die Aufnahme `index` berechnet wurde
+    pub fn crop_of(&self, index: usize) -> Option<&str> {
+        if self.crops.is_empty() {
+            self.crop.as_deref().filter(|_| index == 0)
+        } else {
+            self.crops.get(index)?.as_deref()
+        }
+    }
+
+    /// Fügt eine weitere Aufnahme samt ihrem Ausschnitt hinzu
+    pub fn add_embedding(&mut self, features: Vec<f32>, crop: Option<String>) {
+        self.crops = (0..self.embeddings.len()).map(|index| self.crop_of(index).map(str::to_string)).collect();
+        self.embeddings.push(features);
+        self.crops.push(crop);
+    }
+}
+
+/// Leitet eine ID aus dem Embedding ab, damit dieselbe Erfassung über Läufe hinweg dieselbe ID erhält
+pub fn deterministic_id(features: &[f32]) -> String {
+    let bytes: Vec<u8> = features.iter().flat_map(|value| value.to_le_bytes()).collect();
+    Uuid::new_v5(&Uuid::NAMESPACE_OID, &bytes).to_string()
+}
+
+/// Inhalt der Datenbankdatei: Kopf mit der Embedding-Dimension und dem Modell, sowie die Einträge
+#[derive(Serialize, Deserialize, Default)]
+pub struct Database {
+    #[serde(default)]
+    pub dimension: Option<usize>,
+    /// Hash der Modelldatei, mit der die Embeddings berechnet wurden
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Hauptachsen aus `fit-pca` für den verkleinerten Abgleich
+    #[serde(default)]
+    pub projection: Option<Projection>,
+    pub faces: Vec<FaceEntry>,
+}
+
+/// Ältere Datenbanken bestehen nur aus der Liste der Einträge
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum StoredDatabase {
+    Current(Database),
+    Legacy(Vec<FaceEntry>),
+}
+
+/// Liest eine Datenbank im Format ihrer Dateiendung, z. B. für das Neuladen im laufenden Betrieb. Fehler werden
+/// zurückgegeben; eine leere Datei gilt als halb geschrieben statt als neue Datenbank.
+pub fn try_read_database(path: &str) -> Result<Database, FacerecError> {
+    let invalid = |reason: String| FacerecError::Invalid { path: path.to_string(), reason };
+    let bytes = fs::read(path).map_err(|source| FacerecError::Read { path: path.to_string(), source })?;
+    if bytes.is_empty() {
+        return Err(invalid("Datei ist leer".to_string()));
+    }
+    if binary::is_binary(path) {
+        return binary::decode(&bytes).map_err(invalid);
+    }
+    match serde_json::from_slice(&bytes) {
+        Ok(StoredDatabase::Current(database)) => Ok(database),
+        Ok(StoredDatabase::Legacy(faces)) => Ok(Database {
+            dimension: faces.first().and_then(FaceEntry::dimension),
+            model: None,
+            projection: None,
+            faces,
+        }),
+        Err(e) => {
+            // Das unbestimmte Format verdeckt die Fehlerstelle; daher erneut im erkennbaren Format lesen
+            let error = if bytes.trim_ascii_start().starts_with(b"[") {
+                serde_json::from_slice::<Vec<FaceEntry>>(&bytes).err()
+            } else {
+                serde_json::from_slice::<Database>(&bytes).err()
+            }
+            .unwrap_or(e);
+            Err(invalid(error.to_string()))
+        }
+    }
+}
+
+/// Schreibt eine Datenbank im Format ihrer Dateiendung; die Dimension im Kopf ergibt sich aus den Einträgen
+pub fn store_database(
+    path: &str,
+    data: &[FaceEntry],
+    model: Option<&str>,
+    projection: Option<&Projection>,
+) -> Result<(), FacerecError> {
+    let failed = |source| FacerecError::Write { path: path.to_string(), source };
+    if binary::is_binary(path) {
+        return fs::write(path, binary::encode(data, model, projection)).map_err(failed);
+    }
+    #[derive(Serialize)]
+    struct DatabaseRef<'a> {
+        dimension: Option<usize>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        model: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        projection: Option<&'a Projection>,
+        faces: &'a [FaceEntry],
+    }
+    let database = DatabaseRef {
+        dimension: data.first().and_then(FaceEntry::dimension),
+        model,
+        projection,
+        faces: data,
+    };
+    let json_data = serde_json::to_string_pretty(&database).expect("Fehler beim Serialisieren");
+    let mut file = File::create(path).map_err(failed)?;
+    file.write_all(json_data.as_bytes()).map_err(failed)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use proptest::prelude::*;
+
+    fn access_level() -> impl Strategy<Value = AccessLevel> {
+        prop_oneof![
+            Just(AccessLevel::Allowed),
+            Just(AccessLevel::Denied),
+            Just(AccessLevel::Probation),
+        ]
+    }
+
+    fn timestamp() -> impl Strategy<Value = DateTime<Local>> {
+        (0i64..4_000_000_000, 0u32..1_000_000_000).prop_map(|(secs, nanos)| Local.timestamp_opt(secs, nanos).unwrap())
+    }
+
+    prop_compose! {
+        pub(crate) fn face_entry()(
+            id in "[a-z0-9-]{1,36}",
+            embeddings in prop::collection::vec(prop::collection::vec(-1.0f32..1.0, 1..16), 1..4),
+            access in access_level(),
+            crop in prop::option::of("[a-z0-9_./]{1,40}"),
+            crops in prop::collection::vec(prop::option::of("[a-z0-9_./]{1,40}"), 0..4),
+            needs_reenrollment in any::<bool>(),
+            name in prop::option::of(".{0,20}"),
+            valid_until in prop::option::of(timestamp()),
+            last_seen in prop::option::of(timestamp()),
+            match_count in any::<u64>(),
+            notes in prop::option::of(".{0,40}"),
+            pin_hash in prop::option::of("[0-9a-f]{32}"),
+        ) -> FaceEntry {
+            FaceEntry {
+                id,
+                embeddings,
+                access,
+                crop,
+                crops,
+                needs_reenrollment,
+                name,
+                valid_until,
+                last_seen,
+                match_count,
+                notes,
+                pin_hash,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn face_entry_round_trip(entry in face_entry()) {
+            let json = serde_json::to_string(&entry).unwrap();
+            let parsed: FaceEntry = serde_json::from_str(&json).unwrap();
+            prop_assert_eq!(parsed, entry);
+        }
+
+        #[test]
+        fn database_round_trip(faces in prop::collection::vec(face_entry(), 0..5)) {
+            let database = Database {
+                dimension: faces.first().and_then(FaceEntry::dimension),
+                model: Some("modell".to_string()),
+                projection: None,
+                faces,
+            };
+            let json = serde_json::to_string(&database).unwrap();
+            let Ok(StoredDatabase::Current(parsed)) = serde_json::from_str(&json) else {
+                panic!("Datenbank mit Kopf nicht als aktuelles Format erkannt: {json}");
+            };
+            prop_assert_eq!(parsed.dimension, database.dimension);
+            prop_assert_eq!(parsed.model, database.model);
+            prop_assert_eq!(parsed.faces, database.faces);
+        }
+    }
+
+    #[test]
+    fn legacy_entry_gets_defaults() {
+        let entry: FaceEntry = serde_json::from_str(r#"{"id":"alt","features":[0.5,-0.25],"allowed":true}"#).unwrap();
+        assert_eq!(entry, FaceEntry::with_id("alt".to_string(), vec![0.5, -0.25], AccessLevel::Allowed));
+
+        let denied: FaceEntry = serde_json::from_str(r#"{"id":"gesperrt","features":[1.0],"allowed":false}"#).unwrap();
+        assert_eq!(denied.access, AccessLevel::Denied);
+    }
+
+    #[test]
+    fn crops_follow_the_embeddings() {
+        let mut entry = FaceEntry::with_id("a".to_string(), vec![1.0], AccessLevel::Allowed);
+        entry.crop = Some("a.png".to_string());
+        assert_eq!((entry.crop_of(0), entry.crop_of(1)), (Some("a.png"), None));
+
+        entry.add_embedding(vec![0.5], None);
+        entry.add_embedding(vec![0.25], Some("a-2.png".to_string()));
+        assert_eq!(entry.crops, [Some("a.png".to_string()), None, Some("a-2.png".to_string())]);
+        assert_eq!((entry.crop_of(1), entry.crop_of(2)), (None, Some("a-2.png")));
+    }
+
+    #[test]
+    fn legacy_database_is_a_plain_list() {
+        let json = r#"[{"id":"a","features":[0.1,0.2,0.3],"allowed":true}]"#;
+        let Ok(StoredDatabase::Legacy(faces)) = serde_json::from_str(json) else {
+            panic!("Liste ohne Kopf nicht als altes Format erkannt");
+        };
+        assert_eq!(faces.len(), 1);
+        assert_eq!(faces[0].embeddings, vec![vec![0.1, 0.2, 0.3]]);
+        assert_eq!(faces[0].match_count, 0);
+        assert!(faces[0].last_seen.is_none());
+    }
+}
@@ -0,0 +1,23 @@
+//! Reproduzierbare Pseudozufallszahlen (xorshift) für die Tests, damit sie ohne Zufallsquelle auskommen
+
+pub struct Rng(pub u64);
+
+impl Rng {
+    /// Gleichverteilt in [-1, 1)
+    pub fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 40) as f32 / (1u64 << 24) as f32 * 2.0 - 1.0
+    }
+
+    pub fn vector(&mut self, dimension: usize) -> Vec<f32> {
+        (0..dimension).map(|_| self.next()).collect()
+    }
+
+    pub fn unit_vector(&mut self, dimension: usize) -> Vec<f32> {
+        let vector = self.vector(dimension);
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        vector.into_iter().map(|v| v / norm).collect()
+    }
+}
@@ -0,0 +1,100 @@
+//! SMPTE-Timecode für Ereignisse aus Videodateien (--timecode), damit sich ein Treffer in Schnittprogrammen
+//! direkt anspringen lässt. Bei 29,97 und 59,94 fps wird Drop-Frame-Timecode (`HH:MM:SS;FF`) erzeugt,
+//! sonst Non-Drop-Frame (`HH:MM:SS:FF`) zur gerundeten Bildrate.
+
+#[derive(Clone, Copy)]
+pub struct Timecode {
+    fps: f64,
+    /// Ganzzahlige Nennbildrate, in der gezählt wird (30 bei 29,97 fps)
+    nominal: u64,
+    drop_frame: bool,
+}
+
+impl Timecode {
+    /// None, wenn die Videodatei keine brauchbare Bildrate meldet
+    pub fn new(fps: f64) -> Option<Self> {
+        if !fps.is_finite() || fps < 1.0 {
+            return None;
+        }
+        let nominal = fps.round() as u64;
+        // NTSC-Raten: Nennrate · 1000/1001
+        let ntsc = (fps - nominal as f64 * 1000.0 / 1001.0).abs() < 0.01;
+        Some(Self {
+            fps,
+            nominal,
+            drop_frame: ntsc && nominal.is_multiple_of(30),
+        })
+    }
+
+    /// Timecode des Frames an der Abspielposition `media_ms`
+    pub fn at(&self, media_ms: f64) -> String {
+        self.format((media_ms.max(0.0) / 1000.0 * self.fps).round() as u64)
+    }
+
+    /// Timecode zur Framenummer ab Videobeginn
+    pub fn format(&self, frame: u64) -> String {
+        let mut frame = frame;
+        if self.drop_frame {
+            // Je Minute entfallen die ersten Nummern, außer in jeder zehnten Minute
+            let dropped = self.nominal / 15;
+            let per_ten_minutes = self.nominal * 600 - dropped * 9;
+            let per_minute = self.nominal * 60 - dropped;
+            let (tens, rest) = (frame / per_ten_minutes, frame % per_ten_minutes);
+            frame += dropped * 9 * tens;
+            if rest > dropped {
+                frame += dropped * ((rest - dropped) / per_minute);
+            }
+        }
+        let frames = frame % self.nominal;
+        let seconds = frame / self.nominal;
+        let separator = if self.drop_frame { ';' } else { ':' };
+        format!(
+            "{:02}:{:02}:{:02}{separator}{frames:02}",
+            seconds / 3600 % 24,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Erster Frame der Minute `minute` bei Drop-Frame-Zählung mit `nominal` Nennbildrate
+    fn first_frame_of(minute: u64, nominal: u64) -> u64 {
+        let dropped = nominal / 15;
+        let (tens, ones) = (minute / 10, minute % 10);
+        let since_ten = if ones == 0 { 0 } else { nominal * 60 + (ones - 1) * (nominal * 60 - dropped) };
+        tens * (nominal * 600 - dropped * 9) + since_ten
+    }
+
+    #[test]
+    fn drop_frame_skips_numbers_at_each_minute_except_every_tenth() {
+        for (fps, nominal, last) in [(30000.0 / 1001.0, 30, "29"), (60000.0 / 1001.0, 60, "59")] {
+            let timecode = Timecode::new(fps).unwrap();
+            let first = if nominal == 30 { "02" } else { "04" };
+            assert_eq!(timecode.format(nominal * 60 - 1), format!("00:00:59;{last}"));
+            assert_eq!(timecode.format(nominal * 60), format!("00:01:00;{first}"));
+            for minute in 1..=60 {
+                let frame = first_frame_of(minute, nominal);
+                let (hours, minutes) = (minute / 60, minute % 60);
+                let expected = if minute % 10 == 0 { "00" } else { first };
+                assert_eq!(timecode.format(frame), format!("{hours:02}:{minutes:02}:00;{expected}"), "{fps} fps");
+                let (hours, minutes) = ((minute - 1) / 60, (minute - 1) % 60);
+                assert_eq!(timecode.format(frame - 1), format!("{hours:02}:{minutes:02}:59;{last}"), "{fps} fps");
+            }
+        }
+    }
+
+    #[test]
+    fn other_rates_count_without_drop_frame() {
+        let timecode = Timecode::new(25.0).unwrap();
+        assert_eq!(timecode.format(25 * 60), "00:01:00:00");
+        assert_eq!(timecode.at(1000.0), "00:00:01:00");
+        // 23,976 fps ist eine NTSC-Rate, aber kein Vielfaches von 30
+        assert_eq!(Timecode::new(24000.0 / 1001.0).unwrap().format(24 * 60), "00:01:00:00");
+        assert!(Timecode::new(0.0).is_none());
+        assert!(Timecode::new(f64::NAN).is_none());
+    }
+}
@@ -0,0 +1,199 @@
+//! Verfolgung von Gesichtern über aufeinanderfolgende Frames per Überlappung (IoU)
+
+use opencv::core::{Point, Rect};
+use std::collections::VecDeque;
+
+/// Mindestüberlappung, ab der eine Erkennung einer bestehenden Spur zugeordnet wird
+const MIN_IOU: f32 = 0.3;
+
+/// Ein über mehrere Frames verfolgtes Gesicht
+pub struct Track {
+    pub id: u64,
+    pub bbox: Rect,
+    scores: VecDeque<f32>,
+    /// Eintrag, zu dem die Werte in `scores` gehören
+    scored_id: Option<String>,
+    /// Mittelpunkte der letzten Erkennungen, der neueste zuletzt
+    trail: VecDeque<Point>,
+    /// Frames seit der letzten Erkennung
+    missed: u32,
+}
+
+impl Track {
+    fn new(id: u64, bbox: Rect) -> Self {
+        Self {
+            id,
+            bbox,
+            scores: VecDeque::new(),
+            scored_id: None,
+            trail: VecDeque::new(),
+            missed: 0,
+        }
+    }
+
+    /// Nimmt eine neue Ähnlichkeit zum Eintrag `id` auf und liefert den gleitenden Mittelwert der letzten
+    /// `window` Werte. Wechselt der ähnlichste Eintrag, beginnt das Fenster neu: sonst könnten hohe Werte einer
+    /// Person einen anderen, kaum ähnlichen Eintrag über die Schwelle heben.
+    pub fn smooth_score(&mut self, id: &str, score: f32, window: usize) -> f32 {
+        if self.scored_id.as_deref() != Some(id) {
+            self.scores.clear();
+            self.scored_id = Some(id.to_string());
+        }
+        self.scores.push_back(score);
+        while self.scores.len() > window.max(1) {
+            self.scores.pop_front();
+        }
+        self.scores.iter().sum::<f32>() / self.scores.len() as f32
+    }
+
+    /// Verwirft die bisherigen Ähnlichkeiten, z. B. nachdem die Person neu erfasst wurde
+    pub fn reset_scores(&mut self) {
+        self.scores.clear();
+        self.scored_id = None;
+    }
+
+    pub fn trail(&self) -> &VecDeque<Point> {
+        &self.trail
+    }
+}
+
+/// Ordnet Erkennungen eines Frames den Spuren des vorherigen Frames zu
+#[derive(Default)]
+pub struct Tracker {
+    /// Zuerst die Spuren des aktuellen Frames in der Reihenfolge der Erkennungen, danach die gehaltenen
+    tracks: Vec<Track>,
+    detected: usize,
+    next_id: u64,
+    /// Anzahl Frames, die eine Spur ohne Erkennung erhalten bleibt
+    persistence: u32,
+    /// Anzahl der Mittelpunkte, die je Spur als Bewegungspfad gespeichert werden
+    trail_length: usize,
+}
+
+impl Tracker {
+    /// Hält Spuren nach ihrer letzten Erkennung noch `frames` Frames lang, damit kurze Verdeckungen
+    /// weder die Spur noch ihre geglättete Ähnlichkeit zurücksetzen
+    pub fn with_persistence(frames: u32) -> Self {
+        Self {
+            persistence: frames,
+            ..Self::default()
+        }
+    }
+
+    /// Merkt sich je Spur die Mittelpunkte der letzten `points` Erkennungen
+    pub fn with_trail(mut self, points: usize) -> Self {
+        self.trail_length = points;
+        self
+    }
+
+    /// Aktualisiert die Spuren mit den Erkennungen des aktuellen Frames.
+    /// Die zurückgegebenen Spuren haben dieselbe Reihenfolge wie `faces`; nicht mehr gesehene Spuren entfallen,
+    /// sobald sie länger als die Haltedauer fehlen.
+    pub fn update(&mut self, faces: &[Rect]) -> &mut [Track] {
+        // Gierige Zuordnung nach absteigender Überlappung
+        let mut pairs = Vec::new();
+        for (detection, face) in faces.iter().enumerate() {
+            for (index, track) in self.tracks.iter().enumerate() {
+                let overlap = iou(*face, track.bbox);
+                if overlap >= MIN_IOU {
+                    pairs.push((overlap, detection, index));
+                }
+            }
+        }
+        pairs.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut assigned = vec![None; faces.len()];
+        let mut used = vec![false; self.tracks.len()];
+        for (_, detection, index) in pairs {
+            if assigned[detection].is_none() && !used[index] {
+                assigned[detection] = Some(index);
+                used[index] = true;
+            }
+        }
+
+        let mut previous: Vec<Option<Track>> = self.tracks.drain(..).map(Some).collect();
+        for (face, index) in faces.iter().zip(assigned) {
+            let mut track = match index.and_then(|index| previous[index].take()) {
+                Some(track) => track,
+                None => {
+                    self.next_id += 1;
+                    Track::new(self.next_id, *face)
+                }
+            };
+            track.bbox = *face;
+            track.missed = 0;
+            if self.trail_length > 0 {
+                if track.trail.len() == self.trail_length {
+                    track.trail.pop_front();
+                }
+                track.trail.push_back(Point::new(face.x + face.width / 2, face.y + face.height / 2));
+            }
+            self.tracks.push(track);
+        }
+        self.detected = faces.len();
+        for mut track in previous.into_iter().flatten() {
+            if track.missed < self.persistence {
+                track.missed += 1;
+                self.tracks.push(track);
+            }
+        }
+        &mut self.tracks[..self.detected]
+    }
+
+    /// Spuren, die im aktuellen Frame nicht erkannt, aber noch gehalten werden
+    pub fn coasting(&self) -> &[Track] {
+        &self.tracks[self.detected..]
+    }
+
+    /// Alle Spuren, erkannte und gehaltene
+    pub fn tracks(&self) -> &[Track] {
+        &self.tracks
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.tracks.iter().any(|track| track.id == id)
+    }
+}
+
+/// Bewegung zwischen zwei Rahmen desselben Gesichts relativ zur Breite von `a`: der größere Wert aus
+/// Verschiebung des Mittelpunkts und Änderung der Breite
+pub fn motion(a: Rect, b: Rect) -> f32 {
+    let width = a.width.max(1) as f32;
+    let dx = ((b.x + b.width / 2) - (a.x + a.width / 2)) as f32;
+    let dy = ((b.y + b.height / 2) - (a.y + a.height / 2)) as f32;
+    let shift = dx.hypot(dy) / width;
+    let scale = (b.width - a.width).abs() as f32 / width;
+    shift.max(scale)
+}
+
+/// Überlappung zweier Rechtecke (Schnittfläche durch Vereinigungsfläche)
+pub fn iou(a: Rect, b: Rect) -> f32 {
+    let intersection = (a & b).area() as f32;
+    let union = (a.area() + b.area()) as f32 - intersection;
+    if union > 0.0 { intersection / union } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smoothing_window_restarts_when_the_candidate_changes() {
+        let mut track = Track::new(1, Rect::default());
+        assert_eq!(track.smooth_score("a", 1.0, 5), 1.0);
+        assert_eq!(track.smooth_score("a", 0.5, 5), 0.75);
+        // Ein anderer Eintrag erbt die hohen Werte von "a" nicht
+        assert_eq!(track.smooth_score("x", 0.25, 5), 0.25);
+        assert_eq!(track.smooth_score("x", 0.75, 5), 0.5);
+        assert_eq!(track.smooth_score("a", 0.5, 5), 0.5);
+    }
+
+    #[test]
+    fn smoothing_window_drops_the_oldest_scores() {
+        let mut track = Track::new(1, Rect::default());
+        for score in [1.0, 0.0, 0.5, 0.25] {
+            track.smooth_score("a", score, 2);
+        }
+        assert_eq!(track.smooth_score("a", 0.75, 2), 0.5);
+    }
+}